@@ -0,0 +1,158 @@
+//! Allocations and time taken by the handler of a 10 argument method, with and without
+//! `#[rpc(tuple_args)]`. Run with `cargo bench --bench tuple_args`.
+
+#![allow(clippy::too_many_arguments)] // the generated client helpers take each argument
+
+use easy_jsonrpc::{Handler, Params};
+use serde_json::{json, Value};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+// the system allocator, counting the allocations made
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[easy_jsonrpc::rpc(tuple_args)]
+pub trait WideTuple {
+    fn wide(
+        &self,
+        a: u8,
+        b: u16,
+        c: u32,
+        d: u64,
+        e: i8,
+        f: i16,
+        g: i32,
+        h: i64,
+        i: bool,
+        j: bool,
+    ) -> u64;
+}
+
+#[easy_jsonrpc::rpc]
+pub trait Wide {
+    fn wide(
+        &self,
+        a: u8,
+        b: u16,
+        c: u32,
+        d: u64,
+        e: i8,
+        f: i16,
+        g: i32,
+        h: i64,
+        i: bool,
+        j: bool,
+    ) -> u64;
+}
+
+struct WideImpl;
+
+impl WideTuple for WideImpl {
+    fn wide(
+        &self,
+        a: u8,
+        b: u16,
+        c: u32,
+        d: u64,
+        e: i8,
+        f: i16,
+        g: i32,
+        h: i64,
+        i: bool,
+        j: bool,
+    ) -> u64 {
+        let signed = i64::from(e) + i64::from(f) + i64::from(g) + h;
+        u64::from(a) + u64::from(b) + u64::from(c) + d + signed as u64 + i as u64 + j as u64
+    }
+}
+
+impl Wide for WideImpl {
+    fn wide(
+        &self,
+        a: u8,
+        b: u16,
+        c: u32,
+        d: u64,
+        e: i8,
+        f: i16,
+        g: i32,
+        h: i64,
+        i: bool,
+        j: bool,
+    ) -> u64 {
+        WideTuple::wide(self, a, b, c, d, e, f, g, h, i, j)
+    }
+}
+
+const CALLS: usize = 100_000;
+
+// the allocations made per call and the nanoseconds taken per call, handling params
+fn measure<H: Handler + ?Sized>(handler: &H, params: &Params) -> (f64, f64) {
+    let calls: Vec<Params> = (0..CALLS).map(|_| params.clone()).collect();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for params in calls {
+        handler.handle("wide", params).unwrap();
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    (
+        allocations as f64 / CALLS as f64,
+        elapsed.as_nanos() as f64 / CALLS as f64,
+    )
+}
+
+fn main() {
+    let args = json!([1, 2, 3, 4, -1, -2, -3, -4, true, false]);
+    let names = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+    let named: serde_json::Map<String, Value> = names
+        .iter()
+        .map(|name| name.to_string())
+        .zip(args.as_array().unwrap().clone())
+        .collect();
+    let params = [
+        (
+            "positional",
+            Params::Positional(args.as_array().unwrap().clone()),
+        ),
+        ("named", Params::Named(named)),
+    ];
+    println!(
+        "{:<12} {:<14} {:>12} {:>10}",
+        "params", "handler", "allocations", "ns"
+    );
+    for (kind, params) in &params {
+        let tuple_args = measure(&WideImpl as &dyn WideTuple, params);
+        let per_argument = measure(&WideImpl as &dyn Wide, params);
+        for (name, (allocations, nanos)) in
+            [("tuple_args", tuple_args), ("per argument", per_argument)]
+        {
+            println!(
+                "{:<12} {:<14} {:>12.2} {:>10.0}",
+                kind, name, allocations, nanos
+            );
+        }
+    }
+}
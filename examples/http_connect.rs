@@ -50,6 +50,7 @@ fn post(addr: &SocketAddrV6, body: &Value) -> Result<Value, reqwest::Error> {
 
 #[derive(Debug)]
 enum RpcErr {
+    #[allow(dead_code)] // only read through Debug
     Http(reqwest::Error),
     InvalidResponse,
 }
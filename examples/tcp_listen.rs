@@ -4,7 +4,6 @@ mod common;
 use crate::common::create_frob_server;
 use easy_jsonrpc::{Handler, MaybeReply};
 use serde::Deserialize;
-use serde_json;
 use std::io::Read;
 use std::net::TcpListener;
 use std::time::Duration;
@@ -4,23 +4,32 @@
 #![recursion_limit = "256"]
 
 extern crate proc_macro;
-use heck::SnakeCase;
-use proc_macro2::{self, Span, TokenStream};
+use heck::{CamelCase, ShoutySnakeCase, SnakeCase};
+use proc_macro2::{self, Span, TokenStream, TokenTree};
 use quote::{quote, quote_spanned};
 use syn::{
-    parse_macro_input, punctuated::Punctuated, spanned::Spanned, token::Paren, ArgSelfRef, FnArg,
-    FnDecl, Ident, ItemTrait, MethodSig, Pat, PatIdent, ReturnType, TraitItem, Type, TypeTuple,
+    parse_macro_input, punctuated::Punctuated, spanned::Spanned, token::Paren, Attribute, Data,
+    DeriveInput, Expr, ExprLit, ExprUnary, FnArg, GenericArgument, GenericParam, Ident, ItemTrait,
+    Lit, LitStr, Meta, MetaNameValue, Pat, PatIdent, PatType, Path, PathArguments, Receiver,
+    ReturnType, Signature, Token, TraitItem, Type, TypeParamBound, TypePath, TypeReference,
+    TypeTuple, UnOp,
 };
 
+// comma separated list of options, as found in #[rpc(option, option, ..)]
+type Options = Punctuated<Meta, Token![,]>;
+
 /// Generate a Handler implementation and client helpers for trait input.
 ///
-/// Example usage:
+/// If any method in the trait takes `&mut self`, a HandlerMut implementation is generated instead
+/// of a Handler implementation.
 ///
 /// ```rust,no_run
 /// #[rpc]
 /// trait MyApi {
 ///     fn my_method(&self, a: usize);
 ///     fn my_other_method(&self) -> bool {}
+///     #[rpc(name = "myApi.third")]
+///     fn third(&self);
 /// }
 /// ```
 ///
@@ -41,206 +50,3036 @@ use syn::{
 ///     fn my_other_method() -> Result<BoundMethod<'static, bool>, ArgSerializeError> {
 ///          ..
 ///     }
+///
+///     fn third() -> Result<BoundMethod<'static, ()>, ArgSerializeError> {
+///          ..
+///     }
+/// }
+/// ```
+///
+/// # Generated items
+///
+/// - The helper enum, `my_api` above, has a client helper per method, along with a
+///   `notify_my_method` counterpart building a notification instead. `rpc_descriptors()` lists the
+///   name and the argument names and types of each method, see `easy_jsonrpc::MethodDescriptor`.
+///   `method_doc(name)` returns the doc comment of a method, `is_idempotent(name)` and
+///   `deprecation(name)` look up the options of the same name.
+/// - The module `my_api_methods` holds a constant for the name of each method, named in
+///   SHOUTY_SNAKE_CASE, e.g. `my_api_methods::THIRD == "myApi.third"`.
+///
+/// Methods returning `Result<T, E>` respond with a jsonrpc error object when they return `Err`.
+/// `E` must implement `easy_jsonrpc::IntoRpcError`, or `easy_jsonrpc::RpcErrorCode` to pick just
+/// the error code and data. Methods returning `easy_jsonrpc::Value` or `serde_json::Value`,
+/// directly or as the `Ok` type, have the value used as the result as is. The type must be
+/// written out as such a path, a `Value` imported with `use` is serialized like any other type.
+///
+/// # Trait requirements
+///
+/// The handler is implemented for `dyn Trait`, so the trait must stay object safe, and client
+/// helpers need nameable types to deserialize results into.
+///
+/// - The trait may only contain methods, associated consts and types are rejected.
+/// - Arguments and return values must have concrete types, `-> impl Serialize` is rejected.
+///   Return types that don't implement `Serialize` are reported at the return type, unless they
+///   name lifetimes or `Self`.
+/// - Methods with type or const parameters are rejected, declare a method per type instead, e.g.
+///   `fn echo_string(&self, v: String)`. The generic method may stay in the trait when it lists
+///   them with `#[rpc(instantiate(echo_string = "echo::<String>"))]`. It is then bound by
+///   `Self: Sized` and not served itself, and each listed method is checked to have the
+///   signature of its instantiation. Implementations usually forward them to the generic method.
+/// - Traits may have lifetime parameters, e.g. `trait Store<'a>`, as long as only skipped methods
+///   mention them. Type and const parameters are rejected, as are lifetime parameters of
+///   rpc_async traits.
+/// - Method names starting with `rpc.` are reserved for extensions to jsonrpc and are rejected,
+///   see `allow_reserved` and `reserved_prefixes`.
+///
+/// `#[cfg(..)]` attributes of a method are repeated on everything generated for it, so a method
+/// compiled out of the trait is neither handled nor listed. The hook methods can't be compiled
+/// out.
+///
+/// # Trait options
+///
+/// Given as `#[rpc(option, ..)]`.
+///
+/// | Option | Effect |
+/// |---|---|
+/// | `async` | Generates an AsyncHandler for a trait of async methods, see [rpc_async](attr.rpc_async.html). |
+/// | `prefix = "wallet_"` | Prepends the prefix to the name of every method not given a `name`. |
+/// | `reserved_prefixes("sys.")` | Reserves more prefixes the way `rpc.` is reserved. |
+/// | `embed_errors` | Serializes a returned `Result` as a whole into the `result` member, as older versions did. |
+/// | `describe` | Answers calls to `rpc.describe` with `rpc_descriptors()`, unless the trait has a method of that name. |
+/// | `openrpc` | Generates `openrpc_schema()`, an [OpenRPC](https://spec.open-rpc.org/) document. Schemas are guessed from the names of the types. |
+/// | `client` | Generates `MyApiClient<Tr>(pub Tr)`, calling the methods over an `easy_jsonrpc::Transport`. |
+/// | `request_enum` | Generates `MyApiRequest`, an enum with a variant per method holding its arguments. |
+/// | `supertraits(Accounts)` | Also serves the methods of the listed supertraits. |
+/// | `tuple_args` | Deserializes the arguments of a call in a single pass, as a tuple. |
+/// | `lenient_numbers` | Applies `lenient_numbers` to every method. |
+///
+/// The client methods return `Result<R, easy_jsonrpc::ClientError<Tr::Error>>`, with R the return
+/// type of the method, or T for `Result<T, E>`. ClientError tells apart transport failures, errors
+/// returned by the server and results that fail to deserialize.
+///
+/// ```
+/// # use easy_jsonrpc::{ClientError, InMemoryTransport};
+/// #[easy_jsonrpc::rpc(client)]
+/// pub trait Adder {
+///     fn checked_add(&self, a: u8, b: u8) -> Result<u8, String>;
+/// }
+///
+/// struct AdderImpl;
+/// impl Adder for AdderImpl {
+///     fn checked_add(&self, a: u8, b: u8) -> Result<u8, String> {
+///         a.checked_add(b).ok_or_else(|| "overflow".to_string())
+///     }
+/// }
+///
+/// let client = AdderClient(InMemoryTransport::new(&AdderImpl as &dyn Adder));
+/// assert_eq!(client.checked_add(1, 2), Ok(3));
+/// assert!(matches!(client.checked_add(255, 1), Err(ClientError::Rpc(_))));
+/// ```
+///
+/// `MyApiRequest::parse(method, params)` parses the params of a call once, `dispatch(handler)`
+/// calls the trait method and `method()` names it. It (de)serializes as
+/// `{"method": .., "params": {..}}`. It leaves out the methods of supertraits, and is not
+/// supported by rpc_async, with_id methods or context arguments.
+///
+/// Each supertrait listed in `supertraits(..)` must itself be annotated with `#[rpc]`, with its
+/// helper enum in scope. Calls go to the trait's own methods first, then to the supertraits in
+/// order. Method names shared with a supertrait are rejected. A supertrait with a HandlerMut can
+/// only be served by a trait with one too.
+///
+/// ```rust,no_run
+/// #[rpc]
+/// trait Accounts {
+///     fn balance(&self) -> u64;
+/// }
+///
+/// #[rpc(supertraits(Accounts))]
+/// trait Wallet: Accounts {
+///     fn send(&self, to: String, amount: u64);
+/// }
+/// ```
+///
+/// `tuple_args` still deserializes one argument at a time for methods with `default` arguments,
+/// `lenient_numbers` or `base64`, and for methods of more than 16 arguments, the widest tuples
+/// serde deserializes.
+///
+/// # Method options
+///
+/// | Option | Effect |
+/// |---|---|
+/// | `name = "wallet.getBalance"` | Exposes the method under this name rather than its rust identifier. |
+/// | `allow_reserved` | Allows a name starting with a reserved prefix, e.g. `rpc.discover`. |
+/// | `skip` | Keeps the method out of the jsonrpc api, for helper methods. Takes no other option. |
+/// | `instantiate(echo_string = "echo::<String>")` | Lists the methods serving a generic method, see above. |
+/// | `with_id` | Passes the id of the request, an `easy_jsonrpc::Id`, as the first argument after self. Notifications pass `Id::Null`. |
+/// | `params = "named"` or `"positional"` | Rejects calls passing the other kind of parameters. Passing none is accepted. |
+/// | `params_struct` | Deserializes the arguments into a generated struct, e.g. `WrappingAddParams`, which also builds requests. |
+/// | `lenient_numbers` | Also accepts strings holding a number, e.g. `"9007199254740993"`, for arguments of primitive number type. |
+/// | `base64_result` | Returns `Vec<u8>` as a base64 string, the client helper returns an `easy_jsonrpc::Base64Bytes`. |
+/// | `idempotent` | Marks the method safe to retry, see `BoundMethod::is_idempotent`. No effect on the server. |
+/// | `deprecated` or `deprecated = "use v2_add"` | Records the note in the descriptor and the OpenRPC document. With "tracing", the first call logs a warning. |
+///
+/// The struct of a `params_struct` method has a public field per argument. Named parameters map
+/// to fields by name, positional ones by order, other parameters are rejected.
+///
+/// # Argument options
+///
+/// | Option | Effect |
+/// |---|---|
+/// | `default` | The argument may be omitted, `Default::default()` is passed. Must come after the required arguments. |
+/// | `rename = "maxAmount"` | Looks the argument up under this name in named parameters. |
+/// | `flatten` | Takes the named parameters as a whole, like serde's `flatten`. Must be the only argument passed by clients. |
+/// | `rest` | Takes the named parameters no other argument takes, as a dictionary. Must be the last argument. |
+/// | `context` | Receives a request context from the caller of the handler, see below. |
+/// | `base64` | Receives `Vec<u8>` or `&[u8]` sent as a base64 string. `base64 = "url_safe"` picks the url safe alphabet. |
+///
+/// ```rust,no_run
+/// #[rpc]
+/// trait Volumes {
+///     fn list(&self, dir: String, #[rpc(default)] verbose: bool) -> Vec<String>;
+///     // called as {"method": "create", "params": {"name": "data", "size": 10}}
+///     fn create(&self, #[rpc(flatten)] req: CreateRequest) -> bool;
+///     // called as {"method": "call", "params": {"name": "resize", "width": 10}}
+///     fn call(&self, name: String, #[rpc(rest)] extra: HashMap<String, Value>) -> Value;
+/// }
+/// ```
+///
+/// Positional parameters pass a flattened argument as their single element, and a rest argument
+/// as their last element, or leave it out. Bytes are encoded with padding in the standard
+/// alphabet and without in the url safe one, either is accepted when decoding.
+///
+/// The context argument comes first, after the id for with_id methods, and must be a reference.
+/// All context arguments of a trait have the same type `&C`, and the trait implements
+/// `easy_jsonrpc::ContextHandler<C>` instead of Handler. Not supported with `&mut self` methods or
+/// by rpc_async.
+///
+/// Arguments may be references: `&str` is deserialized as a `String` and `&[T]` as a `Vec<T>`,
+/// at no extra cost. Only the outermost reference is supported, take `&[String]` rather than
+/// `&[&str]`. `Option<T>` arguments may be left out of named parameters, positional parameters
+/// still list them as `null` unless marked `default`.
+///
+/// # Hooks
+///
+/// These methods are called by the handler and are not exposed as jsonrpc methods. A trait has at
+/// most one of each.
+///
+/// | Option | Signature | Called |
+/// |---|---|---|
+/// | `fallback` | `fn(&self, method: String, params: Params) -> Result<Value, Error>` | for method names the trait does not handle, rather than answering "Method not found" |
+/// | `authorize` | `fn(&self, method: &str) -> Result<(), Error>` | before each call to a method marked `auth`, an `Err` answers the call |
+/// | `before_dispatch` | `fn(&self, method: &str) -> Result<(), Error>` | before every call, notifications included, an `Err` answers the call |
+///
+/// ```rust,no_run
+/// #[rpc]
+/// trait MyApi {
+///     #[rpc(authorize)]
+///     fn authorize(&self, method: &str) -> Result<(), Error>;
+///     #[rpc(auth)]
+///     fn shutdown(&self);
+///     fn status(&self) -> String;
+/// }
+/// ```
+///
+/// `before_dispatch` implements `Handler::before_dispatch`, e.g. for a maintenance mode. It is not
+/// supported by rpc_async or with context arguments.
+#[proc_macro_attribute]
+pub fn rpc(
+    args: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let (asyncness, args) = take_async_option(args.into());
+    if asyncness {
+        return rpc_async(args.into(), item);
+    }
+    let args = proc_macro::TokenStream::from(args);
+    let args = parse_macro_input!(args with Options::parse_terminated);
+    let mut trait_def = parse_macro_input!(item as ItemTrait);
+    let impls = raise_if_err(TraitOptions::parse(&args).and_then(|options| {
+        let methods = trait_methods(&trait_def, &options)?;
+        check_trait_generics(&trait_def, &methods, false)?;
+        reject_async_methods(&methods)?;
+        let instantiations = impl_instantiations(&trait_def, &methods)?;
+        let server_impl = impl_server(&trait_def, &methods, &options)?;
+        let client_impl = impl_client(&trait_def, &methods, &options)?;
+        let request_enum = if options.request_enum {
+            impl_request_enum(&trait_def, &methods)?
+        } else {
+            quote! {}
+        };
+        Ok(quote! {
+            #instantiations
+            #server_impl
+            #client_impl
+            #request_enum
+        })
+    }));
+    strip_rpc_attrs(&mut trait_def);
+    proc_macro::TokenStream::from(quote! {
+        #trait_def
+        #impls
+    })
+}
+
+/// Generate an AsyncHandler implementation and client helpers for a trait containing async
+/// methods. Requires the "async" feature of easy-jsonrpc. `#[rpc(async)]` is the same as
+/// `#[rpc_async]`.
+///
+/// Every method in the trait must be async and take `&self`. The trait must have `Sync` as a
+/// supertrait. The trait is desugared with async_trait so implementations must be annotated with
+/// `#[easy_jsonrpc::async_trait]`. Method options are the same as for [rpc](attr.rpc.html).
+///
+/// Example usage:
+///
+/// ```rust,no_run
+/// #[rpc_async]
+/// trait MyApi: Sync {
+///     async fn my_method(&self, a: usize) -> bool;
+/// }
+///
+/// #[easy_jsonrpc::async_trait]
+/// impl MyApi for MyApiImpl {
+///     async fn my_method(&self, a: usize) -> bool {
+///         ..
+///     }
+/// }
+/// ```
+///
+/// Generated code:
+///
+/// ```
+/// impl AsyncHandler for dyn MyApi {
+///    ..
+/// }
+///
+/// pub enum my_api {}
+///
+/// impl my_api {
+///     fn my_method(arg0: usize) -> Result<BoundMethod<'static, bool>, ArgSerializeError> {
+///          ..
+///     }
 /// }
 /// ```
 #[proc_macro_attribute]
-pub fn rpc(_: proc_macro::TokenStream, item: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let trait_def = parse_macro_input!(item as ItemTrait);
-    let server_impl = raise_if_err(impl_server(&trait_def));
-    let client_impl = raise_if_err(impl_client(&trait_def));
+pub fn rpc_async(
+    args: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let args = parse_macro_input!(args with Options::parse_terminated);
+    let mut trait_def = parse_macro_input!(item as ItemTrait);
+    let impls = raise_if_err(TraitOptions::parse(&args).and_then(|options| {
+        let methods = trait_methods(&trait_def, &options)?;
+        check_trait_generics(&trait_def, &methods, true)?;
+        check_async_trait(&trait_def, &methods)?;
+        if options.request_enum {
+            return Err(
+                Rejection::create(trait_def.ident.span(), Reason::UnsupportedRequestEnum).into(),
+            );
+        }
+        let instantiations = impl_instantiations(&trait_def, &methods)?;
+        let server_impl = impl_async_server(&trait_def, &methods, &options)?;
+        let client_impl = impl_client(&trait_def, &methods, &options)?;
+        Ok(quote! {
+            #instantiations
+            #server_impl
+            #client_impl
+        })
+    }));
+    strip_rpc_attrs(&mut trait_def);
     proc_macro::TokenStream::from(quote! {
+        #[easy_jsonrpc::async_trait]
         #trait_def
-        #server_impl
-        #client_impl
+        #impls
+    })
+}
+
+/// Implement `easy_jsonrpc::RpcErrorCode` for an error enum, picking the code of each variant
+/// from its `#[rpc_error(code = ..)]` attribute. The error message is the `Display` output of the
+/// error, the error has no data.
+///
+/// `error_codes()` lists the code of each variant along with a label, the name of the variant
+/// unless given with `#[rpc_error(label = "...")]`.
+///
+/// ```rust,no_run
+/// #[derive(Debug, RpcErrorCode)]
+/// enum WalletError {
+///     #[rpc_error(code = -32010, label = "Insufficient funds")]
+///     InsufficientFunds { needed: u64 },
+///     #[rpc_error(code = -32011)]
+///     Locked,
+/// }
+/// ```
+#[proc_macro_derive(RpcErrorCode, attributes(rpc_error))]
+pub fn derive_rpc_error_code(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    proc_macro::TokenStream::from(raise_if_err(impl_rpc_error_code(&input)))
+}
+
+fn impl_rpc_error_code(input: &DeriveInput) -> Result<TokenStream, Rejections> {
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _ => return Err(Rejection::create(input.ident.span(), Reason::ErrorCodeNotEnum).into()),
+    };
+    let codes = partition(variants.iter().map(|variant| {
+        let mut code = None;
+        let mut label = variant.ident.to_string();
+        let options = variant
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("rpc_error"))
+            .map(|attr| {
+                attr.parse_args_with(Options::parse_terminated)
+                    .map_err(|e| Rejection::create(e.span(), Reason::MalformedAttribute).into())
+            });
+        for options in partition(options)? {
+            partition(options.iter().map(|option| match option {
+                Meta::NameValue(MetaNameValue { path, value, .. }) if path.is_ident("code") => {
+                    code = Some(integer_literal(value)?);
+                    Ok(())
+                }
+                Meta::NameValue(MetaNameValue { path, value, .. }) if path.is_ident("label") => {
+                    match value {
+                        Expr::Lit(ExprLit {
+                            lit: Lit::Str(name),
+                            ..
+                        }) => {
+                            label = name.value();
+                            Ok(())
+                        }
+                        other => Err(
+                            Rejection::create(other.span(), Reason::ExpectedStringLiteral).into(),
+                        ),
+                    }
+                }
+                other => {
+                    Err(Rejection::create(other.span(), Reason::UnknownErrorCodeOption).into())
+                }
+            }))?;
+        }
+        match code {
+            Some(code) => Ok((&variant.ident, code, label)),
+            None => Err(Rejection::create(variant.ident.span(), Reason::MissingErrorCode).into()),
+        }
+    }))?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let arms = codes.iter().map(|(variant, code, _)| {
+        quote! { Self::#variant { .. } => easy_jsonrpc::ErrorCode::from(#code), }
+    });
+    let listed = codes
+        .iter()
+        .map(|(_, code, label)| quote! { (#code, #label) });
+    Ok(quote! {
+        impl #impl_generics easy_jsonrpc::RpcErrorCode for #name #ty_generics #where_clause {
+            fn code(&self) -> easy_jsonrpc::ErrorCode {
+                match self {
+                    #(#arms)*
+                }
+            }
+
+            fn error_codes() -> &'static [(i64, &'static str)] {
+                &[#(#listed),*]
+            }
+        }
+    })
+}
+
+// the value of an integer literal, which may be negated
+fn integer_literal(expr: &Expr) -> Result<i64, Rejections> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int
+            .base10_parse()
+            .map_err(|_| Rejection::create(int.span(), Reason::ExpectedIntegerLiteral).into()),
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => integer_literal(expr).map(|value| -value),
+        other => Err(Rejection::create(other.span(), Reason::ExpectedIntegerLiteral).into()),
+    }
+}
+
+// Remove the option `async` from the options passed to the rpc macro, returning whether it was
+// present. Meta can't represent keywords, so it is taken out before the other options are parsed.
+fn take_async_option(args: TokenStream) -> (bool, TokenStream) {
+    let mut options: Vec<Vec<TokenTree>> = vec![Vec::new()];
+    for token in args {
+        match &token {
+            TokenTree::Punct(punct) if punct.as_char() == ',' => options.push(Vec::new()),
+            _ => options
+                .last_mut()
+                .expect("options is never empty")
+                .push(token),
+        }
+    }
+    let is_async = |option: &Vec<TokenTree>| match option.as_slice() {
+        [TokenTree::Ident(ident)] => ident == "async",
+        _ => false,
+    };
+    let asyncness = options.iter().any(is_async);
+    let rest = options
+        .into_iter()
+        .filter(|option| !option.is_empty() && !is_async(option))
+        .map(|option| option.into_iter().collect::<TokenStream>());
+    (asyncness, quote! { #(#rest),* })
+}
+
+// if Ok, return token stream, else report error
+fn raise_if_err(res: Result<TokenStream, Rejections>) -> TokenStream {
+    match res {
+        Ok(stream) => stream,
+        Err(rej) => rej.raise(),
+    }
+}
+
+// generate a Handler implementation for &dyn Trait, or a HandlerMut implementation if any method
+// in the trait takes &mut self
+fn impl_server(
+    tr: &ItemTrait,
+    methods: &[RpcMethod],
+    options: &TraitOptions,
+) -> Result<TokenStream, Rejections> {
+    let trait_name = &tr.ident;
+    // only lifetime parameters get this far, see check_trait_generics
+    let (impl_generics, ty_generics, where_clause) = tr.generics.split_for_impl();
+    let mutable = methods.iter().any(|method| takes_mut_self(method.sig));
+    let kind = if mutable {
+        HandlerKind::Mutable
+    } else {
+        HandlerKind::Immutable
+    };
+    let body = handler_body(trait_name, methods, options, kind, false)?;
+    let notification_body = handler_body(trait_name, methods, options, kind, true)?;
+    let list = methods_body(methods, options, kind);
+    let before_dispatch = methods.iter().find(|method| method.before_dispatch);
+
+    if let Some(context) = context_type(methods)? {
+        if mutable {
+            return Err(Rejection::create(context.span(), Reason::ContextMutHandler).into());
+        }
+        if let Some(method) = before_dispatch {
+            return Err(
+                Rejection::create(method.name_span, Reason::UnsupportedBeforeDispatch).into(),
+            );
+        }
+        return Ok(quote! {
+            impl #impl_generics easy_jsonrpc::ContextHandler<#context>
+                for dyn #trait_name #ty_generics #where_clause
+            {
+                fn handle_with_ctx(&self, id: &easy_jsonrpc::Id, method: &str,
+                                   params: easy_jsonrpc::Params, ctx: &#context)
+                                   -> Result<easy_jsonrpc::Value, easy_jsonrpc::Error> {
+                    #body
+                }
+
+                fn methods_with_ctx(&self) -> Vec<&'static str> {
+                    #list
+                }
+            }
+        });
+    }
+
+    // a before_dispatch method overrides the hook of the same name
+    let before_dispatch = before_dispatch.map(|method| {
+        let method_name = &method.sig.ident;
+        let hook = if mutable {
+            quote! { before_dispatch_mut }
+        } else {
+            quote! { before_dispatch }
+        };
+        quote! {
+            fn #hook(&self, method: &str) -> Result<(), easy_jsonrpc::Error> {
+                <dyn #trait_name>::#method_name(self, method)
+            }
+        }
+    });
+
+    Ok(if mutable {
+        quote! {
+            impl #impl_generics easy_jsonrpc::HandlerMut for dyn #trait_name #ty_generics
+                #where_clause
+            {
+                fn handle_mut(&mut self, method: &str, params: easy_jsonrpc::Params)
+                              -> Result<easy_jsonrpc::Value, easy_jsonrpc::Error> {
+                    self.handle_mut_with_id(&easy_jsonrpc::Id::Null, method, params)
+                }
+
+                fn handle_mut_with_id(&mut self, id: &easy_jsonrpc::Id, method: &str,
+                                      params: easy_jsonrpc::Params)
+                                      -> Result<easy_jsonrpc::Value, easy_jsonrpc::Error> {
+                    #body
+                }
+
+                fn handle_notification_mut(&mut self, method: &str, params: easy_jsonrpc::Params)
+                                           -> Result<easy_jsonrpc::Value, easy_jsonrpc::Error> {
+                    #[allow(unused_variables)]
+                    let id = &easy_jsonrpc::Id::Null;
+                    #notification_body
+                }
+
+                fn methods_mut(&self) -> Vec<&'static str> {
+                    #list
+                }
+
+                #before_dispatch
+            }
+        }
+    } else {
+        quote! {
+            impl #impl_generics easy_jsonrpc::Handler for dyn #trait_name #ty_generics
+                #where_clause
+            {
+                fn handle(&self, method: &str, params: easy_jsonrpc::Params)
+                          -> Result<easy_jsonrpc::Value, easy_jsonrpc::Error> {
+                    self.handle_with_id(&easy_jsonrpc::Id::Null, method, params)
+                }
+
+                fn handle_with_id(&self, id: &easy_jsonrpc::Id, method: &str,
+                                  params: easy_jsonrpc::Params)
+                                  -> Result<easy_jsonrpc::Value, easy_jsonrpc::Error> {
+                    #body
+                }
+
+                fn handle_notification(&self, method: &str, params: easy_jsonrpc::Params)
+                                       -> Result<easy_jsonrpc::Value, easy_jsonrpc::Error> {
+                    #[allow(unused_variables)]
+                    let id = &easy_jsonrpc::Id::Null;
+                    #notification_body
+                }
+
+                fn methods(&self) -> Vec<&'static str> {
+                    #list
+                }
+
+                #before_dispatch
+            }
+        }
+    })
+}
+
+// the tokens passing the request context to method, if it takes one
+fn context_arg_token(method: &RpcMethod) -> Result<TokenStream, Rejections> {
+    Ok(match method.context_arg()? {
+        Some(_) => quote! { ctx, },
+        None => quote! {},
+    })
+}
+
+// The type of the request context taken by methods with a #[rpc(context)] argument, if any. All
+// context arguments must have the same type.
+fn context_type<'a>(methods: &[RpcMethod<'a>]) -> Result<Option<&'a Type>, Rejections> {
+    let mut context_types = Vec::new();
+    for method in methods {
+        if let Some(RpcArg {
+            ty: Type::Reference(reference),
+            ..
+        }) = method.context_arg()?
+        {
+            context_types.push(&*reference.elem);
+        }
+    }
+    let first = match context_types.first() {
+        Some(first) => *first,
+        None => return Ok(None),
+    };
+    partition(context_types.iter().map(|ty| {
+        if quote!(#ty).to_string() == quote!(#first).to_string() {
+            Ok(())
+        } else {
+            Err(Rejection::create(ty.span(), Reason::MismatchedContextTypes).into())
+        }
+    }))?;
+    Ok(Some(first))
+}
+
+// Generate the match dispatching a call to the trait method it names. For notifications, whose
+// results are dropped, the values returned by methods are not serialized and the result is null.
+fn handler_body(
+    trait_name: &Ident,
+    methods: &[RpcMethod],
+    options: &TraitOptions,
+    kind: HandlerKind,
+    notification: bool,
+) -> Result<TokenStream, Rejections> {
+    let authorize = match methods.iter().find(|method| method.authorize) {
+        Some(authorize) => {
+            let method_name = &authorize.sig.ident;
+            let await_call = match authorize.sig.asyncness {
+                Some(_) => quote! { .await },
+                None => quote! {},
+            };
+            let ctx_arg = context_arg_token(authorize)?;
+            Some(quote! { <dyn #trait_name>::#method_name(self, #ctx_arg method) #await_call?; })
+        }
+        None => None,
+    };
+    let handlers = jsonrpc_methods(methods).map(|method| {
+        let method_literal = &method.name;
+        let cfg = &method.cfg;
+        let method_return_type_span = return_type_span(method.sig);
+        let handler = add_handler(trait_name, method)?;
+        // guarded methods are only called once the authorize method lets them through
+        let guard = match &authorize {
+            Some(authorize) if method.auth => authorize.clone(),
+            _ => quote! {},
+        };
+        let warn_deprecated = warn_deprecated(method);
+        let handler = match method.ok_type {
+            Some(_) => quote_spanned! {
+                method_return_type_span =>
+                    match #handler {
+                        Ok(ok) => ok,
+                        Err(err) => return Err(easy_jsonrpc::IntoRpcError::into_error(err)),
+                    }
+            },
+            None => handler,
+        };
+        let result_type = method.ok_type.or(match &method.sig.output {
+            ReturnType::Type(_, typ) => Some(&**typ),
+            ReturnType::Default => None,
+        });
+        let assert_serialize = result_type.map(assert_serialize);
+        // values returned as json already are used as the result as is
+        let try_serialize = match result_type {
+            _ if method.base64_result.is_some() => serialize_base64_result(method),
+            Some(typ) if is_json_value(typ) => quote! { Ok(result) },
+            _ => quote_spanned! {
+                method_return_type_span =>
+                    easy_jsonrpc::try_serialize(&result).map_err(easy_jsonrpc::Error::from)
+            },
+        };
+        if notification {
+            return Ok(quote! { #cfg #method_literal => {
+                #guard
+                #warn_deprecated
+                let _ = #handler;
+                Ok(easy_jsonrpc::Value::Null)
+            }});
+        }
+        Ok(quote! { #cfg #method_literal => {
+            #guard
+            #warn_deprecated
+            let result = #handler;
+            #assert_serialize
+            #try_serialize
+        }})
+    });
+    let handlers: Vec<TokenStream> = partition(handlers)?;
+    // methods not defined by this trait are forwarded to the first supertrait that handles them
+    let supertrait_handlers = options.supertraits.iter().map(|supertrait| {
+        let helper = helper_path(supertrait);
+        let delegate = match kind {
+            HandlerKind::Immutable if notification => quote! {
+                easy_jsonrpc::Handler::handle_notification(self as &dyn #supertrait, method, params)
+            },
+            HandlerKind::Mutable if notification => quote! {
+                easy_jsonrpc::HandlerMut::handle_notification_mut(
+                    self as &mut dyn #supertrait, method, params
+                )
+            },
+            HandlerKind::Immutable => quote! {
+                easy_jsonrpc::Handler::handle_with_id(self as &dyn #supertrait, id, method, params)
+            },
+            HandlerKind::Mutable => quote! {
+                easy_jsonrpc::HandlerMut::handle_mut_with_id(
+                    self as &mut dyn #supertrait, id, method, params
+                )
+            },
+            HandlerKind::Async => quote! {
+                easy_jsonrpc::AsyncHandler::handle_async_with_id(
+                    self as &dyn #supertrait, id, method, params
+                ).await
+            },
+        };
+        quote! { _ if #helper::handles_method(method) => #delegate }
+    });
+    let fallback = match methods.iter().find(|method| method.fallback) {
+        Some(fallback) => {
+            let method_name = &fallback.sig.ident;
+            let await_call = match fallback.sig.asyncness {
+                Some(_) => quote! { .await },
+                None => quote! {},
+            };
+            let ctx_arg = context_arg_token(fallback)?;
+            quote! {
+                <dyn #trait_name>::#method_name(self, #ctx_arg method.to_owned(), params)
+                    #await_call
+            }
+        }
+        None => quote! { Err(easy_jsonrpc::method_not_found(method)) },
+    };
+    let describe = if options.describe && notification {
+        quote! { "rpc.describe" => Ok(easy_jsonrpc::Value::Null), }
+    } else if options.describe {
+        let helper = Ident::new(&trait_name.to_string().to_snake_case(), trait_name.span());
+        quote! {
+            "rpc.describe" => easy_jsonrpc::try_serialize(&#helper::rpc_descriptors())
+                .map_err(easy_jsonrpc::Error::from),
+        }
+    } else {
+        quote! {}
+    };
+    Ok(quote! {
+        match method {
+            #(#handlers,)*
+            #describe
+            #(#supertrait_handlers,)*
+            _ => #fallback,
+        }
     })
 }
 
-// if Ok, return token stream, else report error
-fn raise_if_err(res: Result<TokenStream, Rejections>) -> TokenStream {
-    match res {
-        Ok(stream) => stream,
-        Err(rej) => rej.raise(),
-    }
+// methods callable by jsonrpc clients, the fallback and authorize methods are only called by the
+// handler
+fn jsonrpc_methods<'a, 'b>(
+    methods: &'b [RpcMethod<'a>],
+) -> impl Iterator<Item = &'b RpcMethod<'a>> {
+    methods
+        .iter()
+        .filter(|method| !method.fallback && !method.authorize && !method.before_dispatch)
+}
+
+// generate an expression listing the names of the methods handled, including those of supertraits
+fn methods_body(methods: &[RpcMethod], options: &TraitOptions, kind: HandlerKind) -> TokenStream {
+    let describe = if options.describe {
+        Some(quote! { "rpc.describe" })
+    } else {
+        None
+    };
+    let names = jsonrpc_methods(methods)
+        .map(|method| {
+            let (cfg, name) = (&method.cfg, &method.name);
+            quote! { #cfg #name }
+        })
+        .chain(describe);
+    let supertrait_methods = options.supertraits.iter().map(|supertrait| match kind {
+        HandlerKind::Immutable => {
+            quote! { easy_jsonrpc::Handler::methods(self as &dyn #supertrait) }
+        }
+        HandlerKind::Mutable => {
+            quote! { easy_jsonrpc::HandlerMut::methods_mut(self as &dyn #supertrait) }
+        }
+        HandlerKind::Async => {
+            quote! { easy_jsonrpc::AsyncHandler::methods_async(self as &dyn #supertrait) }
+        }
+    });
+    quote! {{
+        #[allow(unused_mut)]
+        let mut methods: Vec<&'static str> = vec![#(#names),*];
+        #(methods.extend(#supertrait_methods);)*
+        methods
+    }}
+}
+
+// the kind of handler trait being implemented
+#[derive(Clone, Copy)]
+enum HandlerKind {
+    Immutable, // Handler
+    Mutable,   // HandlerMut
+    Async,     // AsyncHandler
+}
+
+// path to the helper enum generated for the trait at path
+fn helper_path(trait_path: &Path) -> Path {
+    let mut path = trait_path.clone();
+    if let Some(last) = path.segments.last_mut() {
+        last.ident = Ident::new(&last.ident.to_string().to_snake_case(), last.ident.span());
+    }
+    path
+}
+
+// generate an AsyncHandler implementation for &dyn Trait
+fn impl_async_server(
+    tr: &ItemTrait,
+    methods: &[RpcMethod],
+    options: &TraitOptions,
+) -> Result<TokenStream, Rejections> {
+    let trait_name = &tr.ident;
+    if let Some(context) = context_type(methods)? {
+        return Err(Rejection::create(context.span(), Reason::ContextAsyncHandler).into());
+    }
+    if let Some(method) = methods.iter().find(|method| method.before_dispatch) {
+        return Err(Rejection::create(method.name_span, Reason::UnsupportedBeforeDispatch).into());
+    }
+    let body = handler_body(trait_name, methods, options, HandlerKind::Async, false)?;
+    let list = methods_body(methods, options, HandlerKind::Async);
+    Ok(quote! {
+        impl easy_jsonrpc::AsyncHandler for dyn #trait_name {
+            fn handle_async<'a>(&'a self, method: &'a str, params: easy_jsonrpc::Params)
+                                -> easy_jsonrpc::BoxFuture<'a, Result<easy_jsonrpc::Value, easy_jsonrpc::Error>> {
+                self.handle_async_with_id(&easy_jsonrpc::Id::Null, method, params)
+            }
+
+            fn handle_async_with_id<'a>(&'a self, id: &'a easy_jsonrpc::Id, method: &'a str,
+                                        params: easy_jsonrpc::Params)
+                                        -> easy_jsonrpc::BoxFuture<'a, Result<easy_jsonrpc::Value, easy_jsonrpc::Error>> {
+                Box::pin(async move { #body })
+            }
+
+            fn methods_async(&self) -> Vec<&'static str> {
+                #list
+            }
+        }
+    })
+}
+
+// the sync rpc macro can't handle async methods, point users to rpc_async
+fn reject_async_methods(methods: &[RpcMethod]) -> Result<(), Rejections> {
+    partition(methods.iter().map(|method| match &method.sig.asyncness {
+        Some(asyncness) => Err(Rejection::create(asyncness.span(), Reason::AsyncMethod).into()),
+        None => Ok(()),
+    }))
+    .map(|_| ())
+}
+
+// Every method passed to rpc_async must be async and take &self. The trait must be Sync so that
+// futures borrowing the handler are Send.
+fn check_async_trait(tr: &ItemTrait, methods: &[RpcMethod]) -> Result<(), Rejections> {
+    let is_sync = tr.supertraits.iter().any(|bound| match bound {
+        TypeParamBound::Trait(bound) => bound
+            .path
+            .segments
+            .last()
+            .map(|last| last.ident == "Sync")
+            .unwrap_or(false),
+        _ => false,
+    });
+    let sync_check = if is_sync {
+        Ok(())
+    } else {
+        Err(Rejection::create(tr.ident.span(), Reason::AsyncTraitNotSync).into())
+    };
+    let method_checks = methods.iter().map(|method| {
+        if method.sig.asyncness.is_none() {
+            Err(Rejection::create(method.sig.ident.span(), Reason::SyncMethod).into())
+        } else if takes_mut_self(method.sig) {
+            Err(Rejection::create(method.sig.inputs.span(), Reason::AsyncMutSelf).into())
+        } else {
+            Ok(())
+        }
+    });
+    partition(std::iter::once(sync_check).chain(method_checks)).map(|_| ())
+}
+
+fn impl_client(
+    tr: &ItemTrait,
+    methods: &[RpcMethod],
+    options: &TraitOptions,
+) -> Result<TokenStream, Rejections> {
+    let trait_name = &tr.ident;
+    let mod_name = Ident::new(&trait_name.to_string().to_snake_case(), Span::call_site());
+    let mut method_impls = jsonrpc_methods(methods)
+        .map(impl_client_method)
+        .collect::<Result<Vec<TokenStream>, Rejections>>()?;
+    if options.openrpc {
+        method_impls.push(impl_openrpc_schema(tr, methods)?);
+    }
+    // names, along with the #[cfg] attributes of their method
+    let cfg_name = |method: &RpcMethod| {
+        let (cfg, name) = (&method.cfg, &method.name);
+        quote! { #cfg #name }
+    };
+    let method_names = jsonrpc_methods(methods).map(cfg_name);
+    // errors of methods with embed_errors are serialized as results
+    let error_types = jsonrpc_methods(methods)
+        .filter(|method| method.ok_type.is_some())
+        .filter_map(|method| Some((&method.cfg, result_err_type(&method.sig.output)?)));
+    let (error_cfgs, error_types): (Vec<_>, Vec<_>) = error_types.unzip();
+    let idempotent_names = jsonrpc_methods(methods)
+        .filter(|method| method.idempotent)
+        .map(cfg_name);
+    let descriptors = partition(jsonrpc_methods(methods).map(|method| {
+        let name = &method.name;
+        let cfg = &method.cfg;
+        let params = method.rpc_args()?.into_iter().map(|arg| {
+            let arg_name = arg.name;
+            let type_name = type_string(arg.ty);
+            quote! { (#arg_name, #type_name) }
+        });
+        let deprecated = match &method.deprecated {
+            Some(note) => quote! { Some(#note) },
+            None => quote! { None },
+        };
+        Ok(quote! {
+            #cfg
+            easy_jsonrpc::MethodDescriptor {
+                name: #name,
+                params: vec![#(#params),*],
+                deprecated: #deprecated,
+            }
+        })
+    }))?;
+    let supertrait_helpers = options
+        .supertraits
+        .iter()
+        .map(helper_path)
+        .collect::<Vec<_>>();
+    let (doc_names, docs): (Vec<_>, Vec<_>) = jsonrpc_methods(methods)
+        .filter_map(|method| Some((cfg_name(method), method.doc.as_ref()?)))
+        .unzip();
+    let (deprecated_names, notes): (Vec<_>, Vec<_>) = jsonrpc_methods(methods)
+        .filter_map(|method| Some((cfg_name(method), method.deprecated.as_ref()?)))
+        .unzip();
+    method_impls.push(quote! {
+        /// The doc comment of a jsonrpc method, including those of supertraits. Lines are joined
+        /// with newlines. Automatically generated by easy-jsonrpc.
+        pub fn method_doc(method: &str) -> Option<&'static str> {
+            match method {
+                #(#doc_names => Some(#docs),)*
+                _ => None #(.or_else(|| #supertrait_helpers::method_doc(method)))*,
+            }
+        }
+
+        /// The note of a jsonrpc method marked `#[rpc(deprecated)]`, including those of
+        /// supertraits, empty if it was given none. None for methods that are not deprecated.
+        /// Automatically generated by easy-jsonrpc.
+        pub fn deprecation(method: &str) -> Option<&'static str> {
+            match method {
+                #(#deprecated_names => Some(#notes),)*
+                _ => None #(.or_else(|| #supertrait_helpers::deprecation(method)))*,
+            }
+        }
+
+        #[doc(hidden)]
+        pub const RPC_METHODS: &'static [&'static str] = &[#(#method_names),*];
+
+        /// Whether the generated handler knows how to handle the method, either directly or
+        /// through a supertrait. Automatically generated by easy-jsonrpc.
+        #[doc(hidden)]
+        pub fn handles_method(method: &str) -> bool {
+            Self::RPC_METHODS.contains(&method)
+                #(|| #supertrait_helpers::handles_method(method))*
+        }
+
+        #[doc(hidden)]
+        pub const IDEMPOTENT_METHODS: &'static [&'static str] = &[#(#idempotent_names),*];
+
+        /// Whether the method is marked `#[rpc(idempotent)]`, either directly or in a
+        /// supertrait. Only calls to idempotent methods may be retried. Automatically generated
+        /// by easy-jsonrpc.
+        pub fn is_idempotent(method: &str) -> bool {
+            Self::IDEMPOTENT_METHODS.contains(&method)
+                #(|| #supertrait_helpers::is_idempotent(method))*
+        }
+
+        /// The application error codes the jsonrpc methods may return, including those of
+        /// supertraits, each along with a label. Sorted by code. See
+        /// `easy_jsonrpc::IntoRpcError::error_codes`. Automatically generated by easy-jsonrpc.
+        pub fn error_codes() -> Vec<(i64, &'static str)> {
+            let mut codes: Vec<(i64, &'static str)> = Vec::new();
+            #(#error_cfgs codes.extend_from_slice(
+                <#error_types as easy_jsonrpc::IntoRpcError>::error_codes()
+            );)*
+            #(codes.extend(#supertrait_helpers::error_codes());)*
+            codes.sort_unstable();
+            codes.dedup();
+            codes
+        }
+
+        /// Name and arguments of each jsonrpc method, including those of supertraits.
+        /// Automatically generated by easy-jsonrpc.
+        pub fn rpc_descriptors() -> Vec<easy_jsonrpc::MethodDescriptor> {
+            let mut descriptors = vec![#(#descriptors),*];
+            #(descriptors.extend(#supertrait_helpers::rpc_descriptors());)*
+            descriptors
+        }
+    });
+    let conflict_check = if options.supertraits.is_empty() {
+        quote! {}
+    } else {
+        let message = format!(
+            "jsonrpc method names of {} conflict with the names of its supertraits",
+            trait_name
+        );
+        quote! {
+            const _: () = assert!(
+                !easy_jsonrpc::contains_duplicate_names(
+                    &[#mod_name::RPC_METHODS, #(#supertrait_helpers::RPC_METHODS),*]
+                ),
+                #message
+            );
+        }
+    };
+    let method_constants = impl_method_constants(trait_name, methods)?;
+    let typed_client = if options.client {
+        impl_typed_client(trait_name, &mod_name, methods)?
+    } else {
+        quote! {}
+    };
+    let params_structs = partition(
+        jsonrpc_methods(methods)
+            .filter(|method| method.params_struct)
+            .map(impl_params_struct),
+    )?;
+
+    Ok(quote! {
+        // We originally used "mod" here. The problem was that modules can't access the
+        // namespace of their parent if their parent is a fn.
+        /// Helper module for jsonrpc clients. Automatically generated by easy-jsonrpc.
+        pub enum #mod_name {}
+        impl #mod_name {
+            #(#method_impls)*
+        }
+        #conflict_check
+        #method_constants
+        #typed_client
+        #(#params_structs)*
+    })
+}
+
+// generate a client struct with a method calling each jsonrpc method over a transport
+fn impl_typed_client(
+    trait_name: &Ident,
+    mod_name: &Ident,
+    methods: &[RpcMethod],
+) -> Result<TokenStream, Rejections> {
+    let client_name = Ident::new(&format!("{}Client", trait_name), Span::call_site());
+    let doc = format!(
+        "Typed jsonrpc client for {}, calling its methods over the wrapped transport. \
+         Automatically generated by easy-jsonrpc.",
+        trait_name
+    );
+    let client_methods = partition(jsonrpc_methods(methods).map(|method| {
+        let method_name = &method.sig.ident;
+        let args = method.rpc_args()?;
+        let arg_names: Vec<Ident> = (0..args.len())
+            .map(|i| Ident::new(&format!("arg{}", i), args[i].ident.span()))
+            .collect();
+        let arg_types = args.iter().map(|arg| arg.ty);
+        let return_typ = match method.ok_type {
+            Some(ok_type) => ok_type.clone(),
+            None => return_type(method.sig),
+        };
+        let generics = &method.sig.generics;
+        let doc = format!(
+            "Call the jsonrpc method \"{}\". Automatically generated by easy-jsonrpc.",
+            method.name
+        );
+        // bytes received as base64 are returned as the type of the method itself
+        let into_return = if method.base64_result.is_some() {
+            quote! { .map(::std::convert::From::from) }
+        } else {
+            quote! {}
+        };
+        let cfg = &method.cfg;
+        Ok(quote! {
+            #cfg
+            #[doc = #doc]
+            pub fn #method_name #generics (&self, #(#arg_names: #arg_types,)*)
+                    -> Result<#return_typ, easy_jsonrpc::ClientError<Tr::Error>> {
+                #mod_name::#method_name(#(#arg_names),*)?.send(&self.0)#into_return
+            }
+        })
+    }))?;
+    Ok(quote! {
+        #[doc = #doc]
+        pub struct #client_name<Tr>(pub Tr);
+
+        impl<Tr: easy_jsonrpc::Transport> #client_name<Tr> {
+            #(#client_methods)*
+        }
+    })
+}
+
+// warns through easy_jsonrpc::warn_deprecated the first time a deprecated method is called
+fn warn_deprecated(method: &RpcMethod) -> TokenStream {
+    match &method.deprecated {
+        Some(note) => {
+            let name = &method.name;
+            quote! {{
+                static WARNED: ::std::sync::atomic::AtomicBool =
+                    ::std::sync::atomic::AtomicBool::new(false);
+                easy_jsonrpc::warn_deprecated(&WARNED, #name, #note);
+            }}
+        }
+        None => quote! {},
+    }
+}
+
+// marks the requests generated for methods marked idempotent as such
+fn mark_idempotent(method: &RpcMethod) -> TokenStream {
+    if method.idempotent {
+        quote! { .mark_idempotent() }
+    } else {
+        quote! {}
+    }
+}
+
+// A field holding arg, deserialized the way the generated handler deserializes the argument.
+// Reference arguments are held as their owned type.
+fn serde_field(method: &RpcMethod, arg: &RpcArg, vis: TokenStream) -> TokenStream {
+    let ident = arg.ident;
+    let ty = match arg.ty {
+        Type::Reference(reference) => owned_arg_type(reference),
+        ty => quote! { #ty },
+    };
+    let rename = if *ident != arg.name {
+        let name = &arg.name;
+        quote! { #[serde(rename = #name)] }
+    } else {
+        quote! {}
+    };
+    let default = if arg.default {
+        quote! { #[serde(default)] }
+    } else {
+        quote! {}
+    };
+    let lenient = if method.lenient_numbers && is_numeric(arg.ty) {
+        quote! { #[serde(deserialize_with = "easy_jsonrpc::deserialize_lenient")] }
+    } else {
+        quote! {}
+    };
+    let base64 = match arg.base64 {
+        Some(Base64::Standard) => quote! {
+            #[serde(
+                serialize_with = "easy_jsonrpc::serialize_base64",
+                deserialize_with = "easy_jsonrpc::deserialize_base64"
+            )]
+        },
+        Some(Base64::UrlSafe) => quote! {
+            #[serde(
+                serialize_with = "easy_jsonrpc::serialize_base64_url_safe",
+                deserialize_with = "easy_jsonrpc::deserialize_base64"
+            )]
+        },
+        None => quote! {},
+    };
+    quote! { #rename #default #lenient #base64 #vis #ident: #ty }
+}
+
+// generate an enum with a variant holding the arguments of each jsonrpc method of the trait,
+// which dispatches to the trait methods, see #[rpc(request_enum)]
+fn impl_request_enum(tr: &ItemTrait, methods: &[RpcMethod]) -> Result<TokenStream, Rejections> {
+    let trait_name = &tr.ident;
+    let enum_name = Ident::new(&format!("{}Request", trait_name), trait_name.span());
+    let helper = Ident::new(&trait_name.to_string().to_snake_case(), Span::call_site());
+    if let Some(context) = context_type(methods)? {
+        return Err(Rejection::create(context.span(), Reason::UnsupportedRequestEnum).into());
+    }
+    partition(jsonrpc_methods(methods).map(|method| {
+        if method.with_id {
+            return Err(Rejection::create(method.name_span, Reason::UnsupportedRequestEnum).into());
+        }
+        match method
+            .rpc_args()?
+            .iter()
+            .find(|arg| arg.flatten || arg.rest)
+        {
+            Some(arg) => {
+                Err(Rejection::create(arg.ident.span(), Reason::UnsupportedRequestEnum).into())
+            }
+            None => Ok(()),
+        }
+    }))?;
+    let authorize = methods
+        .iter()
+        .find(|method| method.authorize)
+        .map(|authorize| &authorize.sig.ident);
+    let handler_type = if methods.iter().any(|method| takes_mut_self(method.sig)) {
+        quote! { &mut dyn #trait_name }
+    } else {
+        quote! { &dyn #trait_name }
+    };
+    let variants = partition(jsonrpc_methods(methods).map(|method| {
+        let variant = params_variant_name(method);
+        let name = &method.name;
+        let fields = method
+            .rpc_args()?
+            .iter()
+            .map(|arg| serde_field(method, arg, quote! {}))
+            .collect::<Vec<_>>();
+        let cfg = &method.cfg;
+        Ok(quote! {
+            #cfg
+            #[serde(rename = #name)]
+            #variant { #(#fields,)* }
+        })
+    }))?;
+    let arg_names = partition(jsonrpc_methods(methods).map(|method| {
+        let name = &method.name;
+        let cfg = &method.cfg;
+        let arg_names = method.rpc_args()?.into_iter().map(|arg| arg.name);
+        Ok(quote! { #cfg #name => &[#(#arg_names),*], })
+    }))?;
+    let names = partition(jsonrpc_methods(methods).map(|method| {
+        let variant = params_variant_name(method);
+        let (cfg, name) = (&method.cfg, &method.name);
+        Ok(quote! { #cfg #enum_name::#variant { .. } => #name, })
+    }))?;
+    let calls = partition(jsonrpc_methods(methods).map(|method| {
+        let variant = params_variant_name(method);
+        let method_name = &method.sig.ident;
+        let name = &method.name;
+        let args = method.rpc_args()?;
+        let bindings = args.iter().map(|arg| arg.ident);
+        let pass_args = args.iter().map(|arg| {
+            let ident = arg.ident;
+            match arg.ty {
+                Type::Reference(_) => quote! { &#ident },
+                _ => quote! { #ident },
+            }
+        });
+        let guard = match authorize {
+            Some(authorize) if method.auth => {
+                quote! { <dyn #trait_name>::#authorize(handler, #name)?; }
+            }
+            _ => quote! {},
+        };
+        let call = quote! { <dyn #trait_name>::#method_name(handler, #(#pass_args),*) };
+        let call = match method.ok_type {
+            Some(_) => quote! {
+                match #call {
+                    Ok(ok) => ok,
+                    Err(err) => return Err(easy_jsonrpc::IntoRpcError::into_error(err)),
+                }
+            },
+            None => call,
+        };
+        let result_type = method.ok_type.or(match &method.sig.output {
+            ReturnType::Type(_, typ) => Some(&**typ),
+            ReturnType::Default => None,
+        });
+        let try_serialize = match result_type {
+            _ if method.base64_result.is_some() => serialize_base64_result(method),
+            Some(typ) if is_json_value(typ) => quote! { Ok(result) },
+            _ => quote! { easy_jsonrpc::try_serialize(&result).map_err(easy_jsonrpc::Error::from) },
+        };
+        let cfg = &method.cfg;
+        let warn_deprecated = warn_deprecated(method);
+        Ok(quote! {
+            #cfg
+            #enum_name::#variant { #(#bindings),* } => {
+                #guard
+                #warn_deprecated
+                let result = #call;
+                #try_serialize
+            }
+        })
+    }))?;
+    let doc = format!(
+        "A call to a jsonrpc method of {}, along with its arguments. Automatically generated by \
+         easy-jsonrpc.",
+        trait_name
+    );
+
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(easy_jsonrpc::serde::Serialize, easy_jsonrpc::serde::Deserialize)]
+        #[serde(
+            crate = "easy_jsonrpc::serde",
+            tag = "method",
+            content = "params",
+            deny_unknown_fields
+        )]
+        pub enum #enum_name {
+            #(#variants,)*
+        }
+
+        impl #enum_name {
+            /// Parse the params of a call to method. Automatically generated by easy-jsonrpc.
+            pub fn parse(
+                method: &str,
+                params: easy_jsonrpc::Params,
+            ) -> Result<#enum_name, easy_jsonrpc::Error> {
+                if !#helper::RPC_METHODS.contains(&method) {
+                    return Err(easy_jsonrpc::method_not_found(method));
+                }
+                // positional parameters are named after the arguments they are passed as
+                let params = match params {
+                    easy_jsonrpc::Params::Positional(args) => {
+                        let names: &[&str] = match method {
+                            #(#arg_names)*
+                            _ => &[],
+                        };
+                        if args.len() > names.len() {
+                            return Err(easy_jsonrpc::Error::from(
+                                easy_jsonrpc::InvalidArgs::WrongNumberOfArgs {
+                                    expected: names.len(),
+                                    actual: args.len(),
+                                },
+                            ));
+                        }
+                        easy_jsonrpc::Value::Object(
+                            names.iter().map(|name| name.to_string()).zip(args).collect(),
+                        )
+                    }
+                    named => named.into_value(),
+                };
+                let call = easy_jsonrpc::serde_json::json!({
+                    "method": method,
+                    "params": params,
+                });
+                easy_jsonrpc::serde_json::from_value(call).map_err(|e| {
+                    easy_jsonrpc::Error::from(easy_jsonrpc::InvalidArgs::InvalidParamsStructure {
+                        detail: e.to_string(),
+                    })
+                })
+            }
+
+            /// The name of the jsonrpc method called. Automatically generated by easy-jsonrpc.
+            pub fn method(&self) -> &'static str {
+                match self {
+                    #(#names)*
+                }
+            }
+
+            /// Call the trait method, returning its serialized result. Automatically generated
+            /// by easy-jsonrpc.
+            pub fn dispatch(
+                self,
+                handler: #handler_type,
+            ) -> Result<easy_jsonrpc::Value, easy_jsonrpc::Error> {
+                match self {
+                    #(#calls)*
+                }
+            }
+        }
+    })
+}
+
+// the variant of the request enum for a method, e.g. WrappingAdd for wrapping_add
+fn params_variant_name(method: &RpcMethod) -> Ident {
+    Ident::new(
+        &method.sig.ident.to_string().to_camel_case(),
+        method.sig.ident.span(),
+    )
+}
+
+// the name of the struct generated for a method marked params_struct, e.g. WrappingAddParams
+fn params_struct_name(method: &RpcMethod) -> Ident {
+    Ident::new(
+        &format!("{}Params", params_variant_name(method)),
+        method.sig.ident.span(),
+    )
+}
+
+// generate the arguments struct of a method marked params_struct, along with request generators
+fn impl_params_struct(method: &RpcMethod) -> Result<TokenStream, Rejections> {
+    let struct_name = params_struct_name(method);
+    let method_name_literal = &method.name;
+    let args = method.rpc_args()?;
+    let doc = format!(
+        "Arguments of the jsonrpc method \"{}\". Automatically generated by easy-jsonrpc.",
+        method.name
+    );
+    let fields = args
+        .iter()
+        .map(|arg| serde_field(method, arg, quote! { pub }));
+    let args_serialize = args.iter().map(|arg| {
+        let ident = arg.ident;
+        serialize_arg(arg, quote! { &self.#ident })
+    });
+    let args_serialize = quote! { vec![#(#args_serialize),*] };
+    let return_typ = client_return_type(method);
+    let mark_idempotent = mark_idempotent(method);
+    let (constructor, arg_names) = match method.params {
+        ParamsShape::Named => {
+            let arg_names = args.iter().map(|arg| &arg.name);
+            (quote! { new_named }, quote! { &[#(#arg_names),*], })
+        }
+        ParamsShape::Any | ParamsShape::Positional => (quote! { new }, quote! {}),
+    };
+
+    let cfg = &method.cfg;
+    Ok(quote! {
+        #cfg
+        #[doc = #doc]
+        #[derive(easy_jsonrpc::serde::Serialize, easy_jsonrpc::serde::Deserialize)]
+        #[serde(crate = "easy_jsonrpc::serde", deny_unknown_fields)]
+        pub struct #struct_name {
+            #(#fields,)*
+        }
+
+        #cfg
+        impl #struct_name {
+            /// Request generator for jsonrpc clients. Automatically generated by easy-jsonrpc.
+            pub fn request(&self)
+                -> Result<easy_jsonrpc::BoundMethod<'static, #return_typ>, easy_jsonrpc::ArgSerializeError> {
+                Ok(easy_jsonrpc::BoundMethod::#constructor(
+                    #method_name_literal,
+                    #arg_names
+                    #args_serialize,
+                )#mark_idempotent)
+            }
+
+            /// Notification generator for jsonrpc clients. Automatically generated by easy-jsonrpc.
+            pub fn notification(&self)
+                -> Result<easy_jsonrpc::BoundNotification, easy_jsonrpc::ArgSerializeError> {
+                Ok(easy_jsonrpc::BoundNotification::#constructor(
+                    #method_name_literal,
+                    #arg_names
+                    #args_serialize,
+                ))
+            }
+        }
+    })
+}
+
+// Render ty the way it would be written in source, e.g. "Vec<(u8, String)>" rather than the
+// "Vec < (u8 , String) >" produced by TokenStream::to_string.
+fn type_string(ty: &Type) -> String {
+    let spaced = quote!(#ty).to_string();
+    let chars = spaced.chars().collect::<Vec<_>>();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut ret = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        match c {
+            ' ' if i > 0 && chars[i - 1] == ',' => ret.push(' '),
+            ' ' if i > 0
+                && i + 1 < chars.len()
+                && is_word(chars[i - 1])
+                && is_word(chars[i + 1]) =>
+            {
+                ret.push(' ')
+            }
+            ' ' if i > 0 && chars[i - 1] == '\'' => ret.push(' '), // lifetimes, as in &'a str
+            ' ' => {}
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+// generate a module containing a constant for the name of each method
+fn impl_method_constants(
+    trait_name: &Ident,
+    methods: &[RpcMethod],
+) -> Result<TokenStream, Rejections> {
+    let mod_name = Ident::new(
+        &format!("{}_methods", trait_name.to_string().to_snake_case()),
+        Span::call_site(),
+    );
+    let mut seen = Vec::new();
+    let constants = partition(jsonrpc_methods(methods).map(|method| {
+        let sanitized: String = method
+            .name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let mut const_name = sanitized.to_shouty_snake_case();
+        if !const_name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            const_name.insert(0, '_');
+        }
+        if seen.contains(&const_name) {
+            return Err(
+                Rejection::create(method.name_span, Reason::MethodConstantCollision).into(),
+            );
+        }
+        seen.push(const_name.clone());
+        let const_name = Ident::new(&const_name, method.name_span);
+        let name = &method.name;
+        let doc = format!("`{}`", name);
+        let cfg = &method.cfg;
+        Ok(quote! {
+            #cfg
+            #[doc = #doc]
+            pub const #const_name: &str = #name;
+        })
+    }))?;
+    Ok(quote! {
+        /// Names of the jsonrpc methods of the trait. Automatically generated by easy-jsonrpc.
+        pub mod #mod_name {
+            #(#constants)*
+        }
+    })
+}
+
+// generate a function returning an OpenRPC document describing the methods of the trait
+// https://spec.open-rpc.org/
+fn impl_openrpc_schema(tr: &ItemTrait, methods: &[RpcMethod]) -> Result<TokenStream, Rejections> {
+    let title = tr.ident.to_string();
+    let method_descriptions = partition(jsonrpc_methods(methods).map(|method| {
+        let name = &method.name;
+        let params = method.rpc_args()?.into_iter().map(|arg| {
+            let arg_name = &arg.name;
+            let schema = match arg.base64 {
+                Some(_) => base64_schema(),
+                None => json_schema(arg.ty),
+            };
+            let required = !arg.default;
+            quote! {
+                easy_jsonrpc::serde_json::json!({
+                    "name": #arg_name,
+                    "required": #required,
+                    "schema": #schema,
+                })
+            }
+        });
+        // "either" is the default, so it is left out
+        let param_structure = match method.params {
+            ParamsShape::Any => quote! {},
+            ParamsShape::Named => quote! { "paramStructure": "by-name", },
+            ParamsShape::Positional => quote! { "paramStructure": "by-position", },
+        };
+        let result_schema = match (method.base64_result, method.ok_type) {
+            (Some(_), _) => base64_schema(),
+            (None, Some(ok_type)) => json_schema(ok_type),
+            (None, None) => json_schema(&return_type(method.sig)),
+        };
+        let deprecated = match method.deprecated.as_deref() {
+            None => quote! {},
+            Some("") => quote! { "deprecated": true, },
+            Some(note) => quote! { "deprecated": true, "description": #note, },
+        };
+        let cfg = &method.cfg;
+        Ok(quote! {
+            #cfg
+            easy_jsonrpc::serde_json::json!({
+                "name": #name,
+                "params": [#(#params),*],
+                #param_structure
+                #deprecated
+                "result": {
+                    "name": "result",
+                    "schema": #result_schema,
+                },
+            })
+        })
+    }))?;
+    Ok(quote! {
+        /// OpenRPC document describing this api. Automatically generated by easy-jsonrpc.
+        pub fn openrpc_schema() -> easy_jsonrpc::Value {
+            let methods: Vec<easy_jsonrpc::Value> = vec![#(#method_descriptions),*];
+            easy_jsonrpc::serde_json::json!({
+                "openrpc": "1.2.6",
+                "info": {
+                    "title": #title,
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "methods": methods,
+            })
+        }
+    })
+}
+
+fn base64_schema() -> TokenStream {
+    quote! { easy_jsonrpc::serde_json::json!({ "type": "string", "contentEncoding": "base64" }) }
+}
+
+// Best effort mapping from a rust type to a json schema. The macro only sees the syntax of the
+// type, so user defined types, type aliases and anything else unrecognized map to the empty schema,
+// which accepts any value.
+fn json_schema(ty: &Type) -> TokenStream {
+    let simple = |typ: &str| quote! { easy_jsonrpc::serde_json::json!({ "type": #typ }) };
+    match ty {
+        Type::Reference(reference) => json_schema(&reference.elem),
+        Type::Paren(paren) => json_schema(&paren.elem),
+        Type::Group(group) => json_schema(&group.elem),
+        Type::Slice(slice) => {
+            let items = json_schema(&slice.elem);
+            quote! { easy_jsonrpc::serde_json::json!({ "type": "array", "items": #items }) }
+        }
+        Type::Array(array) => {
+            let items = json_schema(&array.elem);
+            quote! { easy_jsonrpc::serde_json::json!({ "type": "array", "items": #items }) }
+        }
+        Type::Tuple(tuple) if tuple.elems.is_empty() => simple("null"),
+        Type::Tuple(tuple) => {
+            let items = tuple.elems.iter().map(json_schema);
+            quote! { easy_jsonrpc::serde_json::json!({ "type": "array", "items": [#(#items),*] }) }
+        }
+        Type::Path(TypePath { qself: None, path }) => {
+            let last = match path.segments.last() {
+                Some(last) => last,
+                None => return quote! { easy_jsonrpc::serde_json::json!({}) },
+            };
+            let type_args: Vec<&Type> = match &last.arguments {
+                PathArguments::AngleBracketed(args) => args
+                    .args
+                    .iter()
+                    .filter_map(|arg| match arg {
+                        GenericArgument::Type(typ) => Some(typ),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            match (last.ident.to_string().as_str(), type_args.as_slice()) {
+                (
+                    "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+                    | "u128" | "usize",
+                    [],
+                ) => simple("integer"),
+                ("f32" | "f64", []) => simple("number"),
+                ("bool", []) => simple("boolean"),
+                // answered with the id, the items are pushed as notifications
+                ("Subscription", [_]) => simple("integer"),
+                ("String" | "str" | "char", []) => simple("string"),
+                // the metadata is an extra member of the result object
+                ("Box" | "Rc" | "Arc" | "Cow" | "RpcResponse", [inner]) => json_schema(inner),
+                ("Option", [inner]) => {
+                    let inner = json_schema(inner);
+                    quote! {
+                        easy_jsonrpc::serde_json::json!({ "oneOf": [#inner, { "type": "null" }] })
+                    }
+                }
+                ("Vec" | "VecDeque" | "HashSet" | "BTreeSet", [inner]) => {
+                    let items = json_schema(inner);
+                    quote! { easy_jsonrpc::serde_json::json!({ "type": "array", "items": #items }) }
+                }
+                ("HashMap" | "BTreeMap", [_, value]) => {
+                    let values = json_schema(value);
+                    quote! {
+                        easy_jsonrpc::serde_json::json!({
+                            "type": "object",
+                            "additionalProperties": #values,
+                        })
+                    }
+                }
+                _ => quote! { easy_jsonrpc::serde_json::json!({}) },
+            }
+        }
+        _ => quote! { easy_jsonrpc::serde_json::json!({}) },
+    }
+}
+
+fn impl_client_method(method: &RpcMethod) -> Result<TokenStream, Rejections> {
+    let method_name = &method.sig.ident;
+    let method_name_literal = &method.name;
+    let args = method.rpc_args()?;
+    let fn_definition_args: &Vec<_> = &args
+        .iter()
+        .enumerate()
+        .map(|(i, arg)| {
+            let arg_num_name = Ident::new(&format!("arg{}", i), arg.ident.span());
+            let typ = arg.ty;
+            quote! {#arg_num_name: #typ}
+        })
+        .collect();
+    let args_serialize: &Vec<_> = &args
+        .iter()
+        .enumerate()
+        .map(|(i, arg)| {
+            let arg_num_name = Ident::new(&format!("arg{}", i), arg.ident.span());
+            serialize_arg(arg, quote! { #arg_num_name })
+        })
+        .collect();
+    let return_typ = client_return_type(method);
+    let cfg = &method.cfg;
+
+    let notify_name = Ident::new(&format!("notify_{}", method_name), method_name.span());
+    // only lifetime parameters are allowed, see reject_generic_method
+    let generics = &method.sig.generics;
+    // named parameters are passed along with their names
+    let mark_idempotent = mark_idempotent(method);
+    let flatten = args.iter().any(|arg| arg.flatten);
+    let (constructor, arg_names) = match method.params {
+        ParamsShape::Any | ParamsShape::Named if flatten => {
+            return Ok(quote! {
+                #cfg
+                /// Request generator for jsonrpc clients. Automatically generated by easy-jsonrpc.
+                pub fn #method_name #generics ( #(#fn_definition_args,)* )
+                        -> Result<easy_jsonrpc::BoundMethod<'static, #return_typ>, easy_jsonrpc::ArgSerializeError> {
+                    Ok(easy_jsonrpc::BoundMethod::new_flattened(
+                        #method_name_literal,
+                        #(#args_serialize)*,
+                    )#mark_idempotent)
+                }
+
+                #cfg
+                /// Notification generator for jsonrpc clients. Automatically generated by easy-jsonrpc.
+                pub fn #notify_name #generics ( #(#fn_definition_args,)* )
+                        -> Result<easy_jsonrpc::BoundNotification, easy_jsonrpc::ArgSerializeError> {
+                    Ok(easy_jsonrpc::BoundNotification::new_flattened(
+                        #method_name_literal,
+                        #(#args_serialize)*,
+                    ))
+                }
+            });
+        }
+        ParamsShape::Named => {
+            let arg_names = args.iter().map(|arg| &arg.name);
+            // the extras of a rest argument are sent as named parameters of their own
+            let constructor = if args.iter().any(|arg| arg.rest) {
+                quote! { new_named_rest }
+            } else {
+                quote! { new_named }
+            };
+            (constructor, quote! { &[#(#arg_names),*], })
+        }
+        ParamsShape::Any | ParamsShape::Positional => (quote! { new }, quote! {}),
+    };
+
+    Ok(quote! {
+        #cfg
+        /// Request generator for jsonrpc clients. Automatically generated by easy-jsonrpc.
+        pub fn #method_name #generics ( #(#fn_definition_args,)* )
+                                 -> Result<easy_jsonrpc::BoundMethod<'static, #return_typ>, easy_jsonrpc::ArgSerializeError> {
+            Ok(easy_jsonrpc::BoundMethod::#constructor(
+                #method_name_literal,
+                #arg_names
+                vec![ #(#args_serialize),* ],
+            )#mark_idempotent)
+        }
+
+        #cfg
+        /// Notification generator for jsonrpc clients. Automatically generated by easy-jsonrpc.
+        pub fn #notify_name #generics ( #(#fn_definition_args,)* )
+                                 -> Result<easy_jsonrpc::BoundNotification, easy_jsonrpc::ArgSerializeError> {
+            Ok(easy_jsonrpc::BoundNotification::#constructor(
+                #method_name_literal,
+                #arg_names
+                vec![ #(#args_serialize),* ],
+            ))
+        }
+    })
+}
+
+// An expression serializing the value of arg to json, returning ArgSerializeError on failure.
+fn serialize_arg(arg: &RpcArg, value: TokenStream) -> TokenStream {
+    match arg.base64 {
+        Some(alphabet) => {
+            let alphabet = alphabet.tokens();
+            quote! { easy_jsonrpc::to_value_base64(&#value, #alphabet) }
+        }
+        None => quote! {
+            easy_jsonrpc::serde_json::to_value(#value).map_err(|_| easy_jsonrpc::ArgSerializeError)?
+        },
+    }
+}
+
+// The type requests generated by the client helpers deserialize results as. Results sent as base64
+// are decoded into Base64Bytes, the return type of the method itself would expect a json array.
+fn client_return_type(method: &RpcMethod) -> TokenStream {
+    if method.base64_result.is_some() {
+        return quote! { easy_jsonrpc::Base64Bytes };
+    }
+    match method.ok_type {
+        Some(ok_type) => quote! { #ok_type },
+        None => {
+            let return_typ = return_type(method.sig);
+            quote! { #return_typ }
+        }
+    }
+}
+
+// Serializes the bytes a method marked base64_result returned.
+fn serialize_base64_result(method: &RpcMethod) -> TokenStream {
+    let alphabet = method.base64_result.map(Base64::tokens);
+    quote_spanned! {
+        return_type_span(method.sig) =>
+            Ok(easy_jsonrpc::to_value_base64(&result, #alphabet))
+    }
+}
+
+// An item failing to compile at the span of typ unless typ implements Serialize. Without it, a
+// return type that can't be serialized is only reported at the generated call to try_serialize.
+// Types that can't be named outside the trait, like those with lifetimes, aren't checked.
+fn assert_serialize(typ: &Type) -> Option<TokenStream> {
+    if !nameable(quote!(#typ)) {
+        return None;
+    }
+    Some(quote_spanned! {
+        typ.span() =>
+            #[allow(dead_code)]
+            fn assert_serialize() where #typ: easy_jsonrpc::serde::Serialize {}
+    })
+}
+
+// whether tokens name a type that is the same wherever it is written within the trait's module
+fn nameable(tokens: TokenStream) -> bool {
+    tokens.into_iter().all(|token| match token {
+        TokenTree::Group(group) => nameable(group.stream()),
+        TokenTree::Ident(ident) => ident != "Self" && ident != "impl" && ident != "_",
+        // references need a lifetime in where clauses, lifetimes aren't in scope
+        TokenTree::Punct(punct) => punct.as_char() != '&' && punct.as_char() != '\'',
+        TokenTree::Literal(_) => true,
+    })
+}
+
+fn return_type_span(method: &Signature) -> Span {
+    let return_type = match &method.output {
+        ReturnType::Default => None,
+        ReturnType::Type(_, typ) => Some(typ),
+    };
+    return_type
+        .map(|typ| typ.span())
+        .unwrap_or_else(|| method.output.span())
+}
+
+fn return_type(method: &Signature) -> Type {
+    match &method.output {
+        ReturnType::Default => Type::Tuple(TypeTuple {
+            paren_token: Paren(method.output.span()),
+            elems: Punctuated::new(),
+        }),
+        ReturnType::Type(_, typ) => *typ.clone(),
+    }
+}
+
+// a trait method, along with the options specified in its #[rpc(..)] attributes
+struct RpcMethod<'a> {
+    sig: &'a Signature,
+    name: String, // name of the method as seen by jsonrpc clients
+    name_span: Span,
+    with_id: bool,         // whether the first argument receives the id of the request
+    fallback: bool, // whether the method is called for methods that are not otherwise handled
+    authorize: bool, // whether the method decides if calls to methods marked auth may go ahead
+    auth: bool,     // whether calls to the method must be authorized first
+    before_dispatch: bool, // whether the method decides if any call may go ahead
+    allow_reserved: bool, // whether the name may start with a reserved prefix, like "rpc."
+    tuple_args: bool, // whether arguments are deserialized in one pass, see add_tuple_handler
+    lenient_numbers: bool, // whether numeric arguments may also be passed as strings
+    params_struct: bool, // whether arguments are deserialized into a generated struct
+    idempotent: bool, // whether clients may retry calls to the method
+    deprecated: Option<String>, // the note of a deprecated method, empty if it has none
+    base64_result: Option<Base64>, // the alphabet the result is sent in as a base64 string
+    params: ParamsShape, // the kind of parameters the method accepts
+    doc: Option<String>, // the doc comment of the method
+    cfg: TokenStream, // the #[cfg] attributes of the method, repeated on the code generated for it
+    // When set, the method returns Result<ok_type, E>. Errors are reported as jsonrpc errors.
+    ok_type: Option<&'a Type>,
+}
+
+impl<'a> RpcMethod<'a> {
+    fn parse(
+        attrs: &[Attribute],
+        sig: &'a Signature,
+        trait_options: &TraitOptions,
+    ) -> Result<RpcMethod<'a>, Rejections> {
+        let mut method = RpcMethod {
+            sig,
+            name: format!("{}{}", trait_options.prefix, sig.ident),
+            name_span: sig.ident.span(),
+            with_id: false,
+            fallback: false,
+            authorize: false,
+            auth: false,
+            before_dispatch: false,
+            allow_reserved: false,
+            tuple_args: trait_options.tuple_args,
+            lenient_numbers: trait_options.lenient_numbers,
+            params_struct: false,
+            idempotent: false,
+            deprecated: None,
+            base64_result: None,
+            params: ParamsShape::Any,
+            doc: doc_string(attrs),
+            cfg: {
+                let cfg = attrs.iter().filter(|attr| attr.path().is_ident("cfg"));
+                quote! { #(#cfg)* }
+            },
+            ok_type: if trait_options.embed_errors {
+                None
+            } else {
+                result_ok_type(&sig.output)
+            },
+        };
+        partition(rpc_options(attrs)?.iter().map(|option| match option {
+            Meta::NameValue(MetaNameValue { path, value, .. }) if path.is_ident("name") => {
+                match value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(name),
+                        ..
+                    }) => {
+                        method.name = name.value();
+                        method.name_span = name.span();
+                        Ok(())
+                    }
+                    other => {
+                        Err(Rejection::create(other.span(), Reason::ExpectedStringLiteral).into())
+                    }
+                }
+            }
+            Meta::NameValue(MetaNameValue { path, value, .. }) if path.is_ident("params") => {
+                match value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(shape),
+                        ..
+                    }) => {
+                        method.params = match shape.value().as_str() {
+                            "named" => ParamsShape::Named,
+                            "positional" => ParamsShape::Positional,
+                            _ => {
+                                return Err(Rejection::create(
+                                    shape.span(),
+                                    Reason::UnknownParamsShape,
+                                )
+                                .into())
+                            }
+                        };
+                        Ok(())
+                    }
+                    other => {
+                        Err(Rejection::create(other.span(), Reason::ExpectedStringLiteral).into())
+                    }
+                }
+            }
+            Meta::Path(path) if path.is_ident("with_id") => {
+                method.with_id = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("fallback") => {
+                method.fallback = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("authorize") => {
+                method.authorize = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("lenient_numbers") => {
+                method.lenient_numbers = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("idempotent") => {
+                method.idempotent = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("deprecated") => {
+                method.deprecated = Some(String::new());
+                Ok(())
+            }
+            Meta::NameValue(MetaNameValue { path, value, .. }) if path.is_ident("deprecated") => {
+                match value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(note),
+                        ..
+                    }) => {
+                        method.deprecated = Some(note.value());
+                        Ok(())
+                    }
+                    other => {
+                        Err(Rejection::create(other.span(), Reason::ExpectedStringLiteral).into())
+                    }
+                }
+            }
+            Meta::Path(path) if path.is_ident("base64_result") => {
+                method.base64_result = Some(Base64::Standard);
+                Ok(())
+            }
+            Meta::NameValue(MetaNameValue { path, value, .. })
+                if path.is_ident("base64_result") =>
+            {
+                method.base64_result = Some(Base64::parse(value)?);
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("params_struct") => {
+                method.params_struct = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("before_dispatch") => {
+                method.before_dispatch = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("auth") => {
+                method.auth = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("allow_reserved") => {
+                method.allow_reserved = true;
+                Ok(())
+            }
+            other => Err(Rejection::create(other.span(), Reason::UnknownOption).into()),
+        }))?;
+        Ok(method)
+    }
+
+    // Get the arguments that are passed by jsonrpc clients. When with_id is set, the first argument
+    // after self is filled in by the server so it is not included. Neither is the context argument.
+    fn rpc_args(&self) -> Result<Vec<RpcArg<'a>>, Rejections> {
+        Ok(self.split_args()?.1)
+    }
+
+    // Get the argument marked #[rpc(context)], if any.
+    fn context_arg(&self) -> Result<Option<RpcArg<'a>>, Rejections> {
+        Ok(self.split_args()?.0)
+    }
+
+    // split arguments into the context argument and the arguments passed by jsonrpc clients
+    fn split_args(&self) -> Result<(Option<RpcArg<'a>>, Vec<RpcArg<'a>>), Rejections> {
+        let mut args = get_args(self.sig)?;
+        if self.with_id {
+            if args.is_empty() {
+                return Err(Rejection::create(self.sig.inputs.span(), Reason::MissingIdArg).into());
+            }
+            args.remove(0);
+        }
+        // the context comes first, after the id if there is one
+        let context = match args.first() {
+            Some(first) if first.context => Some(args.remove(0)),
+            _ => None,
+        };
+        if let Some(context) = &context {
+            if !matches!(context.ty, Type::Reference(_)) {
+                return Err(
+                    Rejection::create(context.ty.span(), Reason::ContextArgNotReference).into(),
+                );
+            }
+        }
+        partition(args.iter().filter(|arg| arg.context).map(|arg| {
+            Err::<(), _>(Rejection::create(arg.ident.span(), Reason::MisplacedContextArg).into())
+        }))?;
+        // optional arguments may be omitted from the end of a positional argument list, so they
+        // must all be trailing, a misplaced rest argument is rejected on its own
+        partition(args.windows(2).map(|pair| match pair {
+            [a, b] if a.default && !a.rest && !b.default => {
+                Err(Rejection::create(b.ident.span(), Reason::RequiredArgAfterDefault).into())
+            }
+            _ => Ok(()),
+        }))?;
+        Ok((context, args))
+    }
+}
+
+// the kind of parameters a method accepts, see #[rpc(params = "...")]
+#[derive(Clone, Copy, PartialEq)]
+enum ParamsShape {
+    Any,
+    Named,
+    Positional,
+}
+
+// an argument to a trait method, along with the options specified in its #[rpc(..)] attributes
+struct RpcArg<'a> {
+    ident: &'a Ident,
+    ty: &'a Type,
+    name: String, // name of the argument as seen by jsonrpc clients passing named parameters
+    name_span: Span,
+    default: bool, // whether the argument may be omitted, Default::default() is passed if so
+    context: bool, // whether the argument is the request context passed to ContextHandler
+    flatten: bool, // whether the argument takes the named parameters as a whole
+    rest: bool,    // whether the argument takes the named parameters no other argument takes
+    base64: Option<Base64>, // the alphabet the bytes of the argument are sent in
+}
+
+impl<'a> RpcArg<'a> {
+    fn parse(attrs: &[Attribute], ident: &'a Ident, ty: &'a Type) -> Result<Self, Rejections> {
+        let mut arg = RpcArg {
+            ident,
+            ty,
+            name: ident.to_string(),
+            name_span: ident.span(),
+            default: false,
+            context: false,
+            flatten: false,
+            rest: false,
+            base64: None,
+        };
+        partition(rpc_options(attrs)?.iter().map(|option| match option {
+            Meta::Path(path) if path.is_ident("default") => {
+                arg.default = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("context") => {
+                arg.context = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("flatten") => {
+                arg.flatten = true;
+                Ok(())
+            }
+            // positional parameters may leave out the extras, which are then empty
+            Meta::Path(path) if path.is_ident("rest") => {
+                arg.rest = true;
+                arg.default = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("base64") => {
+                arg.base64 = Some(Base64::Standard);
+                Ok(())
+            }
+            Meta::NameValue(MetaNameValue { path, value, .. }) if path.is_ident("base64") => {
+                arg.base64 = Some(Base64::parse(value)?);
+                Ok(())
+            }
+            Meta::NameValue(MetaNameValue { path, value, .. }) if path.is_ident("rename") => {
+                match value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(name),
+                        ..
+                    }) => {
+                        arg.name = name.value();
+                        arg.name_span = name.span();
+                        Ok(())
+                    }
+                    other => {
+                        Err(Rejection::create(other.span(), Reason::ExpectedStringLiteral).into())
+                    }
+                }
+            }
+            other => Err(Rejection::create(other.span(), Reason::UnknownArgOption).into()),
+        }))?;
+        // the bytes are the argument itself, not a context, a dictionary or part of one
+        if arg.base64.is_some() && (arg.context || arg.flatten || arg.rest) {
+            return Err(Rejection::create(ident.span(), Reason::MisplacedBase64).into());
+        }
+        Ok(arg)
+    }
+}
+
+// the alphabet of a base64 argument or result, see #[rpc(base64)]
+#[derive(Clone, Copy)]
+enum Base64 {
+    Standard,
+    UrlSafe,
+}
+
+impl Base64 {
+    fn parse(value: &Expr) -> Result<Base64, Rejections> {
+        match value {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(alphabet),
+                ..
+            }) => match alphabet.value().as_str() {
+                "standard" => Ok(Base64::Standard),
+                "url_safe" => Ok(Base64::UrlSafe),
+                _ => Err(Rejection::create(alphabet.span(), Reason::UnknownBase64Alphabet).into()),
+            },
+            other => Err(Rejection::create(other.span(), Reason::ExpectedStringLiteral).into()),
+        }
+    }
+
+    fn tokens(self) -> TokenStream {
+        match self {
+            Base64::Standard => quote! { easy_jsonrpc::Base64::Standard },
+            Base64::UrlSafe => quote! { easy_jsonrpc::Base64::UrlSafe },
+        }
+    }
+}
+
+// options passed to the macro itself, e.g. #[rpc(embed_errors)]
+struct TraitOptions {
+    embed_errors: bool, // serialize Result return values as is, rather than as jsonrpc errors
+    openrpc: bool,      // generate an OpenRPC document along with the client helpers
+    client: bool,       // generate a typed client struct, see impl_typed_client
+    describe: bool,     // answer rpc.describe with the rpc_descriptors of the trait
+    supertraits: Vec<Path>, // supertraits whose methods are served along with those of the trait
+    tuple_args: bool,   // deserialize arguments in a single pass where possible
+    lenient_numbers: bool, // accept numeric arguments passed as strings in every method
+    request_enum: bool, // generate an enum with a variant for each method, see impl_request_enum
+    prefix: String,     // prepended to the names of methods that are not explicitly named
+    reserved_prefixes: Vec<String>, // method names rejected unless marked allow_reserved
+}
+
+impl TraitOptions {
+    fn parse(args: &Options) -> Result<TraitOptions, Rejections> {
+        let mut options = TraitOptions {
+            embed_errors: false,
+            openrpc: false,
+            client: false,
+            describe: false,
+            supertraits: Vec::new(),
+            tuple_args: false,
+            lenient_numbers: false,
+            request_enum: false,
+            prefix: String::new(),
+            reserved_prefixes: vec!["rpc.".to_owned()],
+        };
+        partition(args.iter().map(|arg| match arg {
+            Meta::List(list) if list.path.is_ident("supertraits") => {
+                let paths = list
+                    .parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated)
+                    .map_err(|e| Rejection::create(e.span(), Reason::MalformedAttribute))?;
+                options.supertraits.extend(paths);
+                Ok(())
+            }
+            Meta::List(list) if list.path.is_ident("reserved_prefixes") => {
+                let prefixes = list
+                    .parse_args_with(Punctuated::<LitStr, Token![,]>::parse_terminated)
+                    .map_err(|e| Rejection::create(e.span(), Reason::MalformedAttribute))?;
+                options
+                    .reserved_prefixes
+                    .extend(prefixes.iter().map(LitStr::value));
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("embed_errors") => {
+                options.embed_errors = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("openrpc") => {
+                options.openrpc = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("client") => {
+                options.client = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("describe") => {
+                options.describe = true;
+                Ok(())
+            }
+            Meta::NameValue(MetaNameValue { path, value, .. }) if path.is_ident("prefix") => {
+                match value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(prefix),
+                        ..
+                    }) => {
+                        options.prefix = prefix.value();
+                        Ok(())
+                    }
+                    other => {
+                        Err(Rejection::create(other.span(), Reason::ExpectedStringLiteral).into())
+                    }
+                }
+            }
+            Meta::Path(path) if path.is_ident("lenient_numbers") => {
+                options.lenient_numbers = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("tuple_args") => {
+                options.tuple_args = true;
+                Ok(())
+            }
+            Meta::Path(path) if path.is_ident("request_enum") => {
+                options.request_enum = true;
+                Ok(())
+            }
+            other => Err(Rejection::create(other.span(), Reason::UnknownTraitOption).into()),
+        }))?;
+        Ok(options)
+    }
+}
+
+// If the return type looks like Result<T, E>, return T. Type aliases can't be resolved by proc
+// macros so only types named "Result" with exactly two type parameters are recognized.
+fn result_ok_type(output: &ReturnType) -> Option<&Type> {
+    result_types(output).map(|(ok, _)| ok)
 }
 
-// generate a Handler implementation for &dyn Trait
-fn impl_server(tr: &ItemTrait) -> Result<TokenStream, Rejections> {
-    let trait_name = &tr.ident;
-    let methods: Vec<&MethodSig> = trait_methods(&tr)?;
+// the error type of a method returning Result<T, E>
+fn result_err_type(output: &ReturnType) -> Option<&Type> {
+    result_types(output).map(|(_, err)| err)
+}
 
-    let handlers = methods.iter().map(|method| {
-        let method_literal = method.ident.to_string();
-        let method_return_type_span = return_type_span(&method);
-        let handler = add_handler(trait_name, method)?;
-        let try_serialize = quote_spanned! {
-            method_return_type_span =>
-                easy_jsonrpc::try_serialize(&result)
-        };
-        Ok(quote! { #method_literal => {
-            let result = #handler;
-            #try_serialize
-        }})
+fn result_types(output: &ReturnType) -> Option<(&Type, &Type)> {
+    let path = match output {
+        ReturnType::Type(_, typ) => match &**typ {
+            Type::Path(TypePath { qself: None, path }) => path,
+            _ => return None,
+        },
+        ReturnType::Default => return None,
+    };
+    let last = path.segments.last()?;
+    if last.ident != "Result" {
+        return None;
+    }
+    let args = match &last.arguments {
+        PathArguments::AngleBracketed(args) => &args.args,
+        _ => return None,
+    };
+    let mut types = args.iter().filter_map(|arg| match arg {
+        GenericArgument::Type(typ) => Some(typ),
+        _ => None,
     });
-    let handlers: Vec<TokenStream> = partition(handlers)?;
+    match (types.next(), types.next(), types.next()) {
+        (Some(ok), Some(err), None) => Some((ok, err)),
+        _ => None,
+    }
+}
 
-    Ok(quote! {
-        impl easy_jsonrpc::Handler for dyn #trait_name {
-            fn handle(&self, method: &str, params: easy_jsonrpc::Params)
-                      -> Result<easy_jsonrpc::Value, easy_jsonrpc::Error> {
-                match method {
-                    #(#handlers,)*
-                    _ => Err(easy_jsonrpc::Error::method_not_found()),
-                }
-            }
-        }
-    })
+// Whether typ is one of the primitive integer or float types.
+fn is_numeric(typ: &Type) -> bool {
+    const NUMERIC: &[&str] = &[
+        "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+        "f32", "f64",
+    ];
+    match typ {
+        Type::Path(TypePath { qself: None, path }) => path
+            .get_ident()
+            .is_some_and(|ident| NUMERIC.iter().any(|name| ident == name)),
+        _ => false,
+    }
 }
 
-fn impl_client(tr: &ItemTrait) -> Result<TokenStream, Rejections> {
-    let trait_name = &tr.ident;
-    let methods: Vec<&MethodSig> = trait_methods(&tr)?;
-    let mod_name = Ident::new(&trait_name.to_string().to_snake_case(), Span::call_site());
-    let method_impls = methods
+// whether typ is spelled as an Option, e.g. Option<u8> or std::option::Option<u8>
+fn is_option(typ: &Type) -> bool {
+    match typ {
+        Type::Path(TypePath { qself: None, path }) => path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+// Whether typ names the json value type of serde_json. Only fully qualified paths are recognized,
+// a bare `Value` may be some other type.
+fn is_json_value(typ: &Type) -> bool {
+    let path = match typ {
+        Type::Path(TypePath { qself: None, path }) => path,
+        _ => return false,
+    };
+    let segments: Vec<String> = path
+        .segments
         .iter()
-        .map(|method| impl_client_method(*method))
-        .collect::<Result<Vec<TokenStream>, Rejections>>()?;
+        .map(|segment| match segment.arguments {
+            PathArguments::None => segment.ident.to_string(),
+            _ => String::new(),
+        })
+        .collect();
+    match segments.as_slice() {
+        [krate, value] => (krate == "easy_jsonrpc" || krate == "serde_json") && value == "Value",
+        [krate, serde_json, value] => {
+            krate == "easy_jsonrpc" && serde_json == "serde_json" && value == "Value"
+        }
+        _ => false,
+    }
+}
 
-    Ok(quote! {
-        // We originally used "mod" here. The problem was that modules can't access the
-        // namespace of their parent if their parent is a fn.
-        /// Helper module for jsonrpc clients. Automatically generated by easy-jsonrpc.
-        pub enum #mod_name {}
-        impl #mod_name {
-            #(#method_impls)*
+// Whether the method is annotated with #[rpc(skip)], which leaves it out of the generated code.
+// Skipping a method excludes it from everything else too, so skip must be its only option.
+fn is_skipped(attrs: &[Attribute]) -> Result<bool, Rejections> {
+    let options = rpc_options(attrs)?;
+    let is_skip = |option: &Meta| matches!(option, Meta::Path(path) if path.is_ident("skip"));
+    if !options.iter().any(is_skip) {
+        return Ok(false);
+    }
+    partition(options.iter().map(|option| {
+        if is_skip(option) {
+            Ok(())
+        } else {
+            Err(Rejection::create(option.span(), Reason::SkippedMethodOption).into())
         }
-    })
+    }))?;
+    Ok(true)
 }
 
-fn impl_client_method(method: &MethodSig) -> Result<TokenStream, Rejections> {
-    let method_name = &method.ident;
-    let method_name_literal = &method_name.to_string();
-    let args = get_args(&method.decl)?;
-    let fn_definition_args: &Vec<_> = &args
+// whether the method is marked #[rpc(instantiate(..))], the options are checked by
+// instantiations
+fn is_instantiated(attrs: &[Attribute]) -> Result<bool, Rejections> {
+    let options = rpc_options(attrs)?;
+    Ok(options
         .iter()
-        .enumerate()
-        .map(|(i, (name, typ))| {
-            let arg_num_name = Ident::new(&format!("arg{}", i), name.span());
-            quote! {#arg_num_name: #typ}
-        })
-        .collect();
-    let args_serialize: &Vec<_> = &args
+        .any(|option| matches!(option, Meta::List(list) if list.path.is_ident("instantiate"))))
+}
+
+// Join the lines of a doc comment with newlines, dropping the space following each "///".
+// Returns None when there are no doc attributes.
+fn doc_string(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
         .iter()
-        .enumerate()
-        .map(|(i, (name, _))| {
-            let arg_num_name = Ident::new(&format!("arg{}", i), name.span());
-            quote! {
-                easy_jsonrpc::serde_json::to_value(#arg_num_name).map_err(|_| easy_jsonrpc::ArgSerializeError)?
-            }
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(MetaNameValue {
+                value:
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(line),
+                        ..
+                    }),
+                ..
+            }) => Some(line.value()),
+            _ => None,
         })
-        .collect();
-    let return_typ = return_type(&method);
+        .map(|line| match line.strip_prefix(' ') {
+            Some(stripped) => stripped.to_string(),
+            None => line,
+        })
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
 
-    Ok(quote! {
-        /// Request generator for jsonrpc clients. Automatically generated by easy-jsonrpc.
-        pub fn #method_name ( #(#fn_definition_args,)* )
-                                 -> Result<easy_jsonrpc::BoundMethod<'static, #return_typ>, easy_jsonrpc::ArgSerializeError> {
-            Ok(easy_jsonrpc::BoundMethod::new(
-                #method_name_literal,
-                vec![ #(#args_serialize),* ],
-            ))
-        }
-    })
+fn is_rpc_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("rpc")
 }
 
-fn return_type_span(method: &MethodSig) -> Span {
-    let return_type = match &method.decl.output {
-        ReturnType::Default => None,
-        ReturnType::Type(_, typ) => Some(typ),
-    };
-    return_type
-        .map(|typ| typ.span())
-        .unwrap_or_else(|| method.decl.output.span().clone())
+// get the options from all attributes of the form #[rpc(option, option, ..)]
+fn rpc_options(attrs: &[Attribute]) -> Result<Vec<Meta>, Rejections> {
+    let options = partition(attrs.iter().filter(|attr| is_rpc_attr(attr)).map(|attr| {
+        match &attr.meta {
+            Meta::List(list) => list
+                .parse_args_with(Options::parse_terminated)
+                .map_err(|e| Rejection::create(e.span(), Reason::MalformedAttribute).into()),
+            other => Err(Rejection::create(other.span(), Reason::MalformedAttribute).into()),
+        }
+    }))?;
+    Ok(options.into_iter().flatten().collect())
 }
 
-fn return_type(method: &MethodSig) -> Type {
-    match &method.decl.output {
-        ReturnType::Default => Type::Tuple(TypeTuple {
-            paren_token: Paren {
-                span: method.decl.output.span(),
-            },
-            elems: Punctuated::new(),
-        }),
-        ReturnType::Type(_, typ) => *typ.clone(),
+// #[rpc(..)] attributes on methods and arguments are consumed by this macro, they must not be
+// emitted. Instantiated generic methods are bound by `Self: Sized`, keeping the trait object safe.
+fn strip_rpc_attrs(tr: &mut ItemTrait) {
+    for item in tr.items.iter_mut() {
+        if let TraitItem::Fn(method) = item {
+            if is_instantiated(&method.attrs).unwrap_or(false) {
+                let where_clause = method.sig.generics.make_where_clause();
+                where_clause
+                    .predicates
+                    .push(syn::parse_quote! { Self: Sized });
+            }
+            method.attrs.retain(|attr| !is_rpc_attr(attr));
+            for input in method.sig.inputs.iter_mut() {
+                if let FnArg::Typed(PatType { attrs, .. }) = input {
+                    attrs.retain(|attr| !is_rpc_attr(attr));
+                }
+            }
+        }
     }
 }
 
-// return all methods in the trait, or reject if trait contains an item that is not a method
-fn trait_methods<'a>(tr: &'a ItemTrait) -> Result<Vec<&'a MethodSig>, Rejections> {
+// return all methods in the trait except those marked skip, or reject if trait contains an item
+// that is not a method
+fn trait_methods<'a>(
+    tr: &'a ItemTrait,
+    options: &TraitOptions,
+) -> Result<Vec<RpcMethod<'a>>, Rejections> {
     let methods = partition(tr.items.iter().map(|item| match item {
-        TraitItem::Method(method) => Ok(&method.sig),
+        TraitItem::Fn(method) if is_skipped(&method.attrs)? => Ok(None),
+        // served by the concrete methods standing in for them, see impl_instantiations
+        TraitItem::Fn(method) if is_instantiated(&method.attrs)? => Ok(None),
+        TraitItem::Fn(method) => RpcMethod::parse(&method.attrs, &method.sig, options).map(Some),
+        // associated consts and types would keep the handler from being implemented for dyn Trait
+        TraitItem::Const(item) => {
+            Err(Rejection::create(item.span(), Reason::AssociatedConst).into())
+        }
+        TraitItem::Type(item) => Err(Rejection::create(item.span(), Reason::AssociatedType).into()),
+        TraitItem::Macro(item) => Err(Rejection::create(item.span(), Reason::MacroInTrait).into()),
         other => Err(Rejection::create(other.span(), Reason::TraitNotStrictlyMethods).into()),
     }))?;
-    partition(methods.iter().map(|method| {
-        if method.ident.to_string().starts_with("rpc.") {
-            Err(Rejection::create(method.ident.span(), Reason::ReservedMethodPrefix).into())
+    let methods: Vec<RpcMethod> = methods.into_iter().flatten().collect();
+    partition(jsonrpc_methods(&methods).map(|method| {
+        let reserved = options
+            .reserved_prefixes
+            .iter()
+            .any(|prefix| method.name.starts_with(prefix.as_str()));
+        if reserved && !method.allow_reserved {
+            Err(Rejection::create(method.name_span, Reason::ReservedMethodPrefix).into())
         } else {
             Ok(())
         }
     }))?;
+    let fallbacks = methods.iter().filter(|method| method.fallback);
+    let authorizers = methods.iter().filter(|method| method.authorize);
+    let hooks = methods.iter().filter(|method| method.before_dispatch);
+    let has_authorizer = authorizers.clone().next().is_some();
+    partition(
+        (fallbacks
+            .skip(1)
+            .map(|method| (method, Reason::MultipleFallbacks)))
+        .chain(
+            authorizers
+                .skip(1)
+                .map(|method| (method, Reason::MultipleAuthorizers)),
+        )
+        .chain(
+            hooks
+                .skip(1)
+                .map(|method| (method, Reason::MultipleBeforeDispatch)),
+        )
+        .chain(
+            methods
+                .iter()
+                .filter(|method| method.auth && !has_authorizer)
+                .map(|method| (method, Reason::MissingAuthorizer)),
+        )
+        // the handler calls these on every dispatch, they can't be compiled out
+        .chain(
+            methods
+                .iter()
+                .filter(|method| !method.cfg.is_empty())
+                .filter(|method| method.fallback || method.authorize || method.before_dispatch)
+                .map(|method| (method, Reason::ConditionalHook)),
+        )
+        .map(|(method, reason)| {
+            Err::<(), _>(Rejection::create(method.sig.ident.span(), reason).into())
+        }),
+    )?;
+    partition(methods.iter().map(reject_opaque_return))?;
+    partition(methods.iter().map(reject_generic_method))?;
+    partition(methods.iter().map(reject_misplaced_flatten))?;
+    partition(methods.iter().map(reject_misplaced_rest))?;
+    check_supertraits(tr, options)?;
     Ok(methods)
 }
 
+// Return position impl Trait makes the trait unusable as a trait object, which the generated
+// handler is implemented for. Clients also need a nameable type to deserialize the result into.
+fn reject_opaque_return(method: &RpcMethod) -> Result<(), Rejections> {
+    let return_type = match &method.sig.output {
+        ReturnType::Type(_, typ) => &**typ,
+        ReturnType::Default => return Ok(()),
+    };
+    match method.ok_type.unwrap_or(return_type) {
+        Type::ImplTrait(impl_trait) => {
+            Err(Rejection::create(impl_trait.span(), Reason::OpaqueReturnType).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+// A flattened argument takes all named parameters, leaving none for other arguments. The struct
+// of a params_struct method denies unknown fields, which serde's flatten does not support.
+fn reject_misplaced_flatten(method: &RpcMethod) -> Result<(), Rejections> {
+    let args = method.rpc_args()?;
+    partition(args.iter().filter(|arg| arg.flatten).map(|arg| {
+        if args.len() > 1 || method.params_struct {
+            Err(Rejection::create(arg.ident.span(), Reason::MisplacedFlatten).into())
+        } else {
+            Ok(())
+        }
+    }))?;
+    Ok(())
+}
+
+// Traits may only have lifetime parameters, which the generated handler impls are generic over.
+// The client helpers can't name them, so jsonrpc methods must not mention them. rpc_async traits
+// can't have any parameters.
+fn check_trait_generics(
+    tr: &ItemTrait,
+    methods: &[RpcMethod],
+    asyncness: bool,
+) -> Result<(), Rejections> {
+    let mut lifetimes = Vec::new();
+    partition(tr.generics.params.iter().map(|param| match param {
+        GenericParam::Lifetime(_) if asyncness => {
+            Err(Rejection::create(param.span(), Reason::AsyncTraitLifetime).into())
+        }
+        GenericParam::Lifetime(param) => {
+            lifetimes.push(&param.lifetime.ident);
+            Ok(())
+        }
+        param => Err(Rejection::create(param.span(), Reason::GenericTrait).into()),
+    }))?;
+    if lifetimes.is_empty() {
+        return Ok(());
+    }
+    partition(jsonrpc_methods(methods).map(|method| {
+        let inputs = &method.sig.inputs;
+        let output = &method.sig.output;
+        if mentions_lifetime(quote! { #inputs #output }, &lifetimes) {
+            Err(Rejection::create(method.sig.span(), Reason::TraitLifetimeInMethod).into())
+        } else {
+            Ok(())
+        }
+    }))?;
+    Ok(())
+}
+
+// whether any of the lifetimes named appears in tokens
+fn mentions_lifetime(tokens: TokenStream, names: &[&Ident]) -> bool {
+    let mut after_quote = false;
+    tokens.into_iter().any(|token| {
+        let is_lifetime = std::mem::replace(&mut after_quote, false);
+        match token {
+            TokenTree::Group(group) => mentions_lifetime(group.stream(), names),
+            TokenTree::Punct(punct) => {
+                after_quote = punct.as_char() == '\'';
+                false
+            }
+            TokenTree::Ident(ident) => is_lifetime && names.contains(&&ident),
+            TokenTree::Literal(_) => false,
+        }
+    })
+}
+
+// An argument capturing the extra named parameters comes last, so positional parameters can
+// leave it out. A params_struct denies unknown fields, there are no extras to capture.
+fn reject_misplaced_rest(method: &RpcMethod) -> Result<(), Rejections> {
+    let args = method.rpc_args()?;
+    partition(
+        args.iter()
+            .enumerate()
+            .filter(|(_, arg)| arg.rest)
+            .map(|(index, arg)| {
+                if index + 1 != args.len() || arg.flatten || method.params_struct {
+                    Err(Rejection::create(arg.ident.span(), Reason::MisplacedRest).into())
+                } else {
+                    Ok(())
+                }
+            }),
+    )?;
+    Ok(())
+}
+
+// Like return position impl Trait, type and const parameters make the trait unusable as a trait
+// object. Lifetime parameters are fine.
+fn reject_generic_method(method: &RpcMethod) -> Result<(), Rejections> {
+    partition(method.sig.generics.params.iter().map(|param| match param {
+        GenericParam::Lifetime(_) => Ok(()),
+        param => Err(Rejection::create(param.span(), Reason::GenericMethod).into()),
+    }))?;
+    Ok(())
+}
+
+// A concrete method standing in for an instantiation of a generic method, one entry of
+// #[rpc(instantiate(echo_string = "echo::<String>"))] on the generic method `echo`.
+struct Instantiation<'a> {
+    generic: &'a Signature,
+    concrete: Ident, // the method serving the instantiation
+    instance: syn::AngleBracketedGenericArguments, // the generic arguments, e.g. ::<String>
+}
+
+// get the instantiations listed by the generic methods of the trait
+fn instantiations(tr: &ItemTrait) -> Result<Vec<Instantiation<'_>>, Rejections> {
+    let generic_methods = tr.items.iter().filter_map(|item| match item {
+        TraitItem::Fn(method) => Some(method),
+        _ => None,
+    });
+    let mut instantiations = Vec::new();
+    for method in generic_methods {
+        if !is_instantiated(&method.attrs)? {
+            continue;
+        }
+        let generic = &method.sig;
+        if !generic
+            .generics
+            .params
+            .iter()
+            .any(|param| !matches!(param, GenericParam::Lifetime(_)))
+        {
+            return Err(
+                Rejection::create(generic.ident.span(), Reason::InstantiateConcrete).into(),
+            );
+        }
+        for option in rpc_options(&method.attrs)? {
+            let list = match option {
+                Meta::List(list) if list.path.is_ident("instantiate") => list,
+                other => {
+                    return Err(
+                        Rejection::create(other.span(), Reason::InstantiatedMethodOption).into(),
+                    )
+                }
+            };
+            let entries = list
+                .parse_args_with(Punctuated::<MetaNameValue, Token![,]>::parse_terminated)
+                .map_err(|e| Rejection::create(e.span(), Reason::MalformedInstantiation))?;
+            for entry in entries {
+                let concrete = entry.path.get_ident().cloned().ok_or_else(|| {
+                    Rejection::create(entry.path.span(), Reason::MalformedInstantiation)
+                })?;
+                let instance = match &entry.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(instance),
+                        ..
+                    }) => instance_arguments(generic, instance)?,
+                    other => {
+                        return Err(
+                            Rejection::create(other.span(), Reason::ExpectedStringLiteral).into(),
+                        )
+                    }
+                };
+                instantiations.push(Instantiation {
+                    generic,
+                    concrete,
+                    instance,
+                });
+            }
+        }
+    }
+    Ok(instantiations)
+}
+
+// the generic arguments of an instantiation of generic, spelled with a turbofish as in
+// "echo::<String>"
+fn instance_arguments(
+    generic: &Signature,
+    instance: &LitStr,
+) -> Result<syn::AngleBracketedGenericArguments, Rejections> {
+    let malformed = || Rejection::create(instance.span(), Reason::MalformedInstantiation);
+    let path = instance.parse::<syn::ExprPath>().map_err(|_| malformed())?;
+    match path.path.segments.iter().collect::<Vec<_>>()[..] {
+        [segment] if path.qself.is_none() && segment.ident == generic.ident => {
+            match &segment.arguments {
+                PathArguments::AngleBracketed(arguments) => Ok(arguments.clone()),
+                _ => Err(malformed().into()),
+            }
+        }
+        _ => Err(malformed().into()),
+    }
+}
+
+// Check each concrete method against the instantiation it stands in for. The handler calls the
+// concrete methods, which implementations usually forward to the generic method. The generated
+// functions call the instantiation in place of each concrete method, failing to compile if their
+// signatures are not the same.
+fn impl_instantiations(tr: &ItemTrait, methods: &[RpcMethod]) -> Result<TokenStream, Rejections> {
+    let trait_name = &tr.ident;
+    let trait_lifetimes = tr.generics.params.iter();
+    let (_, ty_generics, _) = tr.generics.split_for_impl();
+    let checks = partition(instantiations(tr)?.into_iter().map(|instantiation| {
+        let Instantiation {
+            generic,
+            concrete,
+            instance,
+        } = instantiation;
+        let method = jsonrpc_methods(methods)
+            .find(|method| method.sig.ident == concrete)
+            .ok_or_else(|| Rejection::create(concrete.span(), Reason::UnknownInstantiation))?;
+        let sig = method.sig;
+        let cfg = &method.cfg;
+        let generic_name = &generic.ident;
+        let method_lifetimes = sig.generics.params.iter();
+        let trait_lifetimes = trait_lifetimes.clone();
+        let this = if takes_mut_self(sig) {
+            quote! { &mut S }
+        } else {
+            quote! { &S }
+        };
+        let arg_types = sig.inputs.iter().filter_map(|input| match input {
+            FnArg::Typed(PatType { ty, .. }) => Some(ty),
+            FnArg::Receiver(_) => None,
+        });
+        let arg_names = (0..arg_types.clone().count())
+            .map(|index| Ident::new(&format!("arg{}", index), sig.ident.span()))
+            .collect::<Vec<_>>();
+        let output = &sig.output;
+        let (asyncness, await_call) = match sig.asyncness {
+            Some(_) => (quote! { async }, quote! { .await }),
+            None => (quote! {}, quote! {}),
+        };
+        Ok(quote_spanned! {sig.ident.span()=>
+            #cfg
+            #[allow(non_snake_case, dead_code, clippy::too_many_arguments)]
+            #asyncness fn #concrete<#(#method_lifetimes,)* #(#trait_lifetimes,)* S: #trait_name #ty_generics>(
+                this: #this,
+                #(#arg_names: #arg_types),*
+            ) #output {
+                this.#generic_name #instance(#(#arg_names),*) #await_call
+            }
+        })
+    }))?;
+    if checks.is_empty() {
+        return Ok(quote! {});
+    }
+    Ok(quote! {
+        const _: () = {
+            #(#checks)*
+        };
+    })
+}
+
+// reject supertraits passed to #[rpc(supertraits(..))] which are not declared as supertraits
+fn check_supertraits(tr: &ItemTrait, options: &TraitOptions) -> Result<(), Rejections> {
+    let declared = |listed: &Path| {
+        tr.supertraits.iter().any(|bound| match bound {
+            TypeParamBound::Trait(bound) => {
+                bound.path.segments.last().map(|last| &last.ident)
+                    == listed.segments.last().map(|last| &last.ident)
+            }
+            _ => false,
+        })
+    };
+    partition(options.supertraits.iter().map(|listed| {
+        if declared(listed) {
+            Ok(())
+        } else {
+            Err(Rejection::create(listed.span(), Reason::UndeclaredSupertrait).into())
+        }
+    }))
+    .map(|_| ())
+}
+
 // generate code that parses rpc arguments and calls the given method
-fn add_handler(trait_name: &Ident, method: &MethodSig) -> Result<TokenStream, Rejections> {
-    let method_name = &method.ident;
-    let args = get_args(&method.decl)?;
-    let arg_name_literals = args.iter().map(|(id, _)| id.to_string());
-    let parse_args = args.iter().enumerate().map(|(index, (ident, ty))| {
-        let argname_literal = format!("\"{}\"", ident);
+fn add_handler(trait_name: &Ident, method: &RpcMethod) -> Result<TokenStream, Rejections> {
+    let method_name = &method.sig.ident;
+    let args = method.rpc_args()?;
+    let arg_name_literals = args.iter().map(|arg| &arg.name);
+    let required = args.iter().filter(|arg| !arg.default).count();
+    let parse_args = args.iter().enumerate().map(|(index, arg)| {
+        let argname_literal = &arg.name;
+        let ty = arg.ty;
         // non-lexical lifetimes make it possible to create a reference to an anonymous owned value
-        let prefix = match ty {
-            syn::Type::Reference(_) => quote! { & },
-            _ => quote! {},
+        let (prefix, turbofish) = match ty {
+            syn::Type::Reference(reference) => {
+                let owned = owned_arg_type(reference);
+                (quote! { & }, quote! { ::<#owned> })
+            }
+            _ => (quote! {}, quote! {}),
+        };
+        let from_value = if arg.base64.is_some() {
+            quote! { easy_jsonrpc::from_value_base64 }
+        } else if method.lenient_numbers && is_numeric(ty) {
+            quote! { easy_jsonrpc::from_value_lenient }
+        } else {
+            quote! { easy_jsonrpc::serde_json::from_value #turbofish }
+        };
+        let parse = quote_spanned! { ty.span() =>
+            #from_value(next_arg).map_err(|e| {
+                easy_jsonrpc::Error::from(easy_jsonrpc::InvalidArgs::InvalidArgStructure {
+                    name: #argname_literal,
+                    index: #index,
+                    detail: e.to_string(),
+                })
+            })?
+        };
+        let parse_or_default = if arg.default {
+            quote_spanned! { ty.span() =>
+                match next_arg {
+                    Some(next_arg) => #parse,
+                    None => ::std::default::Default::default(),
+                }
+            }
+        } else {
+            quote_spanned! { ty.span() =>
+                let next_arg = next_arg.expect(
+                    "RPC method Got too few args. This is a bug." // checked in get_rpc_args
+                );
+                #parse
+            }
         };
         quote_spanned! { ty.span() => #prefix {
             let next_arg = ordered_args.next().expect(
                 "RPC method Got too few args. This is a bug." // checked in get_rpc_args
             );
-            easy_jsonrpc::serde_json::from_value(next_arg).map_err(|_| {
-                easy_jsonrpc::InvalidArgs::InvalidArgStructure {
-                    name: #argname_literal,
-                    index: #index,
-                }.into()
-            })?
+            #parse_or_default
         }}
     });
 
+    let await_call = match method.sig.asyncness {
+        Some(_) => quote! { .await },
+        None => quote! {},
+    };
+    let id_arg = if method.with_id {
+        quote! { ::std::clone::Clone::clone(id), }
+    } else {
+        quote! {}
+    };
+    let ctx_arg = context_arg_token(method)?;
+    let id_arg = quote! { #id_arg #ctx_arg };
+    let check_shape = match method.params {
+        ParamsShape::Any => quote! {},
+        ParamsShape::Named => quote! {
+            params.expect_named().map_err(easy_jsonrpc::Error::from)?;
+        },
+        ParamsShape::Positional => quote! {
+            params.expect_positional().map_err(easy_jsonrpc::Error::from)?;
+        },
+    };
+
+    // Option arguments absent from named parameters are passed as None
+    let optional_names = args
+        .iter()
+        .filter(|arg| !arg.default && !arg.flatten && !arg.rest && is_option(arg.ty))
+        .map(|arg| &arg.name)
+        .collect::<Vec<_>>();
+    let check_shape = if optional_names.is_empty() {
+        check_shape
+    } else {
+        quote! {
+            #check_shape
+            let params = params.fill_missing_named(&[#(#optional_names),*]);
+        }
+    };
+    // named parameters taken by no other argument are gathered into the rest argument
+    let check_shape = match args.iter().find(|arg| arg.rest) {
+        Some(rest) => {
+            let rest_name = &rest.name;
+            let names = args.iter().filter(|arg| !arg.rest).map(|arg| &arg.name);
+            quote! {
+                #check_shape
+                let params = params.gather_rest(&[#(#names),*], #rest_name);
+            }
+        }
+        None => check_shape,
+    };
+    // named parameters are passed as the single positional parameter of a flattened argument
+    let check_shape = if args.iter().any(|arg| arg.flatten) {
+        quote! {
+            #check_shape
+            let params = params.flatten_named();
+        }
+    } else {
+        check_shape
+    };
+
+    if method.params_struct {
+        let struct_name = params_struct_name(method);
+        let pass_args = args.iter().map(|arg| {
+            let ident = arg.ident;
+            match arg.ty {
+                Type::Reference(_) => quote! { &args.#ident },
+                _ => quote! { args.#ident },
+            }
+        });
+        return Ok(quote! {{
+            #check_shape
+            let args: #struct_name = params.deserialize().map_err(easy_jsonrpc::Error::from)?;
+            // call the target procedure
+            <dyn #trait_name>::#method_name(self, #id_arg #(#pass_args),*) #await_call
+        }});
+    }
+
+    let lenient = method.lenient_numbers && args.iter().any(|arg| is_numeric(arg.ty));
+    let base64 = args.iter().any(|arg| arg.base64.is_some());
+    // serde deserializes tuples of up to 16 elements, wider methods take their arguments one by one
+    let fits_tuple = !args.is_empty() && args.len() <= 16;
+    if method.tuple_args && !lenient && !base64 && fits_tuple && required == args.len() {
+        let handler = add_tuple_handler(trait_name, method, &args, id_arg, await_call);
+        return Ok(quote! {{
+            #check_shape
+            #handler
+        }});
+    }
+
     Ok(quote! {{
-        let mut args: Vec<easy_jsonrpc::Value> =
-            params.get_rpc_args(&[#(#arg_name_literals),*])
-                .map_err(|a| a.into())?;
+        #check_shape
+        let mut args: Vec<Option<easy_jsonrpc::Value>> =
+            params.get_optional_rpc_args(&[#(#arg_name_literals),*], #required)
+                .map_err(easy_jsonrpc::Error::from)?;
         let mut ordered_args = args.drain(..);
-        let res = <#trait_name>::#method_name(self, #(#parse_args),*); // call the target procedure
+        // call the target procedure
+        let res = <dyn #trait_name>::#method_name(self, #id_arg #(#parse_args),*) #await_call;
         debug_assert_eq!(ordered_args.next(), None); // parse_args must consume ordered_args
         res
     }})
 }
 
-// Get the name and type of each argument from method. Skip the first argument, which must be &self.
-// If the first argument is not &self, an error will be returned.
-fn get_args<'a>(method: &'a FnDecl) -> Result<Vec<(&'a Ident, &'a Type)>, Rejections> {
+// like add_handler, but deserializes all arguments in a single pass as a tuple
+fn add_tuple_handler(
+    trait_name: &Ident,
+    method: &RpcMethod,
+    args: &[RpcArg],
+    id_arg: TokenStream,
+    await_call: TokenStream,
+) -> TokenStream {
+    let method_name = &method.sig.ident;
+    let arg_name_literals = args.iter().map(|arg| &arg.name);
+    let arg_names = args.iter().map(|arg| &arg.name);
+    let bindings = (0..args.len())
+        .map(|index| Ident::new(&format!("arg{}", index), Span::call_site()))
+        .collect::<Vec<_>>();
+    // reference arguments are deserialized as owned values and borrowed when calling the method
+    let owned_types = args.iter().map(|arg| match arg.ty {
+        Type::Reference(reference) => owned_arg_type(reference),
+        ty => quote! { #ty },
+    });
+    let pass_args = args
+        .iter()
+        .zip(&bindings)
+        .map(|(arg, binding)| match arg.ty {
+            Type::Reference(_) => quote! { &#binding },
+            _ => quote! { #binding },
+        });
+
+    quote! {{
+        let args: Vec<easy_jsonrpc::Value> = params
+            .get_rpc_args(&[#(#arg_name_literals),*])
+            .map_err(easy_jsonrpc::Error::from)?;
+        let (#(#bindings,)*): (#(#owned_types,)*) =
+            easy_jsonrpc::deserialize_args(args).map_err(|(index, detail)| {
+                easy_jsonrpc::Error::from(easy_jsonrpc::InvalidArgs::InvalidArgStructure {
+                    name: [#(#arg_names),*][index],
+                    index,
+                    detail,
+                })
+            })?;
+        // call the target procedure
+        <dyn #trait_name>::#method_name(self, #id_arg #(#pass_args),*) #await_call
+    }}
+}
+
+// The type a reference argument is deserialized as before being borrowed. Unsized types are
+// deserialized as their owned counterpart, &str as String and &[T] as Vec<T>.
+fn owned_arg_type(reference: &TypeReference) -> TokenStream {
+    match &*reference.elem {
+        Type::Path(TypePath { qself: None, path }) if path.is_ident("str") => {
+            quote! { ::std::string::String }
+        }
+        Type::Slice(slice) => {
+            let elem = &slice.elem;
+            quote! { ::std::vec::Vec<#elem> }
+        }
+        elem => quote! { #elem },
+    }
+}
+
+// whether the first argument to method is &mut self
+fn takes_mut_self(method: &Signature) -> bool {
+    match method.inputs.iter().next() {
+        Some(FnArg::Receiver(Receiver {
+            reference: Some(_),
+            mutability,
+            ..
+        })) => mutability.is_some(),
+        _ => false,
+    }
+}
+
+// Get the name and type of each argument from method. Skip the first argument, which must be &self
+// or &mut self. If the first argument is not a self reference, an error will be returned.
+fn get_args(method: &Signature) -> Result<Vec<RpcArg<'_>>, Rejections> {
     let mut inputs = method.inputs.iter();
     match inputs.next() {
-        Some(FnArg::SelfRef(ArgSelfRef {
-            mutability: None, ..
+        Some(FnArg::Receiver(Receiver {
+            reference: Some(_), ..
         })) => Ok(()),
+        // `self` or `mut self`
+        Some(FnArg::Receiver(Receiver {
+            self_token,
+            colon_token: None,
+            ..
+        })) => Err(Rejection::create(self_token.span(), Reason::SelfByValue)),
         Some(a) => Err(Rejection::create(a.span(), Reason::FirstArgumentNotSelfRef)),
         None => Err(Rejection::create(
             method.inputs.span(),
             Reason::FirstArgumentNotSelfRef,
         )),
     }?;
-    partition(inputs.map(as_jsonrpc_arg))
+    let args = partition(inputs.map(as_jsonrpc_arg))?;
+    // named parameters are looked up by name, so each name must belong to a single argument
+    partition(args.iter().enumerate().map(|(index, arg)| {
+        if args[..index].iter().any(|earlier| earlier.name == arg.name) {
+            Err(Rejection::create(arg.name_span, Reason::DuplicateArgName).into())
+        } else {
+            Ok(())
+        }
+    }))?;
+    Ok(args)
 }
 
 // If all Ok, return Vec of successful values, otherwise return all Rejections.
@@ -266,14 +3105,14 @@ fn partition<K, I: Iterator<Item = Result<K, Rejections>>>(iter: I) -> Result<Ve
     }
 }
 
-// Attempt to extract name and type from arg
-fn as_jsonrpc_arg(arg: &FnArg) -> Result<(&Ident, &Type), Rejections> {
+// Attempt to extract name, type and options from arg
+fn as_jsonrpc_arg(arg: &FnArg) -> Result<RpcArg<'_>, Rejections> {
     let arg = match arg {
-        FnArg::Captured(captured) => Ok(captured),
+        FnArg::Typed(typed) => Ok(typed),
         a => Err(Rejection::create(a.span(), Reason::ConcreteTypesRequired)),
     }?;
-    let ty = &arg.ty;
-    let pat_ident = match &arg.pat {
+    let ty = &*arg.ty;
+    let pat_ident = match &*arg.pat {
         Pat::Ident(pat_ident) => Ok(pat_ident),
         a => Err(Rejection::create(a.span(), Reason::PatternMatchedArg)),
     }?;
@@ -294,9 +3133,10 @@ fn as_jsonrpc_arg(arg: &FnArg) -> Result<(&Ident, &Type), Rejections> {
             by_ref: None,
             mutability: None,
             subpat: None,
+            ..
         } => Ok(ident),
     }?;
-    Ok((&ident, &ty))
+    RpcArg::parse(&arg.attrs, ident, ty)
 }
 
 // returned when macro input is invalid
@@ -310,12 +3150,61 @@ struct Rejection {
 #[derive(Clone, Copy)]
 enum Reason {
     FirstArgumentNotSelfRef,
+    SelfByValue,
     PatternMatchedArg,
     ConcreteTypesRequired,
     TraitNotStrictlyMethods,
+    AssociatedConst,
+    SkippedMethodOption,
+    AssociatedType,
+    MacroInTrait,
     ReservedMethodPrefix,
     ReferenceArg,
     MutableArg,
+    MalformedAttribute,
+    UnknownOption,
+    UnknownTraitOption,
+    UnknownArgOption,
+    UnknownErrorCodeOption,
+    ExpectedStringLiteral,
+    ExpectedIntegerLiteral,
+    ErrorCodeNotEnum,
+    UnsupportedRequestEnum,
+    MisplacedFlatten,
+    MisplacedRest,
+    MissingErrorCode,
+    MissingIdArg,
+    AsyncMethod,
+    SyncMethod,
+    AsyncMutSelf,
+    AsyncTraitNotSync,
+    RequiredArgAfterDefault,
+    DuplicateArgName,
+    UndeclaredSupertrait,
+    MultipleFallbacks,
+    MultipleAuthorizers,
+    MultipleBeforeDispatch,
+    UnsupportedBeforeDispatch,
+    MissingAuthorizer,
+    OpaqueReturnType,
+    MethodConstantCollision,
+    UnknownParamsShape,
+    UnknownBase64Alphabet,
+    ConditionalHook,
+    MisplacedBase64,
+    GenericMethod,
+    InstantiateConcrete,
+    InstantiatedMethodOption,
+    MalformedInstantiation,
+    UnknownInstantiation,
+    GenericTrait,
+    AsyncTraitLifetime,
+    TraitLifetimeInMethod,
+    ContextArgNotReference,
+    MisplacedContextArg,
+    MismatchedContextTypes,
+    ContextMutHandler,
+    ContextAsyncHandler,
 }
 
 // Rustc often reports whole batches of errors at once. We can do the same by returning lists of
@@ -355,7 +3244,13 @@ impl Rejection {
     // generate a compile_err!() from self
     fn raise(self) -> TokenStream {
         let description = match self.reason {
-            Reason::FirstArgumentNotSelfRef => "First argument to jsonrpc method must be &self.",
+            Reason::FirstArgumentNotSelfRef => {
+                "First argument to jsonrpc method must be &self or &mut self."
+            }
+            Reason::SelfByValue => {
+                "Jsonrpc methods can't take self by value, the handler calls them through a \
+                 `dyn Trait` reference. Take `&self`, or `&mut self` to mutate."
+            }
             Reason::PatternMatchedArg => {
                 "Pattern matched arguments are not supported in jsonrpc methods."
             }
@@ -365,11 +3260,160 @@ impl Rejection {
             Reason::TraitNotStrictlyMethods => {
                 "Macro 'jsonrpc_server' expects trait definition containing methods only."
             }
+            Reason::SkippedMethodOption => {
+                "Methods marked #[rpc(skip)] are not exposed over jsonrpc, other options have no \
+                 effect on them."
+            }
+            Reason::AssociatedConst => {
+                "Associated consts are not supported in jsonrpc traits, they make the trait \
+                 unusable as a trait object. Use a method returning the value or a free const \
+                 instead."
+            }
+            Reason::AssociatedType => {
+                "Associated types are not supported in jsonrpc traits, they make the trait \
+                 unusable as a trait object without naming the type. Use concrete types instead."
+            }
+            Reason::MacroInTrait => {
+                "Macro invocations are not supported in jsonrpc traits, the macro can't see the \
+                 methods they expand to."
+            }
             Reason::ReservedMethodPrefix => {
-                "The prefix 'rpc.' is reserved https://www.jsonrpc.org/specification#request_object"
+                "This method name starts with a reserved prefix, either 'rpc.' which is reserved \
+                 https://www.jsonrpc.org/specification#request_object or one listed in \
+                 #[rpc(reserved_prefixes(..))]. Annotate the method with #[rpc(allow_reserved)] \
+                 to expose it anyway."
+            }
+            Reason::ReferenceArg => {
+                "ref bindings are not supported in jsonrpc macro. Take an argument of reference \
+                 type instead, e.g. `arg: &T`."
             }
-            Reason::ReferenceArg => "Reference arguments not supported in jsonrpc macro.",
             Reason::MutableArg => "Mutable arguments not supported in jsonrpc macro.",
+            Reason::MalformedAttribute => "Expected an attribute of the form #[rpc(option, ..)].",
+            Reason::UnknownOption => "Unknown option for jsonrpc method.",
+            Reason::UnknownTraitOption => "Unknown option for jsonrpc trait.",
+            Reason::UnknownArgOption => "Unknown option for jsonrpc method argument.",
+            Reason::UnknownErrorCodeOption => {
+                "Unknown option for #[rpc_error], expected code or label."
+            }
+            Reason::ExpectedStringLiteral => "Expected a string literal.",
+            Reason::ExpectedIntegerLiteral => "Expected an integer literal.",
+            Reason::ErrorCodeNotEnum => "RpcErrorCode can only be derived for enums.",
+            Reason::UnsupportedRequestEnum => {
+                "#[rpc(request_enum)] does not support async traits, methods marked with_id, \
+                 context arguments, flattened arguments or rest arguments."
+            }
+            Reason::MisplacedRest => {
+                "A rest argument must be the last argument passed by clients, and can't be \
+                 flattened or part of a #[rpc(params_struct)]."
+            }
+            Reason::MisplacedFlatten => {
+                "A flattened argument must be the only argument passed by clients, and can't be \
+                 part of a #[rpc(params_struct)]."
+            }
+            Reason::MissingErrorCode => {
+                "Each variant must be given an error code, e.g. #[rpc_error(code = -32010)]."
+            }
+            Reason::MissingIdArg => {
+                "Methods marked with_id must take the request id as their first argument after self."
+            }
+            Reason::AsyncMethod => {
+                "Async methods are only supported in traits annotated with #[rpc(async)]."
+            }
+            Reason::SyncMethod => {
+                "Methods of traits annotated with #[rpc(async)] must all be async, mixing async \
+                 and blocking methods is not supported."
+            }
+            Reason::AsyncMutSelf => "Async jsonrpc methods must take &self.",
+            Reason::AsyncTraitNotSync => {
+                "Traits annotated with #[rpc(async)] must have Sync as a supertrait."
+            }
+            Reason::RequiredArgAfterDefault => {
+                "Required arguments must not follow arguments marked #[rpc(default)]."
+            }
+            Reason::DuplicateArgName => {
+                "Another argument of this method already has this name. Arguments are looked up \
+                 by name when called with named parameters, use #[rpc(rename = \"...\")] to \
+                 give each a distinct name."
+            }
+            Reason::UndeclaredSupertrait => "Expected a supertrait of the annotated trait.",
+            Reason::MultipleFallbacks => "Only one method may be marked #[rpc(fallback)].",
+            Reason::MultipleAuthorizers => "Only one method may be marked #[rpc(authorize)].",
+            Reason::MultipleBeforeDispatch => {
+                "Only one method may be marked #[rpc(before_dispatch)]."
+            }
+            Reason::UnsupportedBeforeDispatch => {
+                "#[rpc(before_dispatch)] is not supported by async traits or traits taking a \
+                 request context, use #[rpc(authorize)] and #[rpc(auth)] instead."
+            }
+            Reason::MethodConstantCollision => {
+                "The constant generated for the name of this method collides with the constant \
+                 for the name of another method."
+            }
+            Reason::OpaqueReturnType => {
+                "jsonrpc methods can't return impl Trait. The generated handler requires the trait \
+                 to be object safe and clients need a concrete type to deserialize results into."
+            }
+            Reason::MissingAuthorizer => {
+                "Methods marked #[rpc(auth)] require a method marked #[rpc(authorize)]."
+            }
+            Reason::ContextArgNotReference => {
+                "The context argument must be a reference, e.g. `#[rpc(context)] ctx: &Session`."
+            }
+            Reason::MisplacedContextArg => {
+                "The context argument must be the first argument after self, or after the id for \
+                 methods marked with_id."
+            }
+            Reason::MismatchedContextTypes => {
+                "All context arguments in a trait must have the same type."
+            }
+            Reason::ContextMutHandler => {
+                "Context arguments are not supported in traits with methods that take &mut self."
+            }
+            Reason::ContextAsyncHandler => "Context arguments are not supported by rpc_async.",
+            Reason::GenericMethod => {
+                "jsonrpc methods can't have type or const parameters. The generated handler \
+                 requires the trait to be object safe. Declare a method for each type instead, \
+                 e.g. `fn echo_string(&self, v: String) -> String`, and list them on the generic \
+                 method with #[rpc(instantiate(echo_string = \"echo::<String>\"))]."
+            }
+            Reason::InstantiateConcrete => {
+                "#[rpc(instantiate(..))] applies to methods with type or const parameters."
+            }
+            Reason::InstantiatedMethodOption => {
+                "Methods marked #[rpc(instantiate(..))] are served by the methods they list, \
+                 other options have no effect on them."
+            }
+            Reason::MalformedInstantiation => {
+                "Expected a method name along with the instantiation it serves, e.g. \
+                 #[rpc(instantiate(echo_string = \"echo::<String>\"))]."
+            }
+            Reason::UnknownInstantiation => {
+                "Expected a jsonrpc method of this trait serving the instantiation, e.g. \
+                 `fn echo_string(&self, v: String) -> String`."
+            }
+            Reason::GenericTrait => {
+                "Traits with type or const parameters are not supported. The generated handler is \
+                 implemented for `dyn Trait` and the client helpers can't name the parameters."
+            }
+            Reason::AsyncTraitLifetime => {
+                "rpc_async traits can't have lifetime parameters. Lifetime parameters are only \
+                 supported by #[rpc]."
+            }
+            Reason::TraitLifetimeInMethod => {
+                "jsonrpc methods can't mention the lifetime parameters of the trait, the generated \
+                 client helpers can't name them. Take and return owned types, or mark the method \
+                 #[rpc(skip)] to keep it out of the jsonrpc api."
+            }
+            Reason::UnknownParamsShape => "Expected \"named\" or \"positional\".",
+            Reason::UnknownBase64Alphabet => "Expected \"standard\" or \"url_safe\".",
+            Reason::ConditionalHook => {
+                "Methods marked fallback, authorize or before_dispatch can't have #[cfg] \
+                 attributes."
+            }
+            Reason::MisplacedBase64 => {
+                "#[rpc(base64)] applies to arguments holding bytes, like Vec<u8> and &[u8]. It \
+                 can't be combined with context, flatten or rest."
+            }
         };
 
         syn::Error::new(self.span, description).to_compile_error()
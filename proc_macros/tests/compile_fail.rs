@@ -0,0 +1,7 @@
+// The diagnostics reported for invalid macro input, each case in tests/ui along with the errors
+// it is expected to fail with. Run with TRYBUILD=overwrite to update the expected errors.
+
+#[test]
+fn ui() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,8 @@
+// Named parameters are looked up by name, so each argument needs a name of its own.
+
+#[easy_jsonrpc::rpc]
+pub trait Api {
+    fn move_to(&self, x: i64, #[rpc(rename = "x")] y: i64);
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+// Type and const parameters make the trait unusable as dyn Api, lifetime parameters are fine.
+
+#[easy_jsonrpc::rpc]
+pub trait Api {
+    fn echo<T: easy_jsonrpc::serde::Serialize>(&self, v: T) -> T;
+    fn repeat<const N: usize>(&self, v: u8) -> Vec<u8>;
+    fn first<'a>(&self, v: Vec<String>) -> Option<String>;
+}
+
+fn main() {}
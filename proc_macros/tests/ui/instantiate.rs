@@ -0,0 +1,41 @@
+// #[rpc(instantiate(..))] lists the concrete methods serving the instantiations of a generic
+// method, each must be a jsonrpc method of the trait with the signature of its instantiation.
+
+use easy_jsonrpc::serde::{de::DeserializeOwned, Serialize};
+
+#[easy_jsonrpc::rpc]
+pub trait NotGeneric {
+    #[rpc(instantiate(echo_string = "echo::<String>"))]
+    fn echo(&self, v: String) -> String;
+}
+
+#[easy_jsonrpc::rpc]
+pub trait OtherMethod {
+    #[rpc(instantiate(echo_string = "repeat::<String>"))]
+    fn echo<T: Serialize + DeserializeOwned>(&self, v: T) -> T;
+    fn echo_string(&self, v: String) -> String;
+}
+
+#[easy_jsonrpc::rpc]
+pub trait OtherOption {
+    #[rpc(instantiate(echo_string = "echo::<String>"), name = "echo")]
+    fn echo<T: Serialize + DeserializeOwned>(&self, v: T) -> T;
+    fn echo_string(&self, v: String) -> String;
+}
+
+#[easy_jsonrpc::rpc]
+pub trait Undeclared {
+    #[rpc(instantiate(echo_string = "echo::<String>", echo_u8 = "echo::<u8>"))]
+    fn echo<T: Serialize + DeserializeOwned>(&self, v: T) -> T;
+    #[rpc(skip)]
+    fn echo_string(&self, v: String) -> String;
+}
+
+#[easy_jsonrpc::rpc]
+pub trait Mismatched {
+    #[rpc(instantiate(echo_u8 = "echo::<u8>"))]
+    fn echo<T: Serialize + DeserializeOwned>(&self, v: T) -> T;
+    fn echo_u8(&self, v: u16) -> u16;
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+// Associated consts and types keep the handler from being implemented for dyn Api, and the
+// methods a macro expands to can't be seen.
+
+macro_rules! methods {
+    () => {
+        fn pong(&self);
+    };
+}
+
+#[easy_jsonrpc::rpc]
+pub trait Api {
+    const VERSION: u32;
+    type Output;
+    methods!();
+    fn ping(&self);
+}
+
+fn main() {}
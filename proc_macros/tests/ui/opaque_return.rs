@@ -0,0 +1,9 @@
+// The handler is implemented for dyn Api, which return position impl Trait rules out.
+
+#[easy_jsonrpc::rpc]
+pub trait Api {
+    fn answer(&self) -> impl easy_jsonrpc::serde::Serialize;
+    fn checked(&self) -> Result<impl easy_jsonrpc::serde::Serialize, String>;
+}
+
+fn main() {}
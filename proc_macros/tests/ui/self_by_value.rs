@@ -0,0 +1,8 @@
+// The handler calls methods through a dyn Api reference, so self can't be taken by value.
+
+#[easy_jsonrpc::rpc]
+pub trait Api {
+    fn consume(self);
+}
+
+fn main() {}
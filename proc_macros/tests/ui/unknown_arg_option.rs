@@ -0,0 +1,6 @@
+#[easy_jsonrpc::rpc]
+pub trait Api {
+    fn greet(&self, #[rpc(default, bogus)] name: Option<String>) -> String;
+}
+
+fn main() {}
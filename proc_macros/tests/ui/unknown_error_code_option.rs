@@ -0,0 +1,13 @@
+#[derive(Debug, easy_jsonrpc::RpcErrorCode)]
+enum WalletError {
+    #[rpc_error(code = -32010, bogus = "x")]
+    Locked,
+}
+
+impl std::fmt::Display for WalletError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("locked")
+    }
+}
+
+fn main() {}
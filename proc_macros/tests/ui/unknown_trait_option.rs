@@ -0,0 +1,6 @@
+#[easy_jsonrpc::rpc(client, bogus)]
+pub trait Api {
+    fn ping(&self);
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+// Return types that don't implement Serialize are reported at the return type.
+
+#[derive(easy_jsonrpc::serde::Deserialize)]
+#[serde(crate = "easy_jsonrpc::serde")]
+pub struct Opaque;
+
+#[easy_jsonrpc::rpc]
+pub trait Api {
+    fn opaque(&self) -> Opaque;
+    fn checked(&self) -> Result<Vec<Opaque>, String>;
+}
+
+fn main() {}
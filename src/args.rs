@@ -0,0 +1,448 @@
+// The parameters of calls, their checking against the arguments of methods and the serialization
+// of results. Needs only alloc, so it is all that is left without the "std" feature.
+
+use alloc::{
+    borrow::ToOwned,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+#[doc(hidden)]
+#[derive(
+    Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, serde::Serialize, serde::Deserialize,
+)]
+pub enum InvalidArgs {
+    WrongNumberOfArgs {
+        expected: usize,
+        actual: usize,
+    },
+    ExtraNamedParameter {
+        names: Vec<String>, // sorted by name, whatever the order they were sent in
+    },
+    MissingNamedParameter {
+        names: Vec<&'static str>,
+    },
+    DuplicateNamedParameter {
+        names: Vec<String>,
+    },
+    InvalidArgStructure {
+        name: &'static str,
+        index: usize,
+        detail: String, // the error reported by serde_json
+    },
+    NamedParamsRequired,
+    PositionalParamsRequired,
+    InvalidParamsStructure {
+        detail: String, // the error reported by serde_json
+    },
+}
+
+/// Represetaion of jsonrpc arguments. Passing no arguments is assumed to be semantically equivalent
+/// to passing 0 positional args, or passing a map with zero entries.
+///
+/// Users of this library will rarely need to deal with this type.
+#[derive(Clone, Debug)]
+pub enum Params {
+    /// Arguments were either not present (expressed as a length 0 list), or arguments were provided as
+    /// a json list.
+    Positional(Vec<Value>),
+    /// Arguments were provided as a json dictionary.
+    Named(serde_json::Map<String, Value>),
+}
+
+impl Params {
+    #[cfg(feature = "std")]
+    pub(crate) fn from_rc_params(params: jsonrpc_core::Params) -> Self {
+        match params {
+            jsonrpc_core::Params::Array(arr) => Params::Positional(arr),
+            jsonrpc_core::Params::Map(map) => Params::Named(map),
+            jsonrpc_core::Params::None => Params::Positional(vec![]),
+        }
+    }
+
+    /// Verify that arguments were provided as a json dictionary. Passing no arguments is accepted
+    /// as well.
+    pub fn expect_named(&self) -> Result<(), InvalidArgs> {
+        match self {
+            Params::Positional(ar) if !ar.is_empty() => Err(InvalidArgs::NamedParamsRequired),
+            _ => Ok(()),
+        }
+    }
+
+    /// Verify that arguments were provided as a json list. Passing no arguments is accepted as
+    /// well.
+    pub fn expect_positional(&self) -> Result<(), InvalidArgs> {
+        match self {
+            Params::Named(map) if !map.is_empty() => Err(InvalidArgs::PositionalParamsRequired),
+            _ => Ok(()),
+        }
+    }
+
+    /// The parameters as a json list, or a json dictionary if named.
+    pub fn into_value(self) -> Value {
+        match self {
+            Params::Positional(ar) => Value::Array(ar),
+            Params::Named(ma) => Value::Object(ma),
+        }
+    }
+
+    /// Deserialize the parameters as a whole, named parameters as a json dictionary and positional
+    /// parameters as a json list. Used for methods marked `#[rpc(params_struct)]`.
+    pub fn deserialize<T: DeserializeOwned>(self) -> Result<T, InvalidArgs> {
+        serde_json::from_value(self.into_value()).map_err(|e| InvalidArgs::InvalidParamsStructure {
+            detail: e.to_string(),
+        })
+    }
+
+    /// Named parameters as a single positional parameter holding them all, a json dictionary.
+    /// Used for methods with an argument marked `#[rpc(flatten)]`. Passing no parameters is like
+    /// passing no named parameters, an empty dictionary. Other positional parameters are returned
+    /// as is.
+    pub fn flatten_named(self) -> Params {
+        match self {
+            Params::Named(ma) => Params::Positional(vec![Value::Object(ma)]),
+            Params::Positional(ar) if ar.is_empty() => {
+                Params::Positional(vec![Value::Object(serde_json::Map::new())])
+            }
+            positional => positional,
+        }
+    }
+
+    /// Named parameters other than names, moved into a json dictionary held under rest. Used for
+    /// methods with an argument marked `#[rpc(rest)]`, which captures the parameters no other
+    /// argument takes. Positional parameters are returned as is.
+    pub fn gather_rest(self, names: &[&'static str], rest: &'static str) -> Params {
+        match self {
+            Params::Named(ma) => {
+                let (mut known, extra): (serde_json::Map<String, Value>, _) = ma
+                    .into_iter()
+                    .partition(|(name, _)| names.contains(&name.as_str()));
+                known.insert(rest.to_owned(), Value::Object(extra));
+                Params::Named(known)
+            }
+            positional => positional,
+        }
+    }
+
+    /// Insert null for each of names absent from named parameters. Used for `Option` arguments,
+    /// which may be left out of named parameters. Positional parameters are returned as is.
+    pub fn fill_missing_named(self, names: &[&'static str]) -> Params {
+        match self {
+            Params::Named(mut ma) => {
+                for name in names {
+                    ma.entry(*name).or_insert(Value::Null);
+                }
+                Params::Named(ma)
+            }
+            positional => positional,
+        }
+    }
+
+    /// Verify and convert Params to an argument list. If arguments are provided as named
+    /// parameters, interpret them as positional arguments using the names argument as a key.
+    ///
+    /// Verifies:
+    ///    - Number of args in positional parameter list is correct
+    ///    - No missing args in named parameter object
+    ///    - No extra args in named parameter object
+    pub fn get_rpc_args(self, names: &[&'static str]) -> Result<Vec<Value>, InvalidArgs> {
+        match self {
+            // already in order, returned without copying them to a list of their own
+            Params::Positional(ar) if ar.len() == names.len() => Ok(ar),
+            params => {
+                let ar = params.get_optional_rpc_args(names, names.len())?;
+                Ok(ar
+                    .into_iter()
+                    .map(|arg| arg.expect("all args are required"))
+                    .collect())
+            }
+        }
+    }
+
+    /// Same as [get_rpc_args](#method.get_rpc_args), but only the first `required` arguments must
+    /// be present. Trailing arguments may be omitted from a positional parameter list, any
+    /// argument after the first `required` may be omitted from a named parameter object. Omitted
+    /// arguments are returned as None.
+    pub fn get_optional_rpc_args(
+        self,
+        names: &[&'static str],
+        required: usize,
+    ) -> Result<Vec<Option<Value>>, InvalidArgs> {
+        debug_assert!(
+            {
+                fn contains_duplicates(list: &[&str]) -> bool {
+                    (1..list.len()).any(|i| list[i..].contains(&list[i - 1]))
+                }
+                !contains_duplicates(names)
+            },
+            "get_rpc_args recieved duplicate argument names"
+        );
+        debug_assert!(required <= names.len());
+        match self {
+            Params::Positional(ar) => {
+                if ar.len() < required || ar.len() > names.len() {
+                    let expected = if ar.len() < required {
+                        required
+                    } else {
+                        names.len()
+                    };
+                    return Err(InvalidArgs::WrongNumberOfArgs {
+                        expected,
+                        actual: ar.len(),
+                    });
+                }
+                let omitted = names.len() - ar.len();
+                Ok(ar
+                    .into_iter()
+                    .map(Some)
+                    .chain(core::iter::repeat_with(|| None).take(omitted))
+                    .collect())
+            }
+            Params::Named(mut ma) => {
+                let ar: Vec<Option<Value>> = names.iter().map(|name| ma.remove(*name)).collect();
+                debug_assert_eq!(ar.len(), names.len());
+                // report every offending name at once so callers can fix their call in one go
+                let missing: Vec<&'static str> = names[..required]
+                    .iter()
+                    .zip(&ar)
+                    .filter(|(_, arg)| arg.is_none())
+                    .map(|(name, _)| *name)
+                    .collect();
+                if !missing.is_empty() {
+                    return Err(InvalidArgs::MissingNamedParameter { names: missing });
+                }
+                if !ma.is_empty() {
+                    // maps keep their keys sorted unless serde_json's preserve_order feature is
+                    // enabled, sorting them reports the same names whichever way it is built
+                    let mut names: Vec<String> = ma.keys().cloned().collect();
+                    names.sort_unstable();
+                    return Err(InvalidArgs::ExtraNamedParameter { names });
+                }
+                Ok(ar)
+            }
+        }
+    }
+}
+
+/// used from generated code to deserialize an argument list as a tuple in a single pass, returns
+/// the index of the offending argument along with the deserialization error on failure
+#[doc(hidden)]
+pub fn deserialize_args<T: DeserializeOwned>(args: Vec<Value>) -> Result<T, (usize, String)> {
+    let mut consumed = 0;
+    T::deserialize(ArgsDeserializer {
+        args: args.into_iter(),
+        consumed: &mut consumed,
+    })
+    .map_err(|e| (consumed.saturating_sub(1), e.to_string()))
+}
+
+// presents an argument list to serde as a sequence, keeping count of the elements taken
+struct ArgsDeserializer<'a> {
+    args: vec::IntoIter<Value>,
+    consumed: &'a mut usize,
+}
+
+impl<'de, 'a> serde::de::Deserializer<'de> for ArgsDeserializer<'a> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: serde::de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+impl<'de, 'a> serde::de::SeqAccess<'de> for ArgsDeserializer<'a> {
+    type Error = serde_json::Error;
+
+    fn next_element_seed<S: serde::de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, Self::Error> {
+        match self.args.next() {
+            Some(arg) => {
+                *self.consumed += 1;
+                seed.deserialize(arg).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.args.len())
+    }
+}
+
+/// The failure to serialize the result of a method, answered with an error of code
+/// SERIALIZATION_ERROR.
+#[doc(hidden)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResultSerializeError {
+    pub detail: String, // the error reported by serde_json
+}
+
+/// used from generated code
+#[doc(hidden)]
+pub fn try_serialize<T: Serialize>(t: &T) -> Result<Value, ResultSerializeError> {
+    // Serde serde_json::to_value does not perform io. It's still not safe to unwrap the result. For
+    // example, the implementation of Serialize for Mutex returns an error if the mutex is poisined.
+    // Another example, serialize(&std::Path) returns an error when it encounters invalid utf-8.
+    serde_json::to_value(t).map_err(|e| ResultSerializeError {
+        detail: e.to_string(),
+    })
+}
+
+// only needs alloc, run by `cargo test --no-default-features` as well
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::collections::BTreeMap;
+    use serde_json::json;
+
+    fn named(value: Value) -> Params {
+        match value {
+            Value::Object(map) => Params::Named(map),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn positional_args() {
+        let args = vec![json!(1), json!("a")];
+        assert_eq!(
+            Params::Positional(args.clone()).get_rpc_args(&["a", "b"]),
+            Ok(args)
+        );
+        assert_eq!(
+            Params::Positional(vec![json!(1)]).get_rpc_args(&["a", "b"]),
+            Err(InvalidArgs::WrongNumberOfArgs {
+                expected: 2,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            Params::Positional(vec![json!(1), json!(2), json!(3)]).get_rpc_args(&["a", "b"]),
+            Err(InvalidArgs::WrongNumberOfArgs {
+                expected: 2,
+                actual: 3
+            })
+        );
+        // trailing optional arguments may be left out
+        assert_eq!(
+            Params::Positional(vec![json!(1)]).get_optional_rpc_args(&["a", "b", "c"], 1),
+            Ok(vec![Some(json!(1)), None, None])
+        );
+        assert_eq!(
+            Params::Positional(vec![]).get_optional_rpc_args(&["a", "b"], 1),
+            Err(InvalidArgs::WrongNumberOfArgs {
+                expected: 1,
+                actual: 0
+            })
+        );
+    }
+
+    #[test]
+    fn named_args() {
+        assert_eq!(
+            named(json!({"b": 2, "a": 1})).get_rpc_args(&["a", "b"]),
+            Ok(vec![json!(1), json!(2)])
+        );
+        assert_eq!(
+            named(json!({"a": 1})).get_optional_rpc_args(&["a", "b"], 1),
+            Ok(vec![Some(json!(1)), None])
+        );
+        assert_eq!(
+            named(json!({"b": 1, "x": 2, "y": 3})).get_rpc_args(&["a", "b", "c"]),
+            Err(InvalidArgs::MissingNamedParameter {
+                names: vec!["a", "c"]
+            })
+        );
+        assert_eq!(
+            named(json!({"a": 1, "y": 2, "x": 3})).get_rpc_args(&["a"]),
+            Err(InvalidArgs::ExtraNamedParameter {
+                names: vec!["x".into(), "y".into()]
+            })
+        );
+        // reported sorted, not in the order they were sent in
+        let mut unsorted = serde_json::Map::new();
+        for name in ["zeta", "a", "mid", "alpha"] {
+            unsorted.insert(name.into(), json!(1));
+        }
+        assert_eq!(
+            Params::Named(unsorted).get_rpc_args(&["a"]),
+            Err(InvalidArgs::ExtraNamedParameter {
+                names: vec!["alpha".into(), "mid".into(), "zeta".into()]
+            })
+        );
+    }
+
+    #[test]
+    fn params_shape() {
+        assert_eq!(
+            Params::Positional(vec![json!(1)]).expect_named(),
+            Err(InvalidArgs::NamedParamsRequired)
+        );
+        assert_eq!(
+            named(json!({"a": 1})).expect_positional(),
+            Err(InvalidArgs::PositionalParamsRequired)
+        );
+        // passing no parameters is accepted either way
+        assert_eq!(Params::Positional(vec![]).expect_named(), Ok(()));
+        assert_eq!(named(json!({})).expect_positional(), Ok(()));
+        assert_eq!(
+            named(json!({"a": 1})).flatten_named().into_value(),
+            json!([{"a": 1}])
+        );
+        assert_eq!(
+            Params::Positional(vec![]).flatten_named().into_value(),
+            json!([{}])
+        );
+        assert_eq!(
+            named(json!({"a": 1, "x": 2}))
+                .gather_rest(&["a"], "rest")
+                .into_value(),
+            json!({"a": 1, "rest": {"x": 2}})
+        );
+        assert_eq!(
+            named(json!({"a": 1}))
+                .fill_missing_named(&["a", "b"])
+                .into_value(),
+            json!({"a": 1, "b": null})
+        );
+    }
+
+    #[test]
+    fn deserialize_arg_list() {
+        assert_eq!(
+            deserialize_args::<(u8, String)>(vec![json!(1), json!("a")]),
+            Ok((1, "a".to_string()))
+        );
+        let (index, detail) =
+            deserialize_args::<(u8, String, bool)>(vec![json!(1), json!("a"), json!(0)])
+                .unwrap_err();
+        assert_eq!(index, 2);
+        assert!(detail.contains("expected a boolean"), "{}", detail);
+    }
+
+    #[test]
+    fn serialize_results() {
+        assert_eq!(try_serialize(&(1, "a")), Ok(json!([1, "a"])));
+        // json objects only have string keys
+        let mut map = BTreeMap::new();
+        map.insert((1, 2), 3);
+        assert!(try_serialize(&map)
+            .unwrap_err()
+            .detail
+            .contains("key must be a string"));
+    }
+}
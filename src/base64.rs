@@ -0,0 +1,142 @@
+// Binary arguments and results sent as base64 strings, see #[rpc(base64)].
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use serde_json::Value;
+
+/// The alphabet bytes are encoded with, picked by `#[rpc(base64 = "..")]` and
+/// `#[rpc(base64_result = "..")]`. Decoding accepts either alphabet, padded or not, so the
+/// choice only affects what is sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Base64 {
+    /// The standard alphabet of RFC 4648, with `+` and `/`, padded with `=`. The default.
+    Standard,
+    /// The url and filename safe alphabet of RFC 4648, with `-` and `_`, without padding.
+    UrlSafe,
+}
+
+impl Base64 {
+    /// Encode bytes as a base64 string using this alphabet.
+    pub fn encode(self, bytes: &[u8]) -> String {
+        let (alphabet, pad) = match self {
+            Base64::Standard => (
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/",
+                true,
+            ),
+            Base64::UrlSafe => (
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_",
+                false,
+            ),
+        };
+        let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let triple = chunk.iter().enumerate().fold(0u32, |acc, (i, byte)| {
+                acc | u32::from(*byte) << (16 - 8 * i)
+            });
+            // n bytes are spread over n + 1 characters
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    encoded.push(alphabet[(triple >> (18 - 6 * i)) as usize & 63] as char);
+                } else if pad {
+                    encoded.push('=');
+                }
+            }
+        }
+        encoded
+    }
+}
+
+// Decode base64 in either alphabet, with or without padding.
+fn decode(encoded: &str) -> Result<Vec<u8>, String> {
+    let unpadded = encoded
+        .strip_suffix("==")
+        .or_else(|| encoded.strip_suffix('='))
+        .unwrap_or(encoded);
+    if unpadded.len() % 4 == 1
+        || (unpadded.len() != encoded.len() && !encoded.len().is_multiple_of(4))
+    {
+        return Err(format!("invalid base64 length {}", encoded.len()));
+    }
+    let mut bytes = Vec::with_capacity(unpadded.len() * 3 / 4);
+    let mut acc = 0u32;
+    for (i, c) in unpadded.bytes().enumerate() {
+        let sextet = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return Err(format!("invalid base64 character at position {}", i)),
+        };
+        acc = acc << 6 | u32::from(sextet);
+        if i % 4 == 3 {
+            bytes.extend_from_slice(&acc.to_be_bytes()[1..]);
+            acc = 0;
+        }
+    }
+    // the 2 or 3 characters left over hold 1 or 2 bytes
+    match unpadded.len() % 4 {
+        2 => bytes.push((acc >> 4) as u8),
+        3 => bytes.extend_from_slice(&((acc >> 2) as u16).to_be_bytes()),
+        _ => {}
+    }
+    Ok(bytes)
+}
+
+/// Bytes serialized as a base64 string. Client helpers of methods marked
+/// `#[rpc(base64_result)]` return it in place of `Vec<u8>`. Deserializing accepts either
+/// [alphabet](enum.Base64.html), serializing uses the standard one.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Base64Bytes(pub Vec<u8>);
+
+impl From<Base64Bytes> for Vec<u8> {
+    fn from(bytes: Base64Bytes) -> Vec<u8> {
+        bytes.0
+    }
+}
+
+impl Serialize for Base64Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&Base64::Standard.encode(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Base64Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        decode(&encoded).map(Base64Bytes).map_err(de::Error::custom)
+    }
+}
+
+/// used from generated code to deserialize an argument marked #[rpc(base64)]
+#[doc(hidden)]
+pub fn from_value_base64(value: Value) -> Result<Vec<u8>, serde_json::Error> {
+    Base64Bytes::deserialize(value).map(Vec::from)
+}
+
+/// used from generated code, see from_value_base64
+#[doc(hidden)]
+pub fn deserialize_base64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    Base64Bytes::deserialize(deserializer).map(Vec::from)
+}
+
+/// used from generated code to serialize base64 arguments and results
+#[doc(hidden)]
+pub fn to_value_base64(bytes: &[u8], alphabet: Base64) -> Value {
+    Value::String(alphabet.encode(bytes))
+}
+
+/// used from generated code, serializes with the standard alphabet
+#[doc(hidden)]
+pub fn serialize_base64<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&Base64::Standard.encode(bytes))
+}
+
+/// used from generated code, serializes with the url safe alphabet
+#[doc(hidden)]
+pub fn serialize_base64_url_safe<S: Serializer>(
+    bytes: &[u8],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&Base64::UrlSafe.encode(bytes))
+}
@@ -0,0 +1,148 @@
+// Answers the calls whose method panics with an error holding the panic message.
+
+#[cfg(feature = "async")]
+use crate::AsyncHandler;
+use crate::{Handler, Id, Params, Value, PANIC_ERROR};
+#[cfg(feature = "async")]
+use futures::future::{BoxFuture, FutureExt};
+use jsonrpc_core::{Error, ErrorCode};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Wraps a [Handler](trait.Handler.html), answering calls whose method panics with an error of
+/// code [PANIC_ERROR](constant.PANIC_ERROR.html) rather than letting the panic unwind out of the
+/// handler, which would take a server loop and its connection down with it. The error data names
+/// the method and, when the panic was given a message, holds it, e.g.
+/// `"method 'explode' panicked: boom"`. The before_dispatch hook of the wrapped handler is
+/// covered as well, and so are async handlers.
+///
+/// The "catch_unwind" feature answers the panics of every handler with the same error. Unlike the
+/// feature, the wrapper applies to one server and also covers calls made through
+/// [handle](trait.Handler.html#tymethod.handle) directly. The wrapped
+/// handler must be unwind safe: state a panicking method leaves behind, such as a half updated
+/// field or a poisoned mutex, is seen by later calls. The panic hook still runs, so panics are
+/// reported as usual.
+///
+/// ```
+/// # #[easy_jsonrpc::rpc]
+/// # pub trait Fragile {
+/// #     fn explode(&self);
+/// # }
+/// # struct FragileImpl;
+/// # impl Fragile for FragileImpl {
+/// #     fn explode(&self) { panic!("boom") }
+/// # }
+/// use easy_jsonrpc::{serde_json::json, CatchUnwindServer, Handler};
+///
+/// let server = CatchUnwindServer::new(&FragileImpl as &dyn Fragile);
+/// let reply = server.handle_request(json!({"jsonrpc": "2.0", "method": "explode", "id": 1}));
+/// assert_eq!(
+///     reply.as_option().unwrap()["error"]["data"],
+///     json!("method 'explode' panicked: boom")
+/// );
+/// ```
+pub struct CatchUnwindServer<S> {
+    inner: S,
+}
+
+impl<S> CatchUnwindServer<S> {
+    /// Catch the panics of the methods of inner.
+    pub fn new(inner: S) -> CatchUnwindServer<S> {
+        CatchUnwindServer { inner }
+    }
+
+    /// The wrapped handler.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+// The error answering a call to method that panicked with payload, also used by the
+// "catch_unwind" feature.
+pub(crate) fn panic_error(method: &str, payload: Box<dyn Any + Send>) -> Error {
+    let message = match payload.downcast::<&'static str>() {
+        Ok(message) => Some(message.to_string()),
+        Err(payload) => payload.downcast::<String>().ok().map(|message| *message),
+    };
+    Error {
+        code: ErrorCode::ServerError(PANIC_ERROR),
+        message: "Method panicked".into(),
+        data: Some(Value::String(match message {
+            Some(message) => format!("method '{}' panicked: {}", method, message),
+            None => format!("method '{}' panicked", method),
+        })),
+    }
+}
+
+fn caught<T>(method: &str, call: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    panic::catch_unwind(AssertUnwindSafe(call))
+        .unwrap_or_else(|payload| Err(panic_error(method, payload)))
+}
+
+impl<S: Handler> Handler for CatchUnwindServer<S> {
+    fn handle(&self, method: &str, params: Params) -> Result<Value, Error> {
+        caught(method, || self.inner.handle(method, params))
+    }
+
+    fn handle_with_id(&self, id: &Id, method: &str, params: Params) -> Result<Value, Error> {
+        caught(method, || self.inner.handle_with_id(id, method, params))
+    }
+
+    fn handle_notification(&self, method: &str, params: Params) -> Result<Value, Error> {
+        caught(method, || self.inner.handle_notification(method, params))
+    }
+
+    fn methods(&self) -> Vec<&'static str> {
+        self.inner.methods()
+    }
+
+    fn before_dispatch(&self, method: &str) -> Result<(), Error> {
+        caught(method, || self.inner.before_dispatch(method))
+    }
+
+    fn on_notification_result(&self, method: &str, result: Result<Value, Error>) {
+        self.inner.on_notification_result(method, result)
+    }
+
+    fn on_parse_error(&self, raw: &str, err: &serde_json::Error) {
+        self.inner.on_parse_error(raw, err)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<S: AsyncHandler> AsyncHandler for CatchUnwindServer<S> {
+    fn handle_async<'a>(
+        &'a self,
+        method: &'a str,
+        params: Params,
+    ) -> BoxFuture<'a, Result<Value, Error>> {
+        let call = caught(method, || Ok(self.inner.handle_async(method, params)));
+        Box::pin(async move {
+            AssertUnwindSafe(call?)
+                .catch_unwind()
+                .await
+                .unwrap_or_else(|payload| Err(panic_error(method, payload)))
+        })
+    }
+
+    fn handle_async_with_id<'a>(
+        &'a self,
+        id: &'a Id,
+        method: &'a str,
+        params: Params,
+    ) -> BoxFuture<'a, Result<Value, Error>> {
+        let call = caught(method, || {
+            Ok(self.inner.handle_async_with_id(id, method, params))
+        });
+        Box::pin(async move {
+            AssertUnwindSafe(call?)
+                .catch_unwind()
+                .await
+                .unwrap_or_else(|payload| Err(panic_error(method, payload)))
+        })
+    }
+
+    fn methods_async(&self) -> Vec<&'static str> {
+        self.inner.methods_async()
+    }
+}
@@ -0,0 +1,9395 @@
+// Handlers, clients and everything else built on the types of jsonrpc-core, which need std.
+// Re-exported from the crate root as a whole.
+
+pub use easy_jsonrpc_proc_macro::rpc;
+#[cfg(feature = "async")]
+pub use easy_jsonrpc_proc_macro::rpc_async;
+pub use easy_jsonrpc_proc_macro::RpcErrorCode;
+
+/// Traits passed to [rpc_async](../easy_jsonrpc_proc_macro/attr.rpc_async.html) are desugared
+/// with async_trait. Implementations of those traits must be annotated with
+/// `#[easy_jsonrpc::async_trait]` as well.
+#[cfg(feature = "async")]
+pub use async_trait::async_trait;
+/// Future returned by [AsyncHandler](trait.AsyncHandler.html) methods.
+#[cfg(feature = "async")]
+pub use futures::future::BoxFuture;
+
+// used from generated code
+#[doc(hidden)]
+pub use jsonrpc_core::types::{
+    self, Error, ErrorCode, Failure, Id, MethodCall, Notification, Output, Success, Version,
+};
+
+use crate::{
+    InvalidArgs, Params, ResultSerializeError, Value, APPLICATION_ERROR, SERIALIZATION_ERROR,
+};
+use serde::de::{Deserialize, DeserializeOwned};
+use serde::ser::Serialize;
+use serde_json::json;
+use std::{
+    collections::BTreeMap,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Handles jsonrpc requests.
+pub trait Handler {
+    /// Type-check params and call method if method exists. This method is implemented automatically
+    /// by the [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro.
+    fn handle(&self, method: &str, params: Params) -> Result<Value, jsonrpc_core::Error>;
+
+    /// Same as handle, but also receives the id of the call being handled. Notifications are
+    /// passed `Id::Null`. The [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro implements
+    /// this method so that the id can be passed to methods marked `#[rpc(with_id)]`.
+    fn handle_with_id(
+        &self,
+        _id: &Id,
+        method: &str,
+        params: Params,
+    ) -> Result<Value, jsonrpc_core::Error> {
+        self.handle(method, params)
+    }
+
+    /// Same as handle_with_id, for a notification. Its result is never sent, only passed to
+    /// [on_notification_result](#method.on_notification_result), so the
+    /// [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro implements this method without
+    /// serializing the values returned by methods, the result is null instead. Arguments are
+    /// checked and errors returned as usual. The default calls handle_with_id with `Id::Null`.
+    fn handle_notification(
+        &self,
+        method: &str,
+        params: Params,
+    ) -> Result<Value, jsonrpc_core::Error> {
+        self.handle_with_id(&Id::Null, method, params)
+    }
+
+    /// Names of the methods handled. Implemented automatically by the
+    /// [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro, the default lists no methods.
+    ///
+    /// A fallback method may handle methods that are not listed.
+    fn methods(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// Called before every call handled through handle_request, handle_bytes or handle_parsed,
+    /// notifications included. When it returns `Err`, the call is answered with that error
+    /// instead of being dispatched, notifications still get no reply. The default lets every
+    /// call through.
+    ///
+    /// The [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro implements this method with the
+    /// trait method marked `#[rpc(before_dispatch)]`, if any.
+    fn before_dispatch(&self, _method: &str) -> Result<(), jsonrpc_core::Error> {
+        Ok(())
+    }
+
+    /// Called with the outcome of every notification handled through handle_request,
+    /// handle_bytes or handle_parsed, for observing failures that are not sent on the wire, for
+    /// example by logging them. The default does nothing.
+    ///
+    /// Successful notifications handled by handlers the rpc macro generates have a null result,
+    /// see [handle_notification](#method.handle_notification).
+    fn on_notification_result(&self, _method: &str, _result: Result<Value, jsonrpc_core::Error>) {}
+
+    /// Called for every malformed request handled through handle_request or handle_bytes, before
+    /// it is answered with a "Parse error" or an "Invalid request" error, and for every malformed
+    /// call of a request, before it is answered with an "Invalid request" error. For seeing what
+    /// misbehaving clients send, for example by logging it. The default does nothing.
+    ///
+    /// raw is the offending call as json, or the whole request if it is not a json object or
+    /// array, converted to utf-8 lossily if need be. err tells why it failed to parse.
+    fn on_parse_error(&self, _raw: &str, _err: &serde_json::Error) {}
+
+    /// Parses raw_request as a jsonrpc request, handles request according to the jsonrpc spec.
+    ///
+    /// Json that is neither a call object nor a batch array, like `true` or `"string"`, is
+    /// answered with a single "Invalid request" error with a null id.
+    fn handle_request(&self, raw_request: Value) -> MaybeReply {
+        let request = parse_raw_request(raw_request, &mut |raw, err| self.on_parse_error(raw, err));
+        handle_raw_request(
+            &mut |id: Option<&Id>, method: &str, params| dispatch_call(self, id, method, params),
+            request,
+        )
+    }
+
+    /// Parses raw_request as a jsonrpc request, handles request according to the jsonrpc spec.
+    /// Returns the serialized reply, or None if no reply is necessary.
+    ///
+    /// Equivalent to handle_request but avoids converting to and from `Value`. Input that is not
+    /// valid utf-8 or not valid json is answered with a "Parse error".
+    fn handle_bytes(&self, raw_request: &[u8]) -> Option<Vec<u8>> {
+        self.handle_bytes_with(raw_request, &Config::default())
+    }
+
+    /// Same as [handle_bytes](#method.handle_bytes), with non-default request handling options.
+    fn handle_bytes_with(&self, raw_request: &[u8], config: &Config) -> Option<Vec<u8>> {
+        let request = check_raw_bytes(raw_request, config, &mut |raw, err| {
+            self.on_parse_error(raw, err)
+        });
+        handle_raw_bytes(
+            &mut |id: Option<&Id>, method: &str, params| dispatch_call(self, id, method, params),
+            request,
+            config,
+        )
+    }
+
+    /// Same as [handle_bytes_with](#method.handle_bytes_with), but gives up on replies larger
+    /// than max_bytes once serialized. The reply is serialized incrementally so an oversized one
+    /// is abandoned as soon as it crosses the limit, for example when a method returns a huge
+    /// array by mistake. The calls of the request have been handled either way.
+    fn handle_bytes_capped(
+        &self,
+        raw_request: &[u8],
+        config: &Config,
+        max_bytes: usize,
+    ) -> Option<Result<Vec<u8>, ResponseTooLarge>> {
+        let request = check_raw_bytes(raw_request, config, &mut |raw, err| {
+            self.on_parse_error(raw, err)
+        });
+        handle_raw_bytes_capped(
+            &mut |id: Option<&Id>, method: &str, params| dispatch_call(self, id, method, params),
+            request,
+            config,
+            max_bytes,
+        )
+    }
+
+    /// Same as [handle_bytes](#method.handle_bytes), but the reply is serialized canonically:
+    /// the members of every object, including those of results, are sorted by key. Replies to
+    /// the same request are then byte for byte identical whatever order maps iterate in, as
+    /// needed for golden tests or content addressing.
+    fn handle_raw_canonical(&self, raw_request: &str) -> Option<String> {
+        let config = Config::default();
+        let request = check_raw_bytes(raw_request.as_bytes(), &config, &mut |raw, err| {
+            self.on_parse_error(raw, err)
+        });
+        let reply = respond_raw_bytes(
+            &mut |id: Option<&Id>, method: &str, params| dispatch_call(self, id, method, params),
+            request,
+            &config,
+        )?;
+        Some(serialize_canonical(&reply))
+    }
+
+    /// Same as [handle_bytes](#method.handle_bytes), but also accepts the shorthand
+    /// `["method", arg1, arg2]` sent by some minimalist clients: a json array whose first element
+    /// is a string is handled as a call to the method it names, passing the remaining elements
+    /// as positional parameters. The shorthand carries no id, so its response has a null id.
+    /// Responses are regular jsonrpc responses either way.
+    ///
+    /// A batch can't start with a string, so valid requests are handled as usual.
+    fn handle_raw_lenient(&self, raw_request: &str) -> Option<String> {
+        if let Some(call) = parse_shorthand(raw_request) {
+            return self
+                .handle_request(call)
+                .as_option()
+                .map(|reply| reply.to_string());
+        }
+        let reply = self.handle_bytes(raw_request.as_bytes())?;
+        // handle_bytes always produces serialized json, which is valid utf8
+        Some(String::from_utf8(reply).expect("reply is not valid utf8"))
+    }
+
+    /// Handles an already parsed request according to the jsonrpc spec. Returns None if no reply
+    /// is necessary. For custom transports, see [parse_request](fn.parse_request.html) and
+    /// [response_to_bytes](fn.response_to_bytes.html).
+    fn handle_parsed(&self, request: types::Request) -> Option<types::Response> {
+        handle_parsed_request(
+            &mut |id: Option<&Id>, method: &str, params| dispatch_call(self, id, method, params),
+            request,
+        )
+    }
+
+    /// Same as [handle_parsed](#method.handle_parsed), but the calls of a batch are handled
+    /// concurrently on the rayon threadpool. Requires the "parallel" feature.
+    ///
+    /// The order in which the calls of a batch take effect is unspecified. The outputs in the
+    /// response are in the same order as the corresponding calls in the request, notifications
+    /// still produce no output.
+    ///
+    /// `dyn Trait` is only Sync if the trait has Sync as a supertrait.
+    #[cfg(feature = "parallel")]
+    fn handle_parsed_parallel(&self, request: types::Request) -> Option<types::Response>
+    where
+        Self: Sync,
+    {
+        handle_parsed_request_parallel(
+            &|id: Option<&Id>, method: &str, params| dispatch_call(self, id, method, params),
+            request,
+        )
+    }
+}
+
+// handle a call unless the handler's before_dispatch hook rejects it
+fn dispatch<H: ?Sized + Handler>(
+    handler: &H,
+    id: &Id,
+    method: &str,
+    params: Params,
+) -> Result<Value, jsonrpc_core::Error> {
+    handler.before_dispatch(method)?;
+    handler.handle_with_id(id, method, params)
+}
+
+// Same as dispatch, for a call that is a notification when id is None. The outcome of a
+// notification is passed to the handler's on_notification_result hook.
+fn dispatch_call<H: ?Sized + Handler>(
+    handler: &H,
+    id: Option<&Id>,
+    method: &str,
+    params: Params,
+) -> Result<Value, jsonrpc_core::Error> {
+    match id {
+        Some(id) => dispatch(handler, id, method, params),
+        None => {
+            let ret = handler
+                .before_dispatch(method)
+                .and_then(|()| handler.handle_notification(method, params));
+            let outcome = notification_outcome(&ret);
+            handler.on_notification_result(method, ret);
+            outcome
+        }
+    }
+}
+
+// Notifications get no reply so their results are dropped once handed to the
+// on_notification_result hook. Whether they failed, and how, is kept for tracing.
+fn notification_outcome(
+    ret: &Result<Value, jsonrpc_core::Error>,
+) -> Result<Value, jsonrpc_core::Error> {
+    ret.as_ref().map(|_| Value::Null).map_err(Clone::clone)
+}
+
+/// Handles jsonrpc requests for apis which contain methods that take `&mut self`.
+///
+/// The [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro implements HandlerMut instead of
+/// [Handler](trait.Handler.html) when at least one method in the trait takes `&mut self`. Every
+/// Handler is also a HandlerMut.
+pub trait HandlerMut {
+    /// Type-check params and call method if method exists. This method is implemented automatically
+    /// by the [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro.
+    fn handle_mut(&mut self, method: &str, params: Params) -> Result<Value, jsonrpc_core::Error>;
+
+    /// Same as handle_mut, but also receives the id of the call being handled. Notifications are
+    /// passed `Id::Null`.
+    fn handle_mut_with_id(
+        &mut self,
+        _id: &Id,
+        method: &str,
+        params: Params,
+    ) -> Result<Value, jsonrpc_core::Error> {
+        self.handle_mut(method, params)
+    }
+
+    /// Same as [Handler::handle_notification](trait.Handler.html#method.handle_notification), for
+    /// handlers that take `&mut self`.
+    fn handle_notification_mut(
+        &mut self,
+        method: &str,
+        params: Params,
+    ) -> Result<Value, jsonrpc_core::Error> {
+        self.handle_mut_with_id(&Id::Null, method, params)
+    }
+
+    /// Same as [Handler::methods](trait.Handler.html#method.methods), for handlers that take
+    /// `&mut self`.
+    fn methods_mut(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// Same as [Handler::before_dispatch](trait.Handler.html#method.before_dispatch), for
+    /// handlers that take `&mut self`.
+    fn before_dispatch_mut(&self, _method: &str) -> Result<(), jsonrpc_core::Error> {
+        Ok(())
+    }
+
+    /// Same as
+    /// [Handler::on_notification_result](trait.Handler.html#method.on_notification_result), for
+    /// handlers that take `&mut self`.
+    fn on_notification_result_mut(
+        &self,
+        _method: &str,
+        _result: Result<Value, jsonrpc_core::Error>,
+    ) {
+    }
+
+    /// Same as [Handler::on_parse_error](trait.Handler.html#method.on_parse_error), for handlers
+    /// that take `&mut self`.
+    fn on_parse_error_mut(&self, _raw: &str, _err: &serde_json::Error) {}
+
+    /// Parses raw_request as a jsonrpc request, handles request according to the jsonrpc spec.
+    /// Calls within a batch are handled one at a time, in order.
+    fn handle_request_mut(&mut self, raw_request: Value) -> MaybeReply {
+        let request = parse_raw_request(raw_request, &mut |raw, err| {
+            self.on_parse_error_mut(raw, err)
+        });
+        handle_raw_request(
+            &mut |id: Option<&Id>, method: &str, params| {
+                dispatch_call_mut(self, id, method, params)
+            },
+            request,
+        )
+    }
+
+    /// Same as [Handler::handle_bytes](trait.Handler.html#method.handle_bytes), for handlers
+    /// that take `&mut self`.
+    fn handle_bytes_mut(&mut self, raw_request: &[u8]) -> Option<Vec<u8>> {
+        self.handle_bytes_mut_with(raw_request, &Config::default())
+    }
+
+    /// Same as [Handler::handle_bytes_with](trait.Handler.html#method.handle_bytes_with), for
+    /// handlers that take `&mut self`.
+    fn handle_bytes_mut_with(&mut self, raw_request: &[u8], config: &Config) -> Option<Vec<u8>> {
+        let request = check_raw_bytes(raw_request, config, &mut |raw, err| {
+            self.on_parse_error_mut(raw, err)
+        });
+        handle_raw_bytes(
+            &mut |id: Option<&Id>, method: &str, params| {
+                dispatch_call_mut(self, id, method, params)
+            },
+            request,
+            config,
+        )
+    }
+
+    /// Same as [Handler::handle_bytes_capped](trait.Handler.html#method.handle_bytes_capped), for
+    /// handlers that take `&mut self`.
+    fn handle_bytes_mut_capped(
+        &mut self,
+        raw_request: &[u8],
+        config: &Config,
+        max_bytes: usize,
+    ) -> Option<Result<Vec<u8>, ResponseTooLarge>> {
+        let request = check_raw_bytes(raw_request, config, &mut |raw, err| {
+            self.on_parse_error_mut(raw, err)
+        });
+        handle_raw_bytes_capped(
+            &mut |id: Option<&Id>, method: &str, params| {
+                dispatch_call_mut(self, id, method, params)
+            },
+            request,
+            config,
+            max_bytes,
+        )
+    }
+
+    /// Same as [Handler::handle_parsed](trait.Handler.html#method.handle_parsed), for handlers
+    /// that take `&mut self`.
+    fn handle_parsed_mut(&mut self, request: types::Request) -> Option<types::Response> {
+        handle_parsed_request(
+            &mut |id: Option<&Id>, method: &str, params| {
+                dispatch_call_mut(self, id, method, params)
+            },
+            request,
+        )
+    }
+}
+
+impl<T: ?Sized + Handler> HandlerMut for T {
+    fn handle_mut(&mut self, method: &str, params: Params) -> Result<Value, jsonrpc_core::Error> {
+        self.handle(method, params)
+    }
+
+    fn handle_mut_with_id(
+        &mut self,
+        id: &Id,
+        method: &str,
+        params: Params,
+    ) -> Result<Value, jsonrpc_core::Error> {
+        self.handle_with_id(id, method, params)
+    }
+
+    fn handle_notification_mut(
+        &mut self,
+        method: &str,
+        params: Params,
+    ) -> Result<Value, jsonrpc_core::Error> {
+        self.handle_notification(method, params)
+    }
+
+    fn methods_mut(&self) -> Vec<&'static str> {
+        self.methods()
+    }
+
+    fn before_dispatch_mut(&self, method: &str) -> Result<(), jsonrpc_core::Error> {
+        self.before_dispatch(method)
+    }
+
+    fn on_notification_result_mut(&self, method: &str, result: Result<Value, jsonrpc_core::Error>) {
+        self.on_notification_result(method, result)
+    }
+
+    fn on_parse_error_mut(&self, raw: &str, err: &serde_json::Error) {
+        self.on_parse_error(raw, err)
+    }
+}
+
+// same as dispatch, for handlers that take &mut self
+fn dispatch_mut<H: ?Sized + HandlerMut>(
+    handler: &mut H,
+    id: &Id,
+    method: &str,
+    params: Params,
+) -> Result<Value, jsonrpc_core::Error> {
+    handler.before_dispatch_mut(method)?;
+    handler.handle_mut_with_id(id, method, params)
+}
+
+// same as dispatch_call, for handlers that take &mut self
+fn dispatch_call_mut<H: ?Sized + HandlerMut>(
+    handler: &mut H,
+    id: Option<&Id>,
+    method: &str,
+    params: Params,
+) -> Result<Value, jsonrpc_core::Error> {
+    match id {
+        Some(id) => dispatch_mut(handler, id, method, params),
+        None => {
+            let ret = handler
+                .before_dispatch_mut(method)
+                .and_then(|()| handler.handle_notification_mut(method, params));
+            let outcome = notification_outcome(&ret);
+            handler.on_notification_result_mut(method, ret);
+            outcome
+        }
+    }
+}
+
+/// Handles jsonrpc requests for apis with methods that take a request context, such as the
+/// authenticated user or a trace id derived by the transport.
+///
+/// The [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro implements ContextHandler instead of
+/// [Handler](trait.Handler.html) when a method has an argument marked `#[rpc(context)]`. The
+/// context is supplied by the caller of the handler rather than deserialized from the params of
+/// the call.
+///
+/// ```
+/// use easy_jsonrpc::{ContextHandler, MaybeReply};
+/// use serde_json::json;
+///
+/// pub struct Session {
+///     user: String,
+/// }
+///
+/// #[easy_jsonrpc::rpc]
+/// pub trait Greeter {
+///     fn greet(&self, #[rpc(context)] session: &Session, greeting: String) -> String;
+/// }
+///
+/// struct GreeterImpl;
+/// impl Greeter for GreeterImpl {
+///     fn greet(&self, session: &Session, greeting: String) -> String {
+///         format!("{}, {}", greeting, session.user)
+///     }
+/// }
+///
+/// let handler = &GreeterImpl as &dyn Greeter;
+/// let session = Session { user: "ann".into() };
+/// assert_eq!(
+///     handler.handle_request_with_ctx(
+///         json!({"jsonrpc": "2.0", "method": "greet", "params": ["hello"], "id": 1}),
+///         &session,
+///     ),
+///     MaybeReply::Reply(json!({"jsonrpc": "2.0", "result": "hello, ann", "id": 1}))
+/// );
+/// ```
+pub trait ContextHandler<C: ?Sized> {
+    /// Type-check params and call method if method exists, passing ctx to methods that take a
+    /// context. Notifications are passed `Id::Null`. This method is implemented automatically by
+    /// the [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro.
+    fn handle_with_ctx(
+        &self,
+        id: &Id,
+        method: &str,
+        params: Params,
+        ctx: &C,
+    ) -> Result<Value, jsonrpc_core::Error>;
+
+    /// Same as [Handler::methods](trait.Handler.html#method.methods).
+    fn methods_with_ctx(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// Same as [Handler::handle_request](trait.Handler.html#method.handle_request), passing ctx
+    /// to the methods called.
+    fn handle_request_with_ctx(&self, raw_request: Value, ctx: &C) -> MaybeReply {
+        handle_raw_request(
+            &mut |id: Option<&Id>, method: &str, params| {
+                self.handle_with_ctx(id.unwrap_or(&Id::Null), method, params, ctx)
+            },
+            parse_raw_request(raw_request, &mut |_, _| {}),
+        )
+    }
+
+    /// Same as [Handler::handle_bytes](trait.Handler.html#method.handle_bytes), passing ctx to
+    /// the methods called.
+    fn handle_bytes_with_ctx(&self, raw_request: &[u8], ctx: &C) -> Option<Vec<u8>> {
+        handle_raw_bytes(
+            &mut |id: Option<&Id>, method: &str, params| {
+                self.handle_with_ctx(id.unwrap_or(&Id::Null), method, params, ctx)
+            },
+            check_raw_bytes(raw_request, &Config::default(), &mut |_, _| {}),
+            &Config::default(),
+        )
+    }
+
+    /// Same as [Handler::handle_parsed](trait.Handler.html#method.handle_parsed), passing ctx to
+    /// the methods called.
+    fn handle_parsed_with_ctx(&self, request: types::Request, ctx: &C) -> Option<types::Response> {
+        handle_parsed_request(
+            &mut |id: Option<&Id>, method: &str, params| {
+                self.handle_with_ctx(id.unwrap_or(&Id::Null), method, params, ctx)
+            },
+            request,
+        )
+    }
+}
+
+macro_rules! forward_handler {
+    ($($pointer:ty),*) => {$(
+        impl<T: ?Sized + Handler> Handler for $pointer {
+            fn handle(&self, method: &str, params: Params) -> Result<Value, jsonrpc_core::Error> {
+                (**self).handle(method, params)
+            }
+
+            fn handle_with_id(
+                &self,
+                id: &Id,
+                method: &str,
+                params: Params,
+            ) -> Result<Value, jsonrpc_core::Error> {
+                (**self).handle_with_id(id, method, params)
+            }
+
+            fn handle_notification(
+                &self,
+                method: &str,
+                params: Params,
+            ) -> Result<Value, jsonrpc_core::Error> {
+                (**self).handle_notification(method, params)
+            }
+
+            fn methods(&self) -> Vec<&'static str> {
+                (**self).methods()
+            }
+
+            fn before_dispatch(&self, method: &str) -> Result<(), jsonrpc_core::Error> {
+                (**self).before_dispatch(method)
+            }
+
+            fn on_notification_result(
+                &self,
+                method: &str,
+                result: Result<Value, jsonrpc_core::Error>,
+            ) {
+                (**self).on_notification_result(method, result)
+            }
+
+            fn on_parse_error(&self, raw: &str, err: &serde_json::Error) {
+                (**self).on_parse_error(raw, err)
+            }
+        }
+    )*};
+}
+
+forward_handler!(&T, Box<T>, std::rc::Rc<T>, std::sync::Arc<T>, ArcServer<T>);
+
+/// Dispatches calls to several handlers, so that multiple apis can be served together.
+///
+/// Calls to a method listed by the [methods](trait.Handler.html#method.methods) of a handler go
+/// straight to the first handler listing it. Other calls are passed to the handlers in the order
+/// they were added, until one of them responds with something other than a "Method not found"
+/// error. The calls of a batch are routed independently.
+///
+/// ```
+/// # use easy_jsonrpc::{rpc, Handler, Router};
+/// #[rpc]
+/// pub trait Accounts {
+///     fn balance(&self) -> u64;
+/// }
+///
+/// #[rpc]
+/// pub trait Prices {
+///     fn price(&self) -> u64;
+/// }
+///
+/// struct Exchange;
+/// impl Accounts for Exchange {
+///     fn balance(&self) -> u64 { 1 }
+/// }
+/// impl Prices for Exchange {
+///     fn price(&self) -> u64 { 2 }
+/// }
+///
+/// let router = Router::new()
+///     .with(Box::new(Exchange) as Box<dyn Accounts>)
+///     .with(Box::new(Exchange) as Box<dyn Prices>);
+/// ```
+#[derive(Default)]
+pub struct Router {
+    handlers: Vec<Box<dyn Handler>>,
+    index: BTreeMap<&'static str, usize>, // position in handlers of the handler for each method
+}
+
+impl Router {
+    /// A router without any handlers, it responds to every call with "Method not found".
+    pub fn new() -> Router {
+        Router::default()
+    }
+
+    /// Route calls to handler. Handlers added earlier take precedence.
+    pub fn with<H: Handler + 'static>(mut self, handler: H) -> Router {
+        for method in handler.methods() {
+            self.index.entry(method).or_insert(self.handlers.len());
+        }
+        self.handlers.push(Box::new(handler));
+        self
+    }
+
+    // Pass a call to method to the handler listing it, or to each handler in turn until one of
+    // them knows the method.
+    fn route<F>(&self, method: &str, params: Params, call: F) -> Result<Value, jsonrpc_core::Error>
+    where
+        F: Fn(&dyn Handler, Params) -> Result<Value, jsonrpc_core::Error>,
+    {
+        if let Some(index) = self.index.get(method) {
+            return call(&*self.handlers[*index], params);
+        }
+        let (last, rest) = match self.handlers.split_last() {
+            Some(split) => split,
+            None => return Err(method_not_found(method)),
+        };
+        for handler in rest {
+            match call(&**handler, params.clone()) {
+                Err(ref err) if err.code == ErrorCode::MethodNotFound => {}
+                ret => return ret,
+            }
+        }
+        // the last handler can take the params without cloning
+        call(&**last, params)
+    }
+}
+
+impl Handler for Router {
+    fn handle(&self, method: &str, params: Params) -> Result<Value, jsonrpc_core::Error> {
+        self.handle_with_id(&Id::Null, method, params)
+    }
+
+    // the before_dispatch hook of each handler applies to the calls routed to it
+    fn handle_with_id(
+        &self,
+        id: &Id,
+        method: &str,
+        params: Params,
+    ) -> Result<Value, jsonrpc_core::Error> {
+        self.route(method, params, |handler, params| {
+            dispatch(handler, id, method, params)
+        })
+    }
+
+    fn handle_notification(
+        &self,
+        method: &str,
+        params: Params,
+    ) -> Result<Value, jsonrpc_core::Error> {
+        self.route(method, params, |handler, params| {
+            handler.before_dispatch(method)?;
+            handler.handle_notification(method, params)
+        })
+    }
+
+    fn methods(&self) -> Vec<&'static str> {
+        self.index.keys().cloned().collect()
+    }
+
+    // notifications handled by a fallback method are not passed on, the handler is unknown
+    fn on_notification_result(&self, method: &str, result: Result<Value, jsonrpc_core::Error>) {
+        if let Some(index) = self.index.get(method) {
+            self.handlers[*index].on_notification_result(method, result);
+        }
+    }
+}
+
+/// A [Handler](trait.Handler.html) calling closures registered by method name, for apis whose
+/// methods are only known at runtime, such as those added by plugins. No trait or macro is
+/// involved: each closure receives the params of its calls as is and returns the result as json.
+///
+/// ```
+/// # use easy_jsonrpc::{Dispatcher, Handler, MaybeReply, Params};
+/// # use serde_json::json;
+/// let dispatcher = Dispatcher::new().method("add", |params: Params| {
+///     let (a, b): (i64, i64) = params.deserialize()?;
+///     Ok(json!(a + b))
+/// });
+/// assert_eq!(
+///     dispatcher.handle_request(json!({"jsonrpc": "2.0", "method": "add", "params": [1, 2], "id": 1})),
+///     MaybeReply::Reply(json!({"jsonrpc": "2.0", "result": 3, "id": 1}))
+/// );
+/// ```
+#[derive(Default)]
+pub struct Dispatcher {
+    methods: BTreeMap<&'static str, Box<DispatchFn>>,
+}
+
+type DispatchFn = dyn Fn(Params) -> Result<Value, jsonrpc_core::Error> + Send + Sync;
+
+impl Dispatcher {
+    /// A dispatcher without any methods, it responds to every call with "Method not found".
+    pub fn new() -> Dispatcher {
+        Dispatcher::default()
+    }
+
+    /// Answer calls to the method name with f. Registering a name again replaces the closure
+    /// registered before. Names chosen at runtime can be made static with `Box::leak`.
+    pub fn method<F>(mut self, name: &'static str, f: F) -> Dispatcher
+    where
+        F: Fn(Params) -> Result<Value, jsonrpc_core::Error> + Send + Sync + 'static,
+    {
+        self.methods.insert(name, Box::new(f));
+        self
+    }
+}
+
+impl Handler for Dispatcher {
+    fn handle(&self, method: &str, params: Params) -> Result<Value, jsonrpc_core::Error> {
+        match self.methods.get(method) {
+            Some(f) => f(params),
+            None => Err(method_not_found(method)),
+        }
+    }
+
+    fn methods(&self) -> Vec<&'static str> {
+        self.methods.keys().cloned().collect()
+    }
+}
+
+impl std::fmt::Debug for Dispatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Dispatcher")
+            .field("methods", &self.methods.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Handles jsonrpc requests for apis whose methods are async.
+///
+/// The [rpc_async](../easy_jsonrpc_proc_macro/attr.rpc_async.html) macro implements AsyncHandler
+/// for traits containing async methods.
+#[cfg(feature = "async")]
+pub trait AsyncHandler: Sync {
+    /// Type-check params and call method if method exists. This method is implemented
+    /// automatically by the [rpc_async](../easy_jsonrpc_proc_macro/attr.rpc_async.html) macro.
+    fn handle_async<'a>(
+        &'a self,
+        method: &'a str,
+        params: Params,
+    ) -> BoxFuture<'a, Result<Value, jsonrpc_core::Error>>;
+
+    /// Same as handle_async, but also receives the id of the call being handled. Notifications
+    /// are passed `Id::Null`.
+    fn handle_async_with_id<'a>(
+        &'a self,
+        _id: &'a Id,
+        method: &'a str,
+        params: Params,
+    ) -> BoxFuture<'a, Result<Value, jsonrpc_core::Error>> {
+        self.handle_async(method, params)
+    }
+
+    /// Same as [Handler::methods](trait.Handler.html#method.methods), for async handlers.
+    fn methods_async(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// Parses raw_request as a jsonrpc request, handles request according to the jsonrpc spec.
+    /// Calls within a batch are handled concurrently, outputs are returned in the same order as
+    /// the calls.
+    fn handle_request_async(&self, raw_request: Value) -> BoxFuture<'_, MaybeReply> {
+        Box::pin(async move {
+            match parse_raw_request(raw_request, &mut |_, _| {}) {
+                Ok(request) => reply(handle_parsed_request_async(self, request).await),
+                Err(parse_error) => MaybeReply::Reply(parse_error),
+            }
+        })
+    }
+}
+
+// Pointers that are Sync only for Send handlers, like Arc, are listed after "Send;".
+macro_rules! forward_async_handler {
+    ($($pointer:ty),*) => {
+        forward_async_handler!(Sync; $($pointer),*);
+    };
+    ($bound:ident; $($pointer:ty),*) => {$(
+        #[cfg(feature = "async")]
+        impl<T: ?Sized + AsyncHandler + $bound> AsyncHandler for $pointer {
+            fn handle_async<'a>(
+                &'a self,
+                method: &'a str,
+                params: Params,
+            ) -> BoxFuture<'a, Result<Value, jsonrpc_core::Error>> {
+                (**self).handle_async(method, params)
+            }
+
+            fn handle_async_with_id<'a>(
+                &'a self,
+                id: &'a Id,
+                method: &'a str,
+                params: Params,
+            ) -> BoxFuture<'a, Result<Value, jsonrpc_core::Error>> {
+                (**self).handle_async_with_id(id, method, params)
+            }
+
+            fn methods_async(&self) -> Vec<&'static str> {
+                (**self).methods_async()
+            }
+        }
+    )*};
+}
+
+forward_async_handler!(&T, Box<T>);
+forward_async_handler!(Send; std::sync::Arc<T>, ArcServer<T>);
+
+/// A handler shared through an `Arc`, handing out requests handled on owned clones of it. The
+/// jobs and futures it returns borrow nothing, so they can be moved into spawned threads or tasks
+/// of an async runtime, one per request or per connection.
+///
+/// ```
+/// # #[easy_jsonrpc::rpc]
+/// # pub trait Adder: Send + Sync {
+/// #     fn wrapping_add(&self, a: isize, b: isize) -> isize;
+/// # }
+/// # struct AdderImpl;
+/// # impl Adder for AdderImpl {
+/// #     fn wrapping_add(&self, a: isize, b: isize) -> isize { a.wrapping_add(b) }
+/// # }
+/// use easy_jsonrpc::ArcServer;
+/// use serde_json::json;
+/// use std::sync::Arc;
+///
+/// let server = ArcServer::new(Arc::new(AdderImpl) as Arc<dyn Adder>);
+/// let job = server.job(json!({"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1}));
+/// let reply = std::thread::spawn(job).join().unwrap();
+/// assert_eq!(reply.as_option().unwrap()["result"], json!(3));
+/// ```
+pub struct ArcServer<H: ?Sized> {
+    handler: Arc<H>,
+}
+
+impl<H: ?Sized> ArcServer<H> {
+    /// Share handler between the requests handed out.
+    pub fn new(handler: Arc<H>) -> ArcServer<H> {
+        ArcServer { handler }
+    }
+
+    /// The shared handler.
+    pub fn handler(&self) -> &Arc<H> {
+        &self.handler
+    }
+}
+
+impl<H: ?Sized + Handler + Send + Sync + 'static> ArcServer<H> {
+    /// Handle raw_request like [Handler::handle_request](trait.Handler.html#method.handle_request)
+    /// once the returned job is run, on whichever thread runs it.
+    pub fn job(&self, raw_request: Value) -> impl FnOnce() -> MaybeReply + Send + 'static {
+        let handler = self.handler.clone();
+        move || handler.handle_request(raw_request)
+    }
+
+    /// Handle raw bytes like [Handler::handle_bytes](trait.Handler.html#method.handle_bytes) once
+    /// the returned job is run, on whichever thread runs it.
+    pub fn bytes_job(&self, bytes: Vec<u8>) -> impl FnOnce() -> Option<Vec<u8>> + Send + 'static {
+        let handler = self.handler.clone();
+        move || handler.handle_bytes(&bytes)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<H: ?Sized + AsyncHandler + Send + Sync + 'static> ArcServer<H> {
+    /// Same as [AsyncHandler::handle_request_async](trait.AsyncHandler.html#method.handle_request_async),
+    /// returning a future that owns a clone of the handler, so it can be spawned. Calls within a
+    /// batch are handled concurrently.
+    pub fn handle_request_owned(&self, raw_request: Value) -> BoxFuture<'static, MaybeReply> {
+        let handler = self.handler.clone();
+        Box::pin(async move { handler.handle_request_async(raw_request).await })
+    }
+}
+
+impl<H: ?Sized> Clone for ArcServer<H> {
+    fn clone(&self) -> Self {
+        ArcServer {
+            handler: self.handler.clone(),
+        }
+    }
+}
+
+impl<H: ?Sized> std::ops::Deref for ArcServer<H> {
+    type Target = H;
+
+    fn deref(&self) -> &H {
+        &self.handler
+    }
+}
+
+impl<H: ?Sized> From<Arc<H>> for ArcServer<H> {
+    fn from(handler: Arc<H>) -> ArcServer<H> {
+        ArcServer::new(handler)
+    }
+}
+
+/// Returned by Handler::handle_request
+#[derive(Clone, PartialEq, Debug)]
+pub enum MaybeReply {
+    /// The value should be serialized and returned to the client.
+    Reply(Value),
+    /// The request consisted solely of notifications. No reply is necessary.
+    DontReply,
+}
+
+impl MaybeReply {
+    /// Convert to optional value.
+    pub fn as_option(self) -> Option<Value> {
+        match self {
+            MaybeReply::Reply(val) => Some(val),
+            MaybeReply::DontReply => None,
+        }
+    }
+}
+
+// Receives the malformed parts of a request along with the reason they failed to parse.
+type OnParseError<'a> = dyn FnMut(&str, &serde_json::Error) + 'a;
+
+// Passes each call of an already parsed raw request to `handle`. Requests that failed to parse
+// are answered with their parse error.
+fn handle_raw_request<F>(
+    handle: &mut F,
+    request: Result<jsonrpc_core::Request, Value>,
+) -> MaybeReply
+where
+    F: FnMut(Option<&Id>, &str, Params) -> Result<Value, jsonrpc_core::Error>,
+{
+    match request {
+        Ok(request) => reply(handle_parsed_request(handle, request)),
+        Err(parse_error) => MaybeReply::Reply(parse_error),
+    }
+}
+
+// Deserialize a jsonrpc request. If raw_request is not a valid request, returns the parse error
+// that should be sent back to the client.
+fn parse_raw_request(
+    raw_request: Value,
+    on_error: &mut OnParseError,
+) -> Result<jsonrpc_core::Request, Value> {
+    match jsonrpc_core::Request::deserialize(&raw_request) {
+        Ok(request) if !has_invalid_calls(&request) => Ok(request),
+        _ => parse_lenient(raw_request, on_error),
+    }
+}
+
+// Same as parse_raw_request, for a request that has not been parsed as json yet.
+fn parse_raw_bytes(
+    raw_request: &[u8],
+    on_error: &mut OnParseError,
+) -> Result<jsonrpc_core::Request, Value> {
+    match serde_json::from_slice(raw_request) {
+        Ok(request) if !has_invalid_calls(&request) => return Ok(request),
+        _ => {}
+    }
+    match serde_json::from_slice(raw_request) {
+        Ok(raw_request) => parse_lenient(raw_request, on_error),
+        Err(err) => {
+            on_error(&String::from_utf8_lossy(raw_request), &err);
+            Err(parse_error())
+        }
+    }
+}
+
+// The method call written as the shorthand `["method", arg1, arg2]`, if raw_request is one.
+fn parse_shorthand(raw_request: &str) -> Option<Value> {
+    if !raw_request.trim_start().starts_with('[') {
+        return None;
+    }
+    match serde_json::from_str(raw_request) {
+        Ok(Value::Array(mut items)) if matches!(items.first(), Some(Value::String(_))) => {
+            let method = items.remove(0);
+            Some(json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": items,
+                "id": null,
+            }))
+        }
+        _ => None,
+    }
+}
+
+fn has_invalid_calls(request: &jsonrpc_core::Request) -> bool {
+    let is_invalid = |call: &jsonrpc_core::Call| matches!(call, jsonrpc_core::Call::Invalid { .. });
+    match request {
+        jsonrpc_core::Request::Single(call) => is_invalid(call),
+        jsonrpc_core::Request::Batch(calls) => calls.iter().any(is_invalid),
+    }
+}
+
+// Second chance for json that failed to parse as a jsonrpc request, or that holds invalid calls.
+// The calls of a batch are parsed one by one, so a malformed call does not spoil the rest of the
+// batch. Calls that are not valid become Call::Invalid, keeping their id when it can be
+// recovered. Fails with an "Invalid request" error if raw_request is neither an object nor an
+// array, it is json but can't be a request.
+fn parse_lenient(
+    raw_request: Value,
+    on_error: &mut OnParseError,
+) -> Result<jsonrpc_core::Request, Value> {
+    match raw_request {
+        Value::Array(calls) => Ok(jsonrpc_core::Request::Batch(
+            calls
+                .into_iter()
+                .map(|call| parse_call_lenient(call, on_error))
+                .collect(),
+        )),
+        call @ Value::Object(_) => Ok(jsonrpc_core::Request::Single(parse_call_lenient(
+            call, on_error,
+        ))),
+        raw_request => {
+            if let Err(err) = jsonrpc_core::Request::deserialize(&raw_request) {
+                on_error(&raw_request.to_string(), &err);
+            }
+            Err(not_a_request())
+        }
+    }
+}
+
+fn parse_call_lenient(raw_call: Value, on_error: &mut OnParseError) -> jsonrpc_core::Call {
+    let id = raw_call
+        .get("id")
+        .and_then(|id| Id::deserialize(id).ok())
+        .unwrap_or(Id::Null);
+    let err = match jsonrpc_core::Call::deserialize(&raw_call) {
+        // anything with a valid id parses as an invalid call, the error that matters is why it
+        // isn't a method call or notification
+        Ok(jsonrpc_core::Call::Invalid { .. }) if raw_call.get("id").is_some() => {
+            MethodCall::deserialize(&raw_call).err()
+        }
+        Ok(jsonrpc_core::Call::Invalid { .. }) => Notification::deserialize(&raw_call).err(),
+        Ok(call) => return call,
+        Err(err) => Some(err),
+    };
+    if let Some(err) = err {
+        on_error(&raw_call.to_string(), &err);
+    }
+    jsonrpc_core::Call::Invalid { id }
+}
+
+// Reply sent when the request can't be parsed.
+fn parse_error() -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": {
+            "code": -32700,
+            "message": "Parse error"
+        },
+        "id": null
+    })
+}
+
+// Reply sent when the request is json, but neither a call nor a batch of calls.
+fn not_a_request() -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": {
+            "code": -32600,
+            "message": "Invalid request"
+        },
+        "id": null
+    })
+}
+
+// Reply sent when a batch holds more calls than allowed by Config::max_batch_size.
+fn batch_too_large(max_batch_size: usize) -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": {
+            "code": -32600,
+            "message": "Invalid request",
+            "data": format!("batch exceeds the maximum of {} calls", max_batch_size),
+        },
+        "id": null
+    })
+}
+
+// Reply sent when a request is nested more deeply than allowed by Config::max_depth.
+fn too_deep(max_depth: usize) -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": {
+            "code": -32602,
+            "message": "Invalid params",
+            "data": format!("request exceeds the maximum nesting depth of {}", max_depth),
+        },
+        "id": null
+    })
+}
+
+// Whether raw_request nests arrays and objects more than max_depth levels deep. Brackets within
+// strings are skipped, the input is not otherwise validated.
+fn exceeds_depth(raw_request: &[u8], max_depth: usize) -> bool {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for byte in raw_request {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > max_depth {
+                    return true;
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    false
+}
+
+// Reply sent when the response can't be serialized.
+fn serialization_error(e: serde_json::Error) -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": {
+            "code": SERIALIZATION_ERROR,
+            "message": "Serialization error",
+            "data": format!("{}", e),
+        },
+        "id": null
+    })
+}
+
+// Serialize the response to a handled request, if any.
+fn reply(response: Option<jsonrpc_core::Response>) -> MaybeReply {
+    let response = match response {
+        Some(ret) => ret,
+        None => return MaybeReply::DontReply,
+    };
+    MaybeReply::Reply(serde_json::to_value(response).unwrap_or_else(serialization_error))
+}
+
+// Same as handle_raw_request, for a request checked by check_raw_bytes. Serializes to bytes
+// directly.
+fn handle_raw_bytes<F>(
+    handle: &mut F,
+    request: Result<CheckedRequest, RawReply>,
+    config: &Config,
+) -> Option<Vec<u8>>
+where
+    F: FnMut(Option<&Id>, &str, Params) -> Result<Value, jsonrpc_core::Error>,
+{
+    let reply = respond_raw_bytes(handle, request, config)?;
+    // a Vec can't grow beyond isize::MAX bytes anyway
+    Some(serialize_reply(&reply, config, usize::MAX).expect("reply exceeds usize::MAX bytes"))
+}
+
+// Same as handle_raw_bytes, but fails once the serialized reply grows beyond max_bytes.
+fn handle_raw_bytes_capped<F>(
+    handle: &mut F,
+    request: Result<CheckedRequest, RawReply>,
+    config: &Config,
+    max_bytes: usize,
+) -> Option<Result<Vec<u8>, ResponseTooLarge>>
+where
+    F: FnMut(Option<&Id>, &str, Params) -> Result<Value, jsonrpc_core::Error>,
+{
+    let reply = respond_raw_bytes(handle, request, config)?;
+    Some(serialize_reply(&reply, config, max_bytes))
+}
+
+// A reply to a raw request, before serialization.
+enum RawReply {
+    Response(jsonrpc_core::Response),
+    // replies that don't answer any particular call, like parse errors
+    Error(Value),
+}
+
+// A raw request that parsed and passed the checks of a Config, ready to be handled.
+struct CheckedRequest {
+    request: jsonrpc_core::Request,
+    // the names repeated within the named params of each call, when keys are strict
+    duplicates: Option<Vec<Vec<String>>>,
+}
+
+// Parse raw_request after checking it against the limits of config. Fails with the reply to send
+// in place of handling the request.
+fn check_raw_bytes(
+    raw_request: &[u8],
+    config: &Config,
+    on_error: &mut OnParseError,
+) -> Result<CheckedRequest, RawReply> {
+    if let Some(max_depth) = config.max_depth {
+        // checked before parsing, without recursion, so deep nesting can't exhaust the stack
+        if exceeds_depth(raw_request, max_depth) {
+            return Err(error_reply(too_deep(max_depth), config));
+        }
+    }
+    if let Some(max_batch_size) = config.max_batch_size {
+        // checked before parsing, so the calls of an oversized batch are never built
+        if batch_len(raw_request) > max_batch_size {
+            return Err(error_reply(batch_too_large(max_batch_size), config));
+        }
+    }
+    let request = parse_raw_bytes(raw_request, on_error)
+        .map_err(|parse_error| error_reply(parse_error, config))?;
+    // by now serde_json has silently dropped all but the last of any repeated key
+    let duplicates = match config.strict_keys {
+        true => Some(duplicate_param_names(raw_request)),
+        false => None,
+    };
+    Ok(CheckedRequest {
+        request,
+        duplicates,
+    })
+}
+
+fn respond_raw_bytes<F>(
+    handle: &mut F,
+    request: Result<CheckedRequest, RawReply>,
+    config: &Config,
+) -> Option<RawReply>
+where
+    F: FnMut(Option<&Id>, &str, Params) -> Result<Value, jsonrpc_core::Error>,
+{
+    let CheckedRequest {
+        request,
+        duplicates,
+    } = match request {
+        Ok(request) => request,
+        Err(reply) => return Some(reply),
+    };
+    let response = if duplicates.is_some() || config.strict_version {
+        handle_calls(request, |index, call| {
+            if config.strict_version && lacks_version(&call) {
+                return reject_call(call, Error::invalid_request());
+            }
+            match duplicates
+                .as_ref()
+                .and_then(|duplicates| duplicates.get(index))
+            {
+                Some(names) if !names.is_empty() => reject_call(
+                    call,
+                    InvalidArgs::DuplicateNamedParameter {
+                        names: names.clone(),
+                    }
+                    .into(),
+                ),
+                _ => handle_call(handle, call),
+            }
+        })?
+    } else {
+        handle_parsed_request(handle, request)?
+    };
+    Some(RawReply::Response(config.version_field.apply(response)))
+}
+
+fn error_reply(mut reply: Value, config: &Config) -> RawReply {
+    if config.version_field == VersionField::Omit {
+        if let Value::Object(members) = &mut reply {
+            members.remove("jsonrpc");
+        }
+    }
+    RawReply::Error(reply)
+}
+
+// Serialize reply, giving up as soon as more than max_bytes have been written. Replies that fail
+// to serialize are replaced by a serialization error.
+fn serialize_reply(
+    reply: &RawReply,
+    config: &Config,
+    max_bytes: usize,
+) -> Result<Vec<u8>, ResponseTooLarge> {
+    let mut out = CappedWriter {
+        bytes: Vec::new(),
+        max_bytes,
+    };
+    let written = match reply {
+        RawReply::Response(response) => serde_json::to_writer(&mut out, response),
+        RawReply::Error(error) => serde_json::to_writer(&mut out, error),
+    };
+    match written {
+        Ok(()) => Ok(out.bytes),
+        // the only io error CappedWriter produces
+        Err(e) if e.is_io() => Err(ResponseTooLarge { max_bytes }),
+        Err(e) => serialize_reply(
+            &error_reply(serialization_error(e), config),
+            config,
+            max_bytes,
+        ),
+    }
+}
+
+// Serialize reply with the members of every object sorted by key.
+fn serialize_canonical(reply: &RawReply) -> String {
+    let reply = match reply {
+        RawReply::Response(response) => serde_json::to_value(response),
+        RawReply::Error(error) => Ok(error.clone()),
+    };
+    canonical(reply.unwrap_or_else(serialization_error)).to_string()
+}
+
+// Rebuild objects with their members inserted in key order. Map sorts by key by itself, unless
+// serde_json's "preserve_order" feature is enabled somewhere in the dependency graph, in which
+// case it keeps insertion order.
+fn canonical(value: Value) -> Value {
+    match value {
+        Value::Object(members) => {
+            let mut members: Vec<(String, Value)> = members.into_iter().collect();
+            members.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(
+                members
+                    .into_iter()
+                    .map(|(key, value)| (key, canonical(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(elements) => Value::Array(elements.into_iter().map(canonical).collect()),
+        value => value,
+    }
+}
+
+struct CappedWriter {
+    bytes: Vec<u8>,
+    max_bytes: usize,
+}
+
+impl std::io::Write for CappedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.len() > self.max_bytes - self.bytes.len() {
+            return Err(std::io::ErrorKind::WriteZero.into());
+        }
+        self.bytes.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returned by [Handler::handle_bytes_capped](trait.Handler.html#method.handle_bytes_capped) when
+/// the serialized reply would be larger than allowed.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct ResponseTooLarge {
+    /// The size limit that was exceeded.
+    pub max_bytes: usize,
+}
+
+impl std::fmt::Display for ResponseTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "response exceeds the maximum of {} bytes",
+            self.max_bytes
+        )
+    }
+}
+
+impl std::error::Error for ResponseTooLarge {}
+
+/// Options for handling requests, see
+/// [Handler::handle_bytes_with](trait.Handler.html#method.handle_bytes_with).
+///
+/// ```
+/// use easy_jsonrpc::{Config, VersionField};
+///
+/// let config = Config::new()
+///     .strict_keys(true)
+///     .max_batch_size(100)
+///     .max_depth(32)
+///     .strict_version(true)
+///     .version_field(VersionField::V2);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    strict_keys: bool,
+    max_batch_size: Option<usize>,
+    max_depth: Option<usize>,
+    strict_version: bool,
+    version_field: VersionField,
+}
+
+impl Config {
+    /// The default options, same as used by
+    /// [Handler::handle_bytes](trait.Handler.html#method.handle_bytes).
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    /// When set, calls whose named parameters contain the same key more than once are answered
+    /// with an "Invalid params" error rather than handled with the last value for that key.
+    /// Defaults to false.
+    pub fn strict_keys(mut self, strict_keys: bool) -> Config {
+        self.strict_keys = strict_keys;
+        self
+    }
+
+    /// When set, batches holding more than max_batch_size calls are answered with a single
+    /// "Invalid request" error, without handling any of the calls. Defaults to no limit.
+    pub fn max_batch_size(mut self, max_batch_size: usize) -> Config {
+        self.max_batch_size = Some(max_batch_size);
+        self
+    }
+
+    /// When set, requests nesting arrays and objects more than max_depth levels deep are answered
+    /// with a single "Invalid params" error, without being parsed. The request itself counts as
+    /// the first level, so the params of the call `{"params": [[1]]}` are at depth 3. Defaults
+    /// to no limit, though serde_json refuses to parse input nested more than 128 levels deep.
+    pub fn max_depth(mut self, max_depth: usize) -> Config {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// When set, calls without a `"jsonrpc": "2.0"` member are answered with an "Invalid request"
+    /// error rather than handled as jsonrpc 1.0 calls, notifications still get no reply. Calls
+    /// declaring any other version are rejected either way. Defaults to false.
+    pub fn strict_version(mut self, strict_version: bool) -> Config {
+        self.strict_version = strict_version;
+        self
+    }
+
+    /// Controls the "jsonrpc" member of responses, for peers that expect something other than
+    /// an echo of the version they sent. Defaults to
+    /// [VersionField::Echo](enum.VersionField.html#variant.Echo).
+    pub fn version_field(mut self, version_field: VersionField) -> Config {
+        self.version_field = version_field;
+        self
+    }
+}
+
+/// How the "jsonrpc" member of responses is written, see
+/// [Config::version_field](struct.Config.html#method.version_field).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VersionField {
+    /// The version of each call is echoed in its response. Responses to calls too malformed to
+    /// tell their version have no "jsonrpc" member, replies not answering any particular call,
+    /// like parse errors, are marked "2.0".
+    #[default]
+    Echo,
+    /// Every response is marked "2.0", including those to malformed calls.
+    V2,
+    /// No response has a "jsonrpc" member, as expected by jsonrpc 1.0 peers.
+    Omit,
+}
+
+impl VersionField {
+    fn apply(self, response: jsonrpc_core::Response) -> jsonrpc_core::Response {
+        let version = match self {
+            VersionField::Echo => return response,
+            VersionField::V2 => Some(Version::V2),
+            VersionField::Omit => None,
+        };
+        let set_version = |output: Output| match output {
+            Output::Success(success) => Output::Success(Success {
+                jsonrpc: version,
+                ..success
+            }),
+            Output::Failure(failure) => Output::Failure(Failure {
+                jsonrpc: version,
+                ..failure
+            }),
+        };
+        match response {
+            jsonrpc_core::Response::Single(output) => {
+                jsonrpc_core::Response::Single(set_version(output))
+            }
+            jsonrpc_core::Response::Batch(outputs) => {
+                jsonrpc_core::Response::Batch(outputs.into_iter().map(set_version).collect())
+            }
+        }
+    }
+}
+
+// The number of elements in raw_request if it is a json array, otherwise 0. Elements are skipped
+// over rather than parsed.
+fn batch_len(raw_request: &[u8]) -> usize {
+    use serde::de::{Deserializer, IgnoredAny, SeqAccess, Visitor};
+    use std::fmt;
+
+    struct Len;
+
+    impl<'de> Visitor<'de> for Len {
+        type Value = usize;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a json array")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<usize, A::Error> {
+            let mut len = 0;
+            while seq.next_element::<IgnoredAny>()?.is_some() {
+                len += 1;
+            }
+            Ok(len)
+        }
+    }
+
+    serde_json::Deserializer::from_slice(raw_request)
+        .deserialize_seq(Len)
+        .unwrap_or(0)
+}
+
+// For each call in raw_request, in order, list the keys that appear more than once in its named
+// parameters. Anything that is not a well formed call is treated as having no duplicates, so
+// parsing it normally decides how it is answered.
+fn duplicate_param_names(raw_request: &[u8]) -> Vec<Vec<String>> {
+    use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+    use std::fmt;
+
+    // Scans a json value. Scan::Call looks for the params member of an object and scans it with
+    // Scan::Params, which lists the keys that are repeated. Anything else has no duplicates.
+    #[derive(Clone, Copy)]
+    enum Scan {
+        Call,
+        Params,
+    }
+
+    impl<'de> Visitor<'de> for Scan {
+        type Value = Vec<String>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("any json value")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut seen = Vec::new();
+            let mut duplicates = Vec::new();
+            while let Some(key) = map.next_key::<String>()? {
+                match self {
+                    Scan::Call if key == "params" => {
+                        duplicates = map.next_value_seed(Scan::Params)?
+                    }
+                    Scan::Call => {
+                        map.next_value::<IgnoredAny>()?;
+                    }
+                    Scan::Params => {
+                        map.next_value::<IgnoredAny>()?;
+                        if !seen.contains(&key) {
+                            seen.push(key);
+                        } else if !duplicates.contains(&key) {
+                            duplicates.push(key);
+                        }
+                    }
+                }
+            }
+            Ok(duplicates)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            while seq.next_element::<IgnoredAny>()?.is_some() {}
+            Ok(Vec::new())
+        }
+
+        fn visit_bool<E>(self, _: bool) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+
+        fn visit_i64<E>(self, _: i64) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+
+        fn visit_u64<E>(self, _: u64) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+
+        fn visit_f64<E>(self, _: f64) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+
+        fn visit_str<E>(self, _: &str) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+    }
+
+    impl<'de> DeserializeSeed<'de> for Scan {
+        type Value = Vec<String>;
+
+        fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+            d.deserialize_any(self)
+        }
+    }
+
+    // a single call or a batch of calls
+    struct Request;
+
+    impl<'de> Visitor<'de> for Request {
+        type Value = Vec<Vec<String>>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a jsonrpc request")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+            Ok(vec![Scan::Call.visit_map(map)?])
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut calls = Vec::new();
+            while let Some(call) = seq.next_element_seed(Scan::Call)? {
+                calls.push(call);
+            }
+            Ok(calls)
+        }
+    }
+
+    serde_json::Deserializer::from_slice(raw_request)
+        .deserialize_any(Request)
+        .unwrap_or_default()
+}
+
+/// Parse a jsonrpc request for use with [Handler::handle_parsed](trait.Handler.html#method.handle_parsed).
+///
+/// Malformed calls are parsed as invalid calls, which are answered with an "Invalid request"
+/// error echoing the id of the call if it has a valid one. Input that is not a json object or
+/// array is parsed as a single invalid call with a null id.
+pub fn parse_request(raw_request: &[u8]) -> types::Request {
+    parse_raw_bytes(raw_request, &mut |_, _| {}).unwrap_or(types::Request::Single(
+        types::Call::Invalid { id: Id::Null },
+    ))
+}
+
+/// Serialize a response returned by [Handler::handle_parsed](trait.Handler.html#method.handle_parsed).
+///
+/// If the response can't be serialized, a serialization error is returned in its place.
+pub fn serialize_response(response: &types::Response) -> String {
+    response_to_string(response)
+}
+
+/// Same as [serialize_response](fn.serialize_response.html).
+pub fn response_to_string(response: &types::Response) -> String {
+    serde_json::to_string(response).unwrap_or_else(|e| serialization_error(e).to_string())
+}
+
+/// Same as [response_to_string](fn.response_to_string.html), as bytes, for writing the body of
+/// an http response or a frame of another transport.
+pub fn response_to_bytes(response: &types::Response) -> Vec<u8> {
+    serde_json::to_vec(response).unwrap_or_else(|e| value_to_bytes(&serialization_error(e)))
+}
+
+/// Get the result of a single call from a response returned by
+/// [Handler::handle_parsed](trait.Handler.html#method.handle_parsed).
+///
+/// A failure is returned as `ResponseFail::RpcError`. A batch response, or a result that can't
+/// be deserialized as T, is returned as `ResponseFail::InvalidResponse`.
+pub fn parse_single_result<T: DeserializeOwned>(
+    response: types::Response,
+) -> Result<T, ResponseFail> {
+    match response {
+        jsonrpc_core::Response::Single(Output::Success(Success { result, .. })) => {
+            serde_json::from_value(result).map_err(|_| ResponseFail::InvalidResponse)
+        }
+        jsonrpc_core::Response::Single(Output::Failure(Failure { error, .. })) => {
+            Err(ResponseFail::RpcError(error))
+        }
+        jsonrpc_core::Response::Batch(_) => Err(ResponseFail::InvalidResponse),
+    }
+}
+
+fn value_to_bytes(value: &Value) -> Vec<u8> {
+    serde_json::to_vec(value).expect("serializing a json value to a Vec never fails")
+}
+
+/// extract method name and parameters from call
+/// if call is a normal method call, call `handle` and return result
+/// if call is a notification, call `handle` and return None
+/// if call is invalid return a jsonrpc failure
+fn handle_call<F>(handle: &mut F, call: jsonrpc_core::Call) -> Option<Output>
+where
+    F: FnMut(Option<&Id>, &str, Params) -> Result<Value, jsonrpc_core::Error>,
+{
+    let (method, params, maybe_id, version) = match split_call(call) {
+        Ok(parts) => parts,
+        Err(invalid) => return Some(invalid),
+    };
+    let ret = traced(&method, maybe_id.as_ref(), || {
+        catch_panic(&method, || handle(maybe_id.as_ref(), &method, params))
+    });
+    Some(to_output(ret, maybe_id?, version))
+}
+
+// Run call, answering with the error of a CatchUnwindServer if it panics, so that the other
+// calls of a batch still get their results.
+//
+// Handlers are assumed to be unwind safe. Whatever state a panicking method leaves behind, for
+// example a half updated field of a HandlerMut or a poisoned mutex, is seen by the calls after
+// it.
+#[cfg(feature = "catch_unwind")]
+fn catch_panic<F>(method: &str, call: F) -> Result<Value, jsonrpc_core::Error>
+where
+    F: FnOnce() -> Result<Value, jsonrpc_core::Error>,
+{
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(call))
+        .unwrap_or_else(|payload| Err(crate::catch_unwind::panic_error(method, payload)))
+}
+
+#[cfg(not(feature = "catch_unwind"))]
+fn catch_panic<F>(_method: &str, call: F) -> Result<Value, jsonrpc_core::Error>
+where
+    F: FnOnce() -> Result<Value, jsonrpc_core::Error>,
+{
+    call()
+}
+
+// Async counterpart to catch_panic, covering both the call starting the future and its polls.
+#[cfg(all(feature = "async", feature = "catch_unwind"))]
+async fn catch_panic_async<'a, F>(method: &str, call: F) -> Result<Value, jsonrpc_core::Error>
+where
+    F: FnOnce() -> BoxFuture<'a, Result<Value, jsonrpc_core::Error>>,
+{
+    use futures::future::FutureExt;
+    use std::panic::AssertUnwindSafe;
+    let ret = match std::panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(future) => AssertUnwindSafe(future).catch_unwind().await,
+        Err(payload) => Err(payload),
+    };
+    ret.unwrap_or_else(|payload| Err(crate::catch_unwind::panic_error(method, payload)))
+}
+
+#[cfg(all(feature = "async", not(feature = "catch_unwind")))]
+async fn catch_panic_async<'a, F>(_method: &str, call: F) -> Result<Value, jsonrpc_core::Error>
+where
+    F: FnOnce() -> BoxFuture<'a, Result<Value, jsonrpc_core::Error>>,
+{
+    call().await
+}
+
+// Run call inside a span named "rpc_call" recording the method name, whether the call is a
+// notification, whether it succeeded and, if not, the error code.
+#[cfg(feature = "tracing")]
+fn traced<F>(method: &str, id: Option<&Id>, call: F) -> Result<Value, jsonrpc_core::Error>
+where
+    F: FnOnce() -> Result<Value, jsonrpc_core::Error>,
+{
+    let span = call_span(method, id);
+    let ret = span.in_scope(call);
+    record_outcome(&span, &ret);
+    ret
+}
+
+#[cfg(not(feature = "tracing"))]
+fn traced<F>(_method: &str, _id: Option<&Id>, call: F) -> Result<Value, jsonrpc_core::Error>
+where
+    F: FnOnce() -> Result<Value, jsonrpc_core::Error>,
+{
+    call()
+}
+
+#[cfg(feature = "tracing")]
+fn call_span(method: &str, id: Option<&Id>) -> tracing::Span {
+    tracing::info_span!(
+        "rpc_call",
+        method,
+        notification = id.is_none(),
+        success = tracing::field::Empty,
+        error_code = tracing::field::Empty,
+    )
+}
+
+#[cfg(feature = "tracing")]
+fn record_outcome(span: &tracing::Span, ret: &Result<Value, jsonrpc_core::Error>) {
+    span.record("success", ret.is_ok());
+    if let Err(err) = ret {
+        span.record("error_code", err.code.code());
+    }
+}
+
+// The parts of a valid call, or a jsonrpc failure if the call is invalid.
+type CallParts = (String, Params, Option<Id>, Option<Version>);
+
+// extract method name, parameters, id and version from call
+fn split_call(call: jsonrpc_core::Call) -> Result<CallParts, Output> {
+    let (method, params, maybe_id, version) = match call {
+        jsonrpc_core::Call::Invalid { id } => {
+            return Err(Output::invalid_request(id, None));
+        }
+        jsonrpc_core::Call::MethodCall(MethodCall {
+            method,
+            params,
+            id,
+            jsonrpc,
+        }) => (method, params, Some(id), jsonrpc),
+        jsonrpc_core::Call::Notification(Notification {
+            method,
+            params,
+            jsonrpc,
+        }) => (method, params, None, jsonrpc),
+    };
+    Ok((method, Params::from_rc_params(params), maybe_id, version))
+}
+
+// wrap the result of a method call in a jsonrpc output
+fn to_output(ret: Result<Value, jsonrpc_core::Error>, id: Id, version: Option<Version>) -> Output {
+    match ret {
+        Ok(ok) => Output::Success(Success {
+            jsonrpc: version,
+            result: ok,
+            id,
+        }),
+        Err(err) => Output::Failure(Failure {
+            jsonrpc: version,
+            error: err,
+            id,
+        }),
+    }
+}
+
+// Handle a request after it has been successfuly deserialized, this function is private to avoid
+// exposing jsonrpc_core types to the user. Also, it's not needed externally.
+fn handle_parsed_request<F>(
+    handle: &mut F,
+    request: jsonrpc_core::Request,
+) -> Option<jsonrpc_core::Response>
+where
+    F: FnMut(Option<&Id>, &str, Params) -> Result<Value, jsonrpc_core::Error>,
+{
+    handle_calls(request, |_, call| handle_call(handle, call))
+}
+
+// The jsonrpc spec requires an empty batch to be answered with a single "Invalid request" error,
+// unlike a batch of notifications, which is not answered at all.
+fn empty_batch() -> jsonrpc_core::Response {
+    jsonrpc_core::Response::Single(Output::invalid_request(Id::Null, Some(Version::V2)))
+}
+
+// Pass each call in request, along with its position in the batch, to handle_call and collect the
+// outputs into a response.
+fn handle_calls<F>(
+    request: jsonrpc_core::Request,
+    mut handle_call: F,
+) -> Option<jsonrpc_core::Response>
+where
+    F: FnMut(usize, jsonrpc_core::Call) -> Option<Output>,
+{
+    match request {
+        jsonrpc_core::Request::Single(call) => {
+            handle_call(0, call).map(jsonrpc_core::Response::Single)
+        }
+        jsonrpc_core::Request::Batch(calls) if calls.is_empty() => Some(empty_batch()),
+        jsonrpc_core::Request::Batch(mut calls) => {
+            let outputs = calls
+                .drain(..)
+                .enumerate()
+                .filter_map(|(index, call)| handle_call(index, call))
+                .collect::<Vec<_>>();
+            if outputs.is_empty() {
+                None
+            } else {
+                Some(jsonrpc_core::Response::Batch(outputs))
+            }
+        }
+    }
+}
+
+// Whether call has no "jsonrpc" member, as sent by jsonrpc 1.0 clients. Invalid calls are
+// answered as such whatever their version.
+fn lacks_version(call: &jsonrpc_core::Call) -> bool {
+    match call {
+        jsonrpc_core::Call::MethodCall(MethodCall { jsonrpc, .. })
+        | jsonrpc_core::Call::Notification(Notification { jsonrpc, .. }) => jsonrpc.is_none(),
+        jsonrpc_core::Call::Invalid { .. } => false,
+    }
+}
+
+// Answer call with err without handling it. Notifications still get no reply.
+fn reject_call(call: jsonrpc_core::Call, err: jsonrpc_core::Error) -> Option<Output> {
+    let (_, _, maybe_id, version) = match split_call(call) {
+        Ok(parts) => parts,
+        Err(invalid) => return Some(invalid),
+    };
+    Some(to_output(Err(err), maybe_id?, version))
+}
+
+// Same as handle_parsed_request, but handles the calls of a batch concurrently.
+#[cfg(feature = "parallel")]
+fn handle_parsed_request_parallel<F>(
+    handle: &F,
+    request: jsonrpc_core::Request,
+) -> Option<jsonrpc_core::Response>
+where
+    F: Fn(Option<&Id>, &str, Params) -> Result<Value, jsonrpc_core::Error> + Sync,
+{
+    use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+    match request {
+        jsonrpc_core::Request::Single(call) => handle_call(
+            &mut |id: Option<&Id>, method: &str, params| handle(id, method, params),
+            call,
+        )
+        .map(jsonrpc_core::Response::Single),
+        jsonrpc_core::Request::Batch(calls) if calls.is_empty() => Some(empty_batch()),
+        jsonrpc_core::Request::Batch(calls) => {
+            // collecting an indexed parallel iterator preserves the order of the calls
+            let outputs = calls
+                .into_par_iter()
+                .map(|call| {
+                    handle_call(
+                        &mut |id: Option<&Id>, method: &str, params| handle(id, method, params),
+                        call,
+                    )
+                })
+                .collect::<Vec<Option<Output>>>();
+            let outputs = outputs.into_iter().flatten().collect::<Vec<_>>();
+            if outputs.is_empty() {
+                None
+            } else {
+                Some(jsonrpc_core::Response::Batch(outputs))
+            }
+        }
+    }
+}
+
+// Async counterpart to handle_call.
+#[cfg(feature = "async")]
+async fn handle_call_async<H: AsyncHandler + ?Sized>(
+    handler: &H,
+    call: jsonrpc_core::Call,
+) -> Option<Output> {
+    let (method, params, maybe_id, version) = match split_call(call) {
+        Ok(parts) => parts,
+        Err(invalid) => return Some(invalid),
+    };
+    let id = maybe_id.as_ref().unwrap_or(&Id::Null);
+    let ret = catch_panic_async(&method, || {
+        handler.handle_async_with_id(id, &method, params)
+    });
+    #[cfg(feature = "tracing")]
+    let ret = {
+        use tracing::Instrument;
+        let span = call_span(&method, maybe_id.as_ref());
+        let ret = ret.instrument(span.clone()).await;
+        record_outcome(&span, &ret);
+        ret
+    };
+    #[cfg(not(feature = "tracing"))]
+    let ret = ret.await;
+    Some(to_output(ret, maybe_id?, version))
+}
+
+// Async counterpart to handle_parsed_request.
+#[cfg(feature = "async")]
+async fn handle_parsed_request_async<H: AsyncHandler + ?Sized>(
+    handler: &H,
+    request: jsonrpc_core::Request,
+) -> Option<jsonrpc_core::Response> {
+    match request {
+        jsonrpc_core::Request::Single(call) => handle_call_async(handler, call)
+            .await
+            .map(jsonrpc_core::Response::Single),
+        jsonrpc_core::Request::Batch(calls) if calls.is_empty() => Some(empty_batch()),
+        jsonrpc_core::Request::Batch(calls) => {
+            let outputs = futures::future::join_all(
+                calls
+                    .into_iter()
+                    .map(|call| handle_call_async(handler, call)),
+            )
+            .await
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+            if outputs.is_empty() {
+                None
+            } else {
+                Some(jsonrpc_core::Response::Batch(outputs))
+            }
+        }
+    }
+}
+
+impl From<InvalidArgs> for Error {
+    fn from(invalid_args: InvalidArgs) -> Error {
+        match invalid_args {
+            InvalidArgs::WrongNumberOfArgs { expected, actual } => Error::invalid_params(format!(
+                "WrongNumberOfArgs. Expected {}. Actual {}",
+                expected, actual
+            )),
+            InvalidArgs::ExtraNamedParameter { names } => {
+                Error::invalid_params(format!("ExtraNamedParameter {}", names.join(", ")))
+            }
+            InvalidArgs::MissingNamedParameter { names } => {
+                Error::invalid_params(format!("MissingNamedParameter {}", names.join(", ")))
+            }
+            InvalidArgs::DuplicateNamedParameter { names } => {
+                Error::invalid_params(format!("DuplicateNamedParameter {}", names.join(", ")))
+            }
+            InvalidArgs::InvalidArgStructure {
+                name,
+                index,
+                detail,
+            } => Error::invalid_params(format!(
+                "InvalidArgStructure {} at position {}: {}",
+                name, index, detail
+            )),
+            InvalidArgs::NamedParamsRequired => {
+                Error::invalid_params("this method requires named parameters")
+            }
+            InvalidArgs::PositionalParamsRequired => {
+                Error::invalid_params("this method requires positional parameters")
+            }
+            InvalidArgs::InvalidParamsStructure { detail } => {
+                Error::invalid_params(format!("InvalidParamsStructure: {}", detail))
+            }
+        }
+    }
+}
+
+/// Params checked against a list of argument names, deserialized one argument at a time with
+/// the errors the handlers generated by the [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html)
+/// macro report. For [Dispatcher](struct.Dispatcher.html) closures and fallback methods that
+/// look at some arguments before deciding how to deserialize the others.
+///
+/// Each argument can be passed positionally, at the index of its name, or as a named parameter.
+///
+/// ```
+/// # use easy_jsonrpc::{Dispatcher, Handler, Params, TypedParams};
+/// # use serde_json::json;
+/// let dispatcher = Dispatcher::new().method("area", |params: Params| {
+///     let params = TypedParams::new(params, &["shape", "size"])?;
+///     let size: f64 = params.get(1)?;
+///     match params.get_named::<String>("shape")?.as_str() {
+///         "square" => Ok(json!(size * size)),
+///         _ => Ok(json!(null)),
+///     }
+/// });
+/// let reply = dispatcher.handle_request(
+///     json!({"jsonrpc": "2.0", "method": "area", "params": {"shape": "square", "size": 2}, "id": 1}),
+/// );
+/// assert_eq!(reply.as_option().unwrap()["result"], json!(4.0));
+/// ```
+#[derive(Debug)]
+pub struct TypedParams {
+    names: &'static [&'static str],
+    args: Vec<Option<Value>>,
+    positional: Option<usize>, // the number of positional parameters, None if named
+}
+
+impl TypedParams {
+    /// Check params against the names of the arguments, like
+    /// [Params::get_rpc_args](enum.Params.html#method.get_rpc_args) but letting any argument be
+    /// omitted: more positional parameters than names, or named parameters not in names, are
+    /// rejected.
+    pub fn new(params: Params, names: &'static [&'static str]) -> Result<TypedParams, Error> {
+        let positional = match &params {
+            Params::Positional(ar) => Some(ar.len()),
+            Params::Named(_) => None,
+        };
+        let args = params.get_optional_rpc_args(names, 0)?;
+        Ok(TypedParams {
+            names,
+            args,
+            positional,
+        })
+    }
+
+    /// Deserialize the argument at index, the index of its name. An omitted argument is
+    /// deserialized from null, so `Option` arguments may be left out, others are reported as
+    /// missing. Panics if index is out of bounds of the names.
+    pub fn get<T: DeserializeOwned>(&self, index: usize) -> Result<T, Error> {
+        let name = self.names[index];
+        let missing = || match self.positional {
+            Some(actual) => InvalidArgs::WrongNumberOfArgs {
+                expected: index + 1,
+                actual,
+            },
+            None => InvalidArgs::MissingNamedParameter { names: vec![name] },
+        };
+        match &self.args[index] {
+            Some(arg) => T::deserialize(arg).map_err(|e| {
+                InvalidArgs::InvalidArgStructure {
+                    name,
+                    index,
+                    detail: e.to_string(),
+                }
+                .into()
+            }),
+            None => T::deserialize(&Value::Null).map_err(|_| missing().into()),
+        }
+    }
+
+    /// Same as [get](#method.get), for the argument called name. Panics if name is not one of
+    /// the names.
+    pub fn get_named<T: DeserializeOwned>(&self, name: &str) -> Result<T, Error> {
+        let index = self
+            .names
+            .iter()
+            .position(|known| *known == name)
+            .unwrap_or_else(|| panic!("{:?} is not an argument name", name));
+        self.get(index)
+    }
+
+    /// Whether the argument at index was passed.
+    pub fn contains(&self, index: usize) -> bool {
+        self.args[index].is_some()
+    }
+}
+
+// Intentionally does not implement Serialize; we don't want users to accidentally send a call by
+// itself. Does not implement clone because Vec<Value> is potentially expensive to clone.
+/// Create a binding of arguments to a method name. Can be turned into either a jsonrpc call using
+/// [call](#method.call), or a jsonrpc notification using [notification](#method.notification).
+#[derive(Debug)]
+pub struct BoundMethod<'a, T>
+where
+    T: Deserialize<'static>,
+{
+    method: &'a str,
+    args: Vec<Value>,
+    names: ParamNames,
+    idempotent: bool, // whether the call may safely be retried
+    _spook: PhantomData<*const T>,
+}
+
+impl<'a, T> BoundMethod<'a, T>
+where
+    T: Deserialize<'static>,
+{
+    /// Create a binding of arguments to a method name.
+    /// You probably don't want to use this method directly.
+    /// Try using the rpc macro instead.
+    pub fn new(method: &'a str, args: Vec<Value>) -> BoundMethod<'a, T> {
+        BoundMethod {
+            method,
+            args,
+            names: ParamNames::Positional,
+            idempotent: false,
+            _spook: PhantomData,
+        }
+    }
+
+    /// Same as [new](#method.new), but the arguments are sent as named parameters, the nth
+    /// argument under the nth name.
+    pub fn new_named(
+        method: &'a str,
+        names: &'static [&'static str],
+        args: Vec<Value>,
+    ) -> BoundMethod<'a, T> {
+        debug_assert_eq!(names.len(), args.len());
+        BoundMethod {
+            method,
+            args,
+            names: ParamNames::Named(names),
+            idempotent: false,
+            _spook: PhantomData,
+        }
+    }
+
+    /// Same as [new](#method.new) for a single argument, a json object, which is sent as the
+    /// named parameters themselves. Used for methods with an argument marked `#[rpc(flatten)]`.
+    pub fn new_flattened(method: &'a str, arg: Value) -> BoundMethod<'a, T> {
+        BoundMethod {
+            method,
+            args: vec![arg],
+            names: ParamNames::Flattened,
+            idempotent: false,
+            _spook: PhantomData,
+        }
+    }
+
+    /// Same as [new_named](#method.new_named), but the last argument, a json object, holds extra
+    /// named parameters sent along with the others. Used for methods with an argument marked
+    /// `#[rpc(rest)]`.
+    pub fn new_named_rest(
+        method: &'a str,
+        names: &'static [&'static str],
+        args: Vec<Value>,
+    ) -> BoundMethod<'a, T> {
+        debug_assert_eq!(names.len(), args.len());
+        BoundMethod {
+            method,
+            args,
+            names: ParamNames::NamedRest(names),
+            idempotent: false,
+            _spook: PhantomData,
+        }
+    }
+
+    /// Mark the method as idempotent. Generated client helpers do so for methods annotated with
+    /// `#[rpc(idempotent)]`.
+    pub fn mark_idempotent(mut self) -> BoundMethod<'a, T> {
+        self.idempotent = true;
+        self
+    }
+
+    /// Whether calling the method more than once has the same effect as calling it once. Only
+    /// idempotent calls may be retried by a transport after a transient failure, a call that may
+    /// or may not have reached the server must not be sent again otherwise.
+    pub fn is_idempotent(&self) -> bool {
+        self.idempotent
+    }
+
+    /// Create a jsonrpc method call with a random id and a tracker for retrieving the return value.
+    pub fn call(&'a self) -> (Call<'a>, Tracker<T>)
+    where
+        T: Deserialize<'static>,
+    {
+        self.call_with_id(Id::Num(rand::random::<u64>()))
+    }
+
+    /// Same as [call](#method.call), but the id of the call is taken from ids, for servers
+    /// expecting ids of a particular form.
+    pub fn call_with(&'a self, ids: &dyn IdGenerator) -> (Call<'a>, Tracker<T>)
+    where
+        T: Deserialize<'static>,
+    {
+        self.call_with_id(ids.next())
+    }
+
+    fn call_with_id(&'a self, id: Id) -> (Call<'a>, Tracker<T>)
+    where
+        T: Deserialize<'static>,
+    {
+        let Self {
+            method,
+            args,
+            names,
+            ..
+        } = self;
+        (
+            Call {
+                method,
+                args,
+                names: *names,
+                id: Some(id.clone()),
+            },
+            Tracker {
+                id,
+                _spook: PhantomData,
+            },
+        )
+    }
+
+    /// Same as [call](#method.call), but the call is returned as a typed jsonrpc method call, as
+    /// accepted by [Handler::handle_parsed](trait.Handler.html#method.handle_parsed). See also
+    /// the [rpc_call](macro.rpc_call.html) macro.
+    pub fn method_call(&'a self) -> (types::MethodCall, Tracker<T>) {
+        self.typed_call(self.call())
+    }
+
+    /// Same as [method_call](#method.method_call), but the id of the call is taken from ids.
+    pub fn method_call_with(&'a self, ids: &dyn IdGenerator) -> (types::MethodCall, Tracker<T>) {
+        self.typed_call(self.call_with(ids))
+    }
+
+    fn typed_call(&self, (call, tracker): (Call, Tracker<T>)) -> (types::MethodCall, Tracker<T>) {
+        let method_call = MethodCall {
+            jsonrpc: Some(Version::V2),
+            method: call.method.to_owned(),
+            params: call.params(),
+            id: tracker.id.clone(),
+        };
+        (method_call, tracker)
+    }
+
+    /// Call the method over transport and retrieve its return value. The typed clients generated
+    /// by the [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro call methods this way.
+    pub fn send<X: ?Sized + Transport>(
+        &'a self,
+        transport: &X,
+    ) -> Result<T, ClientError<X::Error>> {
+        let (call, tracker) = self.call();
+        let reply = transport
+            .send_request(&call.as_request())
+            .map_err(ClientError::Transport)?
+            .ok_or(ClientError::ResultNotFound)?;
+        let mut response =
+            Response::from_json_response(reply).map_err(|_| ClientError::InvalidResponse)?;
+        let result = response
+            .remove_id(&tracker.id)
+            .ok_or(ClientError::ResultNotFound)?;
+        deserialize_result(result.map_err(ClientError::Rpc)?)
+    }
+
+    /// Create a jsonrpc method call with no id. Jsonrpc servers accept notifications silently.
+    /// That is to say, they handle the notification, but send to reasponse.
+    pub fn notification(&'a self) -> Call<'a> {
+        let Self {
+            method,
+            args,
+            names,
+            ..
+        } = self;
+        Call {
+            method,
+            args,
+            names: *names,
+            id: None,
+        }
+    }
+}
+
+// Intentionally does not implement Serialize; we don't want users to accidentally send a call by
+// itself. Does not implement clone because Vec<Value> is potentially expensive to clone.
+/// A single rpc method call with arguments. May be sent to the server by itself using
+/// [as_request](#method.as_request), or as a batch, using
+/// [batch_request](#method.batch_request).
+pub struct Call<'a> {
+    method: &'a str,
+    args: &'a [Value],
+    names: ParamNames,
+    id: Option<Id>,
+}
+
+// How the arguments of a call are sent to the server.
+#[derive(Clone, Copy, Debug)]
+enum ParamNames {
+    Positional,
+    // the nth argument under the nth name
+    Named(&'static [&'static str]),
+    // the single argument is sent as the named parameters if it is a json object
+    Flattened,
+    // like Named, but the members of the last argument are sent as named parameters of their own
+    NamedRest(&'static [&'static str]),
+}
+
+impl<'a> Call<'a> {
+    /// Convert call to a json object which can be serialized and sent to a jsonrpc server.
+    pub fn as_request(&self) -> Value {
+        let Self { method, id, .. } = self;
+        let args = self.params();
+        match id {
+            Some(id) => json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": args,
+                "id": id,
+            }),
+            None => json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": args,
+            }),
+        }
+    }
+
+    // the arguments as sent to the server
+    fn params(&self) -> jsonrpc_core::Params {
+        match (self.names, self.args) {
+            (ParamNames::Named(names), args) => jsonrpc_core::Params::Map(
+                names
+                    .iter()
+                    .map(|name| name.to_string())
+                    .zip(args.iter().cloned())
+                    .collect(),
+            ),
+            (ParamNames::Flattened, [Value::Object(members)]) => {
+                jsonrpc_core::Params::Map(members.clone())
+            }
+            (ParamNames::NamedRest(names), [args @ .., Value::Object(rest)]) => {
+                let mut named: serde_json::Map<String, Value> = names
+                    .iter()
+                    .map(|name| name.to_string())
+                    .zip(args.iter().cloned())
+                    .collect();
+                // arguments of the method take precedence over extras of the same name
+                for (name, value) in rest {
+                    named.entry(name.clone()).or_insert_with(|| value.clone());
+                }
+                jsonrpc_core::Params::Map(named)
+            }
+            (ParamNames::NamedRest(names), args) => jsonrpc_core::Params::Map(
+                names
+                    .iter()
+                    .map(|name| name.to_string())
+                    .zip(args.iter().cloned())
+                    .collect(),
+            ),
+            (_, args) => jsonrpc_core::Params::Array(args.to_vec()),
+        }
+    }
+
+    /// Convert list of calls to a json object which can be serialized and sent to a jsonrpc server.
+    pub fn batch_request(calls: &[Self]) -> Value {
+        debug_assert!({
+            fn contains_duplicates(list: &[&Id]) -> bool {
+                (1..list.len()).any(|i| list[i..].contains(&list[i - 1]))
+            }
+            let ids = calls
+                .iter()
+                .filter_map(|call| call.id.as_ref())
+                .collect::<Vec<_>>();
+            !contains_duplicates(ids.as_slice())
+        });
+        Value::Array(calls.iter().map(Call::as_request).collect())
+    }
+}
+
+/// A binding of arguments to a method name, to be sent as a notification. The server sends no
+/// response to notifications.
+///
+/// Unlike [BoundMethod](struct.BoundMethod.html), which can be sent either way, this owns
+/// everything needed to produce the notification.
+#[derive(Debug)]
+pub struct BoundNotification {
+    method: &'static str,
+    args: Vec<Value>,
+    names: ParamNames,
+}
+
+impl BoundNotification {
+    /// Create a binding of arguments to a method name.
+    /// You probably don't want to use this method directly.
+    /// Try using the rpc macro instead.
+    pub fn new(method: &'static str, args: Vec<Value>) -> BoundNotification {
+        BoundNotification {
+            method,
+            args,
+            names: ParamNames::Positional,
+        }
+    }
+
+    /// Same as [new](#method.new), but the arguments are sent as named parameters, the nth
+    /// argument under the nth name.
+    pub fn new_named(
+        method: &'static str,
+        names: &'static [&'static str],
+        args: Vec<Value>,
+    ) -> BoundNotification {
+        debug_assert_eq!(names.len(), args.len());
+        BoundNotification {
+            method,
+            args,
+            names: ParamNames::Named(names),
+        }
+    }
+
+    /// Same as [BoundMethod::new_named_rest](struct.BoundMethod.html#method.new_named_rest), for
+    /// a notification.
+    pub fn new_named_rest(
+        method: &'static str,
+        names: &'static [&'static str],
+        args: Vec<Value>,
+    ) -> BoundNotification {
+        debug_assert_eq!(names.len(), args.len());
+        BoundNotification {
+            method,
+            args,
+            names: ParamNames::NamedRest(names),
+        }
+    }
+
+    /// Same as [BoundMethod::new_flattened](struct.BoundMethod.html#method.new_flattened), for
+    /// a notification.
+    pub fn new_flattened(method: &'static str, arg: Value) -> BoundNotification {
+        BoundNotification {
+            method,
+            args: vec![arg],
+            names: ParamNames::Flattened,
+        }
+    }
+
+    /// Convert the notification to a json object which can be serialized and sent to a jsonrpc
+    /// server.
+    pub fn as_request(&self) -> Value {
+        self.as_call().as_request()
+    }
+
+    /// The notification as a call, so it can be included in a
+    /// [batch_request](struct.Call.html#method.batch_request).
+    pub fn as_call(&self) -> Call<'_> {
+        Call {
+            method: self.method,
+            args: &self.args,
+            names: self.names,
+            id: None,
+        }
+    }
+}
+
+/// The "Method not found" error, naming the method in its data so that clients can tell which
+/// call went wrong, e.g. a misspelled method name. Generated handlers answer unknown methods with
+/// this error.
+pub fn method_not_found(method: &str) -> Error {
+    Error {
+        data: Some(Value::String(format!("method '{}' not found", method))),
+        ..Error::method_not_found()
+    }
+}
+
+/// The http status to send along with a reply holding error, for serving jsonrpc over http.
+///
+/// Requests that aren't jsonrpc at all, answered with "Parse error" or "Invalid request", get 400
+/// Bad Request. Every other error is an answer to a well formed call, the jsonrpc error in the body
+/// tells what went wrong, so it gets 200 OK like a successful call. Servers that want to signal
+/// "Method not found" as 404, for example, can pass their own mapping to the http adapters.
+pub fn http_status_for(error: &Error) -> u16 {
+    match error.code {
+        ErrorCode::ParseError | ErrorCode::InvalidRequest => 400,
+        _ => 200,
+    }
+}
+
+/// used from generated code, warns the first time a deprecated method is called, through tracing
+#[doc(hidden)]
+pub fn warn_deprecated(warned: &AtomicBool, method: &str, note: &str) {
+    #[cfg(feature = "tracing")]
+    if !warned.swap(true, Ordering::Relaxed) {
+        tracing::warn!(method, note, "deprecated jsonrpc method called");
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = (warned, method, note);
+}
+
+impl From<ResultSerializeError> for Error {
+    fn from(err: ResultSerializeError) -> Error {
+        Error {
+            code: ErrorCode::ServerError(SERIALIZATION_ERROR),
+            message: "Serialization error".to_owned(),
+            data: Some(Value::String(err.detail)),
+        }
+    }
+}
+
+/// A result produced item by item, serialized as a json array without first collecting the items
+/// into a `Vec`. Methods can't return `impl Iterator` as rpc traits must stay usable as
+/// `dyn Trait`, return a Streamed iterator instead.
+///
+/// ```
+/// use easy_jsonrpc::Streamed;
+///
+/// #[easy_jsonrpc::rpc]
+/// pub trait Logs {
+///     fn logs(&self, since: u64) -> Streamed<Box<dyn Iterator<Item = String>>>;
+/// }
+///
+/// struct LogsImpl;
+///
+/// impl Logs for LogsImpl {
+///     fn logs(&self, since: u64) -> Streamed<Box<dyn Iterator<Item = String>>> {
+///         Streamed::new(Box::new((since..since + 3).map(|n| format!("entry {}", n))))
+///     }
+/// }
+/// ```
+///
+/// Only the items are streamed, the reply is still built in memory once serialized. Clients
+/// receive the items of a Streamed result as an iterator over the deserialized array. An iterator
+/// can only be serialized once, serializing it again fails.
+pub struct Streamed<I> {
+    iter: std::cell::Cell<Option<I>>,
+}
+
+impl<I: Iterator> Streamed<I> {
+    /// Serialize the items of iter as a json array.
+    pub fn new(iter: I) -> Streamed<I> {
+        Streamed {
+            iter: std::cell::Cell::new(Some(iter)),
+        }
+    }
+}
+
+impl<I: Iterator> Iterator for Streamed<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        self.iter.get_mut().as_mut()?.next()
+    }
+}
+
+impl<I> Serialize for Streamed<I>
+where
+    I: Iterator,
+    I::Item: Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.iter.take() {
+            Some(iter) => serializer.collect_seq(iter),
+            None => Err(serde::ser::Error::custom("iterator was serialized already")),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Streamed<std::vec::IntoIter<T>> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(|items| Streamed::new(items.into_iter()))
+    }
+}
+
+impl<'de, T: Deserialize<'de> + 'static> Deserialize<'de>
+    for Streamed<Box<dyn Iterator<Item = T>>>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer)
+            .map(|items| Streamed::new(Box::new(items.into_iter()) as Box<dyn Iterator<Item = T>>))
+    }
+}
+
+impl<I> std::fmt::Debug for Streamed<I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Streamed").finish_non_exhaustive()
+    }
+}
+
+/// used from generated code to deserialize arguments of methods marked lenient_numbers, which
+/// may also be strings holding a number
+#[doc(hidden)]
+pub fn from_value_lenient<T>(value: Value) -> Result<T, serde_json::Error>
+where
+    T: DeserializeOwned + std::str::FromStr,
+{
+    match value {
+        // only strings spelling a json number, FromStr of floats also accepts "inf" and "NaN"
+        Value::String(string) if serde_json::from_str::<serde_json::Number>(&string).is_ok() => {
+            string.parse().or_else(|_| {
+                // report the original type mismatch
+                serde_json::from_value(Value::String(string))
+            })
+        }
+        other => serde_json::from_value(other),
+    }
+}
+
+/// used from generated code as the deserialize_with of lenient_numbers fields in params structs
+#[doc(hidden)]
+pub fn deserialize_lenient<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+    T: DeserializeOwned + std::str::FromStr,
+{
+    from_value_lenient(Value::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+/// used from generated code to reject traits whose method names clash with those of their
+/// supertraits
+#[doc(hidden)]
+pub const fn contains_duplicate_names(lists: &[&[&str]]) -> bool {
+    let mut a = 0;
+    while a < lists.len() {
+        let mut i = 0;
+        while i < lists[a].len() {
+            // compare with every name that comes after lists[a][i]
+            let mut b = a;
+            let mut j = i + 1;
+            while b < lists.len() {
+                while j < lists[b].len() {
+                    if str_eq(lists[a][i], lists[b][j]) {
+                        return true;
+                    }
+                    j += 1;
+                }
+                b += 1;
+                j = 0;
+            }
+            i += 1;
+        }
+        a += 1;
+    }
+    false
+}
+
+// str equality usable in const contexts
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Conversion of method errors into jsonrpc error objects.
+///
+/// Methods generated by the [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro which return
+/// `Result<T, E>` report `Err` values as jsonrpc errors. `E` must implement this trait.
+///
+/// ```
+/// use easy_jsonrpc::{Error, ErrorCode, IntoRpcError};
+///
+/// enum WalletError {
+///     InsufficientFunds { needed: u64 },
+/// }
+///
+/// impl IntoRpcError for WalletError {
+///     fn into_error(self) -> Error {
+///         match self {
+///             WalletError::InsufficientFunds { needed } => Error {
+///                 code: ErrorCode::ServerError(-32010),
+///                 message: "Insufficient funds".into(),
+///                 data: Some(needed.into()),
+///             },
+///         }
+///     }
+/// }
+/// ```
+pub trait IntoRpcError {
+    /// Convert self to a jsonrpc error object.
+    fn into_error(self) -> Error;
+
+    /// The codes errors of this type are reported with, each along with a human readable label,
+    /// for advertising them to clients. Defaults to none. The helper enum generated by the
+    /// [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro collects these in `error_codes()`.
+    fn error_codes() -> &'static [(i64, &'static str)]
+    where
+        Self: Sized,
+    {
+        &[]
+    }
+}
+
+impl IntoRpcError for Error {
+    fn into_error(self) -> Error {
+        self
+    }
+}
+
+/// Strings become an error with code [APPLICATION_ERROR](constant.APPLICATION_ERROR.html) and the
+/// string as its message.
+impl IntoRpcError for String {
+    fn into_error(self) -> Error {
+        Error {
+            code: ErrorCode::ServerError(APPLICATION_ERROR),
+            message: self,
+            data: None,
+        }
+    }
+
+    fn error_codes() -> &'static [(i64, &'static str)] {
+        &[(APPLICATION_ERROR, "Application error")]
+    }
+}
+
+/// Same as the implementation for String.
+impl IntoRpcError for &str {
+    fn into_error(self) -> Error {
+        self.to_owned().into_error()
+    }
+
+    fn error_codes() -> &'static [(i64, &'static str)] {
+        String::error_codes()
+    }
+}
+
+/// A simpler alternative to implementing [IntoRpcError](trait.IntoRpcError.html) directly. Errors
+/// implementing this trait become a jsonrpc error with the chosen code and data, and the
+/// `Display` output of the error as the message. For error enums needing no data, it may be
+/// derived with [RpcErrorCode](../easy_jsonrpc_proc_macro/derive.RpcErrorCode.html).
+///
+/// ```
+/// use easy_jsonrpc::{ErrorCode, RpcErrorCode, Value};
+/// use std::fmt;
+///
+/// enum WalletError {
+///     InsufficientFunds { needed: u64 },
+///     Locked,
+/// }
+///
+/// impl fmt::Display for WalletError {
+///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         match self {
+///             WalletError::InsufficientFunds { .. } => f.write_str("Insufficient funds"),
+///             WalletError::Locked => f.write_str("Wallet is locked"),
+///         }
+///     }
+/// }
+///
+/// impl RpcErrorCode for WalletError {
+///     fn code(&self) -> ErrorCode {
+///         match self {
+///             WalletError::InsufficientFunds { .. } => ErrorCode::ServerError(-32010),
+///             WalletError::Locked => ErrorCode::ServerError(-32011),
+///         }
+///     }
+///
+///     fn data(&self) -> Option<Value> {
+///         match self {
+///             WalletError::InsufficientFunds { needed } => Some((*needed).into()),
+///             WalletError::Locked => None,
+///         }
+///     }
+/// }
+/// ```
+pub trait RpcErrorCode: std::fmt::Display {
+    /// The code of the jsonrpc error.
+    fn code(&self) -> ErrorCode;
+
+    /// Additional information to include in the error. Defaults to None.
+    fn data(&self) -> Option<Value> {
+        None
+    }
+
+    /// The code of each kind of error along with a label, see
+    /// [IntoRpcError::error_codes](trait.IntoRpcError.html#method.error_codes). Defaults to none.
+    fn error_codes() -> &'static [(i64, &'static str)]
+    where
+        Self: Sized,
+    {
+        &[]
+    }
+}
+
+impl<E: RpcErrorCode> IntoRpcError for E {
+    fn into_error(self) -> Error {
+        Error {
+            code: self.code(),
+            message: self.to_string(),
+            data: self.data(),
+        }
+    }
+
+    fn error_codes() -> &'static [(i64, &'static str)] {
+        <E as RpcErrorCode>::error_codes()
+    }
+}
+
+/// Build a typed jsonrpc method call using a client helper generated by the
+/// [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro. Calls to methods the trait does not
+/// have, or with arguments of the wrong type, fail to compile.
+///
+/// Evaluates to `Result<(MethodCall, Tracker<T>), ArgSerializeError>`, see
+/// [BoundMethod::method_call](struct.BoundMethod.html#method.method_call).
+///
+/// ```
+/// # #[easy_jsonrpc::rpc]
+/// # pub trait Adder {
+/// #     fn wrapping_add(&self, a: isize, b: isize) -> isize;
+/// # }
+/// let (method_call, tracker) = easy_jsonrpc::rpc_call!(adder::wrapping_add, 1, 2).unwrap();
+/// assert_eq!(method_call.method, "wrapping_add");
+/// ```
+///
+/// ```compile_fail
+/// # #[easy_jsonrpc::rpc]
+/// # pub trait Adder {
+/// #     fn wrapping_add(&self, a: isize, b: isize) -> isize;
+/// # }
+/// let _ = easy_jsonrpc::rpc_call!(adder::wrapping_sub, 1, 2);
+/// ```
+#[macro_export]
+macro_rules! rpc_call {
+    ($($helper:ident)::+ $(, $arg:expr)* $(,)?) => {
+        $($helper)::+($($arg),*).map(|bound| bound.method_call())
+    };
+}
+
+/// Error returned when a tracker fails to retrive its response.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ResponseFail {
+    /// Server responded, but Server did not specify a result for the call in question.
+    ResultNotFound,
+    /// Server specified a result for the call in question, but it the result was malformed.
+    InvalidResponse,
+    /// Server specified a result for the call in question and the result was an rpc error.
+    RpcError(Error),
+}
+
+/// Thrown when arguments fail to be serialized. Possible causes include, but are not limited to:
+/// - A poisoned mutex
+/// - A cstring containing invalid utf-8
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct ArgSerializeError;
+
+/// Returned by [from_json_response](struct.Response.html#method.from_json_response) on error.
+#[derive(Clone, PartialEq, Debug)]
+pub enum InvalidResponse {
+    /// Response is not a valid jsonrpc response.
+    DeserailizeFailure,
+    /// Response contains an id that is neither a number nor a string. The client helpers in
+    /// easy_jsonrpc never send null ids, so if the server responds with one, for example to a
+    /// request it could not parse, something is wrong.
+    ContainsNonNumericId,
+}
+
+/// Special purpose structure for holding a group of responses. Allows for response lookup by id.
+/// Does not support null ids.
+pub struct Response {
+    /// Mapping from id to output of rpc call.
+    pub outputs: BTreeMap<u64, Result<Value, Error>>,
+    /// Same as outputs, for calls with string ids.
+    pub string_outputs: BTreeMap<String, Result<Value, Error>>,
+}
+
+impl Response {
+    /// Deserialize response from a jsonrpc server.
+    pub fn from_json_response(raw_jsonrpc_response: Value) -> Result<Self, InvalidResponse> {
+        let response: jsonrpc_core::Response = serde_json::from_value(raw_jsonrpc_response)
+            .map_err(|_| InvalidResponse::DeserailizeFailure)?;
+        Self::from_parsed(response)
+    }
+
+    /// Same as [from_json_response](#method.from_json_response), for a response returned by
+    /// [Handler::handle_parsed](trait.Handler.html#method.handle_parsed).
+    pub fn from_parsed(response: types::Response) -> Result<Self, InvalidResponse> {
+        let mut calls: Vec<Output> = match response {
+            jsonrpc_core::Response::Single(out) => vec![out],
+            jsonrpc_core::Response::Batch(outs) => outs,
+        };
+        debug_assert!({
+            fn contains_duplicates(list: &[&Id]) -> bool {
+                (1..list.len()).any(|i| list[i..].contains(&list[i - 1]))
+            }
+            let ids = calls
+                .iter()
+                .filter_map(|out| match out {
+                    Output::Success(Success { id, .. }) | Output::Failure(Failure { id, .. })
+                        if *id != Id::Null =>
+                    {
+                        Some(id)
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            !contains_duplicates(ids.as_slice())
+        });
+        let mut outputs = BTreeMap::new();
+        let mut string_outputs = BTreeMap::new();
+        for out in calls.drain(..) {
+            let (id, output) = match out {
+                Output::Success(Success { result, id, .. }) => (id, Ok(result)),
+                Output::Failure(Failure { error, id, .. }) => (id, Err(error)),
+            };
+            match id {
+                Id::Num(id) => outputs.insert(id, output),
+                Id::Str(id) => string_outputs.insert(id, output),
+                Id::Null => return Err(InvalidResponse::ContainsNonNumericId),
+            };
+        }
+        Ok(Self {
+            outputs,
+            string_outputs,
+        })
+    }
+
+    /// Retrieve the output with a matching id and return it, return None if no such output exists.
+    pub fn remove(&mut self, id: u64) -> Option<Result<Value, Error>> {
+        self.outputs.remove(&id)
+    }
+
+    /// Same as [remove](#method.remove), for an id of any kind.
+    pub fn remove_id(&mut self, id: &Id) -> Option<Result<Value, Error>> {
+        match id {
+            Id::Num(id) => self.outputs.remove(id),
+            Id::Str(id) => self.string_outputs.remove(id),
+            Id::Null => None,
+        }
+    }
+}
+
+/// Accumulates typed calls into a batch request, assigning each call its own id.
+///
+/// ```
+/// # #[easy_jsonrpc::rpc]
+/// # pub trait Adder {
+/// #     fn wrapping_add(&self, a: isize, b: isize) -> isize;
+/// #     fn is_some(&self, a: Option<usize>) -> bool;
+/// # }
+/// # struct AdderImpl;
+/// # impl Adder for AdderImpl {
+/// #     fn wrapping_add(&self, a: isize, b: isize) -> isize { a.wrapping_add(b) }
+/// #     fn is_some(&self, a: Option<usize>) -> bool { a.is_some() }
+/// # }
+/// use easy_jsonrpc::{BatchBuilder, BatchResult, Handler};
+///
+/// let handler = &AdderImpl as &dyn Adder;
+/// let mut batch = BatchBuilder::new();
+/// let sum = batch.call(&adder::wrapping_add(1, 2).unwrap());
+/// let some = batch.call(&adder::is_some(None).unwrap());
+/// let response = handler.handle_parsed(batch.build()).unwrap();
+///
+/// let mut result = BatchResult::new(response).unwrap();
+/// assert_eq!(result.get(&sum), Ok(3));
+/// assert_eq!(result.get(&some), Ok(false));
+/// ```
+pub struct BatchBuilder {
+    calls: Vec<types::Call>,
+    ids: Box<dyn IdGenerator>,
+}
+
+impl Default for BatchBuilder {
+    fn default() -> BatchBuilder {
+        BatchBuilder::with_id_generator(MonotonicIds::default())
+    }
+}
+
+impl std::fmt::Debug for BatchBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("BatchBuilder")
+            .field("calls", &self.calls)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BatchBuilder {
+    /// Create an empty batch. Calls are numbered from 0, in the order they are added.
+    pub fn new() -> BatchBuilder {
+        BatchBuilder::default()
+    }
+
+    /// Create an empty batch whose calls get their ids from ids. The ids must be distinct for
+    /// the results of the calls to be told apart.
+    pub fn with_id_generator(ids: impl IdGenerator + 'static) -> BatchBuilder {
+        BatchBuilder {
+            calls: Vec::new(),
+            ids: Box::new(ids),
+        }
+    }
+
+    /// Add a method call to the batch. The returned tracker retrieves its result from the
+    /// [BatchResult](struct.BatchResult.html) of the batch.
+    pub fn call<T: Deserialize<'static>>(&mut self, method: &BoundMethod<'_, T>) -> Tracker<T> {
+        let (method_call, tracker) = method.method_call_with(&*self.ids);
+        self.calls.push(types::Call::MethodCall(method_call));
+        tracker
+    }
+
+    /// Add a notification to the batch. Notifications get no reply.
+    pub fn notify(&mut self, notification: &BoundNotification) {
+        let call = notification.as_call();
+        self.calls
+            .push(types::Call::Notification(types::Notification {
+                jsonrpc: Some(Version::V2),
+                method: call.method.to_owned(),
+                params: call.params(),
+            }));
+    }
+
+    /// The batch request, as accepted by
+    /// [Handler::handle_parsed](trait.Handler.html#method.handle_parsed). Serialize it to send it
+    /// to a remote server.
+    pub fn build(self) -> types::Request {
+        types::Request::Batch(self.calls)
+    }
+}
+
+/// Produces the ids of method calls, see
+/// [BoundMethod::call_with](struct.BoundMethod.html#method.call_with) and
+/// [BatchBuilder::with_id_generator](struct.BatchBuilder.html#method.with_id_generator).
+///
+/// Closures returning an `Id` are id generators, e.g. for servers expecting uuids:
+///
+/// ```
+/// # #[easy_jsonrpc::rpc]
+/// # pub trait Adder {
+/// #     fn wrapping_add(&self, a: isize, b: isize) -> isize;
+/// # }
+/// use easy_jsonrpc::{BatchBuilder, Id};
+/// use std::cell::Cell;
+///
+/// let count = Cell::new(0);
+/// let mut batch = BatchBuilder::with_id_generator(move || {
+///     count.set(count.get() + 1);
+///     Id::Str(format!("req-{}", count.get()))
+/// });
+/// batch.call(&adder::wrapping_add(1, 2).unwrap());
+/// ```
+pub trait IdGenerator {
+    /// The id of the next call.
+    fn next(&self) -> Id;
+}
+
+impl<F: Fn() -> Id> IdGenerator for F {
+    fn next(&self) -> Id {
+        self()
+    }
+}
+
+/// Numeric ids counting up from 0, the ids used by
+/// [BatchBuilder::new](struct.BatchBuilder.html#method.new).
+#[derive(Debug, Default)]
+pub struct MonotonicIds {
+    next: AtomicU64,
+}
+
+impl IdGenerator for MonotonicIds {
+    fn next(&self) -> Id {
+        Id::Num(self.next.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// The reply to a batch built with a [BatchBuilder](struct.BatchBuilder.html), from which the
+/// typed result of each call is retrieved using the tracker returned when adding the call.
+pub struct BatchResult {
+    response: Response,
+}
+
+impl BatchResult {
+    /// Fails if the response contains a non numeric id.
+    pub fn new(response: types::Response) -> Result<BatchResult, InvalidResponse> {
+        Ok(BatchResult {
+            response: Response::from_parsed(response)?,
+        })
+    }
+
+    /// Same as [BatchResult::new](#method.new), for a response received from a remote server.
+    pub fn from_json_response(raw_jsonrpc_response: Value) -> Result<BatchResult, InvalidResponse> {
+        Ok(BatchResult {
+            response: Response::from_json_response(raw_jsonrpc_response)?,
+        })
+    }
+
+    /// Take the result of the tracked call out of the batch result.
+    pub fn get<T: Deserialize<'static>>(
+        &mut self,
+        tracker: &Tracker<T>,
+    ) -> Result<T, ResponseFail> {
+        tracker.get_return(&mut self.response)
+    }
+}
+
+/// Links a jsonrpc id to a return type.
+/// Trackers can be used to get a typed return value from a json response.
+pub struct Tracker<T>
+where
+    T: Deserialize<'static>,
+{
+    id: Id,
+    _spook: PhantomData<*const T>,
+}
+
+impl<T> Tracker<T>
+where
+    T: Deserialize<'static>,
+{
+    /// Get typed return value from server response.
+    /// If response contains the return value for this request, remove it from the
+    /// server response and attempt to interpret it as a value with type T.
+    pub fn get_return(&self, response: &mut Response) -> Result<T, ResponseFail> {
+        let result = response
+            .remove_id(&self.id)
+            .ok_or(ResponseFail::ResultNotFound)?;
+        let raw_return = result.map_err(ResponseFail::RpcError)?;
+        <T>::deserialize(raw_return).map_err(|_| ResponseFail::InvalidResponse)
+    }
+}
+
+/// Name and arguments of a jsonrpc method, as returned by the `rpc_descriptors()` function the
+/// [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro generates for client helpers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MethodDescriptor {
+    /// Name of the method as seen by jsonrpc clients.
+    pub name: &'static str,
+    /// Name and rust type of each argument, in order. Types are spelled as in the trait
+    /// definition, e.g. `"Vec<String>"`.
+    pub params: Vec<(&'static str, &'static str)>,
+    /// The note of a method marked `#[rpc(deprecated = "..")]`, empty if it was given none.
+    /// None unless the method is deprecated.
+    pub deprecated: Option<&'static str>,
+}
+
+/// Serialized as `{"name": "add", "params": [{"name": "a", "type": "u64"}, ..]}`, the format of
+/// the reply to `rpc.describe`. Deprecated methods also have a `"deprecated"` member holding the
+/// note.
+impl Serialize for MethodDescriptor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let params: Vec<Value> = self
+            .params
+            .iter()
+            .map(|(name, ty)| json!({"name": name, "type": ty}))
+            .collect();
+        let mut descriptor = json!({"name": self.name, "params": params});
+        if let Some(note) = self.deprecated {
+            descriptor["deprecated"] = json!(note);
+        }
+        descriptor.serialize(serializer)
+    }
+}
+
+/// Sends requests to a handler in the same process. Requests and responses are serialized and
+/// parsed just as they would be when sent over the network, which makes this useful for testing
+/// clients without setting up a server.
+///
+/// ```
+/// # #[easy_jsonrpc::rpc]
+/// # pub trait Adder {
+/// #     fn wrapping_add(&self, a: isize, b: isize) -> isize;
+/// # }
+/// # struct AdderImpl;
+/// # impl Adder for AdderImpl {
+/// #     fn wrapping_add(&self, a: isize, b: isize) -> isize {
+/// #         a.wrapping_add(b)
+/// #     }
+/// # }
+/// use easy_jsonrpc::InMemoryTransport;
+///
+/// let transport = InMemoryTransport::new(&(&AdderImpl {} as &dyn Adder));
+/// assert_eq!(transport.call(&adder::wrapping_add(1, 2).unwrap()), Ok(3));
+/// ```
+pub struct InMemoryTransport<'a, H: ?Sized> {
+    handler: &'a H,
+}
+
+impl<'a, H: ?Sized + Handler> InMemoryTransport<'a, H> {
+    /// Create a transport sending requests to handler.
+    pub fn new(handler: &'a H) -> InMemoryTransport<'a, H> {
+        InMemoryTransport { handler }
+    }
+
+    /// Send a request as created by [Call::as_request](struct.Call.html#method.as_request) or
+    /// [Call::batch_request](struct.Call.html#method.batch_request). Returns None if the handler
+    /// sends no reply, as is the case for notifications.
+    pub fn send(&self, request: &Value) -> Result<Option<Response>, InvalidResponse> {
+        let reply = match self.handler.handle_bytes(&value_to_bytes(request)) {
+            Some(reply) => reply,
+            None => return Ok(None),
+        };
+        let reply =
+            serde_json::from_slice(&reply).map_err(|_| InvalidResponse::DeserailizeFailure)?;
+        Response::from_json_response(reply).map(Some)
+    }
+
+    /// Call method and retrieve its return value.
+    pub fn call<T>(&self, method: &BoundMethod<'_, T>) -> Result<T, ResponseFail>
+    where
+        T: Deserialize<'static>,
+    {
+        let (call, tracker) = method.call();
+        let mut response = self
+            .send(&call.as_request())
+            .map_err(|_| ResponseFail::InvalidResponse)?
+            .ok_or(ResponseFail::ResultNotFound)?;
+        tracker.get_return(&mut response)
+    }
+}
+
+/// Sends jsonrpc requests to a server, for the typed clients generated by the
+/// [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro. Implemented for closures taking the
+/// request and returning the reply, which makes wrapping an http client a one liner.
+pub trait Transport {
+    /// Why a request could not be sent, or its reply not received.
+    type Error;
+
+    /// Send request, a single call or a batch, and return the reply of the server. None if the
+    /// server sent no reply.
+    fn send_request(&self, request: &Value) -> Result<Option<Value>, Self::Error>;
+}
+
+impl<F, E> Transport for F
+where
+    F: Fn(&Value) -> Result<Option<Value>, E>,
+{
+    type Error = E;
+
+    fn send_request(&self, request: &Value) -> Result<Option<Value>, E> {
+        self(request)
+    }
+}
+
+impl<'a, H: ?Sized + Handler> Transport for InMemoryTransport<'a, H> {
+    type Error = InvalidResponse;
+
+    fn send_request(&self, request: &Value) -> Result<Option<Value>, InvalidResponse> {
+        match self.handler.handle_bytes(&value_to_bytes(request)) {
+            Some(reply) => serde_json::from_slice(&reply)
+                .map(Some)
+                .map_err(|_| InvalidResponse::DeserailizeFailure),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Error returned by the typed clients generated by the
+/// [rpc](../easy_jsonrpc_proc_macro/attr.rpc.html) macro, telling apart the ways a call can fail.
+/// E is the error type of the [Transport](trait.Transport.html).
+#[derive(Clone, PartialEq, Debug)]
+pub enum ClientError<E> {
+    /// The arguments of the call could not be serialized.
+    ArgSerialize,
+    /// The request could not be sent, or its reply not received.
+    Transport(E),
+    /// The server replied with something other than a jsonrpc response.
+    InvalidResponse,
+    /// The server sent no result for the call.
+    ResultNotFound,
+    /// The server answered the call with an error.
+    Rpc(Error),
+    /// The server answered with a result of the form `{"Err": ..}`, as do servers that send the
+    /// errors of methods returning `Result` as results. Holds what was sent under "Err".
+    EmbeddedErr(Value),
+    /// The result could not be deserialized as the return type of the method.
+    Deserialize {
+        /// Why deserialization failed.
+        detail: String,
+    },
+}
+
+impl<E> From<ArgSerializeError> for ClientError<E> {
+    fn from(_: ArgSerializeError) -> ClientError<E> {
+        ClientError::ArgSerialize
+    }
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for ClientError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ClientError::ArgSerialize => write!(f, "arguments could not be serialized"),
+            ClientError::Transport(err) => write!(f, "transport failed: {}", err),
+            ClientError::InvalidResponse => write!(f, "reply is not a jsonrpc response"),
+            ClientError::ResultNotFound => write!(f, "reply holds no result for the call"),
+            ClientError::Rpc(err) => write!(f, "server error {}: {}", err.code.code(), err.message),
+            ClientError::EmbeddedErr(err) => write!(f, "server returned an error result: {}", err),
+            ClientError::Deserialize { detail } => write!(f, "invalid result: {}", detail),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for ClientError<E> {}
+
+// Deserialize the result of a call. Results of the form {"Ok": ..} or {"Err": ..}, sent by
+// servers that embed errors in results, are unwrapped unless they deserialize as T themselves.
+fn deserialize_result<T: Deserialize<'static>, E>(raw: Value) -> Result<T, ClientError<E>> {
+    let invalid = |err: serde_json::Error| ClientError::Deserialize {
+        detail: err.to_string(),
+    };
+    let mut embedded = match raw {
+        Value::Object(map)
+            if map.len() == 1 && (map.contains_key("Ok") || map.contains_key("Err")) =>
+        {
+            map
+        }
+        raw => return T::deserialize(raw).map_err(invalid),
+    };
+    if let Ok(ret) = T::deserialize(Value::Object(embedded.clone())) {
+        return Ok(ret);
+    }
+    match embedded.remove("Ok") {
+        Some(ok) => T::deserialize(ok).map_err(invalid),
+        None => Err(ClientError::EmbeddedErr(
+            embedded.remove("Err").unwrap_or_default(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    mod easy_jsonrpc {
+        pub use crate::*;
+    }
+    use super::{Handler, HandlerMut, Id, InvalidArgs, MaybeReply, Params};
+    use serde_json::{json, Value};
+
+    #[easy_jsonrpc::rpc]
+    pub trait Adder {
+        fn checked_add(&self, a: isize, b: isize) -> Option<isize>;
+        fn wrapping_add(&self, a: isize, b: isize) -> isize;
+        fn greet(&self) -> String;
+        fn swallow(&self);
+        fn repeat_list(&self, lst: Vec<usize>) -> Vec<usize>;
+        fn fail(&self) -> Result<isize, String>;
+        fn succeed(&self) -> Result<isize, String>;
+        fn echo_ref(&self, a: &isize) -> isize;
+    }
+
+    struct AdderImpl;
+    impl Adder for AdderImpl {
+        fn checked_add(&self, a: isize, b: isize) -> Option<isize> {
+            a.checked_add(b)
+        }
+
+        fn wrapping_add(&self, a: isize, b: isize) -> isize {
+            a.wrapping_add(b)
+        }
+
+        fn greet(&self) -> String {
+            "hello".into()
+        }
+
+        fn swallow(&self) {}
+
+        fn repeat_list(&self, lst: Vec<usize>) -> Vec<usize> {
+            let mut ret = lst.clone();
+            ret.extend(lst);
+            ret
+        }
+
+        fn fail(&self) -> Result<isize, String> {
+            Err("tada!".into())
+        }
+
+        fn succeed(&self) -> Result<isize, String> {
+            Ok(1)
+        }
+
+        fn echo_ref(&self, a: &isize) -> isize {
+            *a
+        }
+    }
+
+    fn assert_adder_response(request: Value, response: Value) {
+        assert_eq!(
+            (&AdderImpl {} as &dyn Adder)
+                .handle_request(request)
+                .as_option()
+                .unwrap(),
+            response
+        );
+    }
+
+    fn error_code(request: Value) -> jsonrpc_core::ErrorCode {
+        let raw_response = (&AdderImpl {} as &dyn Adder)
+            .handle_request(request)
+            .as_option()
+            .unwrap();
+        let response: jsonrpc_core::Response = serde_json::from_value(raw_response).unwrap();
+        match response {
+            jsonrpc_core::Response::Single(jsonrpc_core::Output::Failure(
+                jsonrpc_core::Failure { error, .. },
+            )) => error.code,
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn batch() {
+        assert_adder_response(
+            json!([
+                {
+                    "jsonrpc": "2.0",
+                    "method": "wrapping_add",
+                    "params": [1, 1],
+                    "id": 1
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "method": "wrapping_add",
+                    "params": [1, 2],
+                    "id": 2
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "method": "wrapping_add",
+                    "params": [1, 3],
+                    "id": null
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "method": "wrapping_add",
+                    "params": [1, 4],
+                },
+            ]),
+            json!([
+                {
+                    "jsonrpc": "2.0",
+                    "result": 2,
+                    "id": 1
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "result": 3,
+                    "id": 2
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "result": 4,
+                    "id": null
+                }
+            ]),
+        );
+    }
+
+    #[test]
+    fn positional_args() {
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "wrapping_add",
+                "params": [1, 1],
+                "id": 1
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "result": 2,
+                "id": 1
+            }),
+        );
+    }
+
+    #[test]
+    fn string_id() {
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "wrapping_add",
+                "params": [1, 1],
+                "id": "jfjfks sasdfk"
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "result": 2,
+                "id": "jfjfks sasdfk"
+            }),
+        );
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "wrapping_add",
+                "params": [1, 1],
+                "id": ""
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "result": 2,
+                "id": ""
+            }),
+        );
+    }
+
+    #[test]
+    fn named_args() {
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "wrapping_add",
+                "params": {
+                    "a": 1,
+                    "b": 1
+                },
+                "id": 1
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "result": 2,
+                "id": 1
+            }),
+        );
+    }
+
+    #[test]
+    fn null_args() {
+        let response = json!({
+            "jsonrpc": "2.0",
+            "result": "hello",
+            "id": 1
+        });
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "greet",
+                "params": {},
+                "id": 1
+            }),
+            response.clone(),
+        );
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "greet",
+                "params": [],
+                "id": 1
+            }),
+            response.clone(),
+        );
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "greet",
+                "params": null,
+                "id": 1
+            }),
+            response.clone(),
+        );
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "greet",
+                "id": 1
+            }),
+            response.clone(),
+        );
+    }
+
+    #[test]
+    fn null_return() {
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "swallow",
+                "params": [],
+                "id": 1
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "result": null,
+                "id": 1
+            }),
+        );
+    }
+
+    #[test]
+    fn incorrect_method_name() {
+        assert_eq!(
+            error_code(json!({
+                "jsonrpc": "2.0",
+                "method": "nonexist",
+                "params": [],
+                "id": 1
+            })),
+            jsonrpc_core::ErrorCode::MethodNotFound,
+        );
+    }
+
+    #[test]
+    fn incorrect_args() {
+        assert_eq!(
+            error_code(json!({
+                "jsonrpc": "2.0",
+                "method": "wrapping_add",
+                "params": [],
+                "id": 1
+            })),
+            jsonrpc_core::ErrorCode::InvalidParams,
+        );
+        assert_eq!(
+            error_code(json!({
+                "jsonrpc": "2.0",
+                "method": "wrapping_add",
+                "params": {
+                    "notanarg": 1,
+                    "notarg": 1
+                },
+                "id": 1
+            })),
+            jsonrpc_core::ErrorCode::InvalidParams,
+        );
+        assert_eq!(
+            error_code(json!({
+                "jsonrpc": "2.0",
+                "method": "wrapping_add",
+                "params": [[], []],
+                "id": 1
+            })),
+            jsonrpc_core::ErrorCode::InvalidParams,
+        );
+    }
+
+    #[test]
+    fn complex_type() {
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "repeat_list",
+                "params": [[1, 2, 3]],
+                "id": 1
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "result": [1, 2, 3, 1, 2, 3],
+                "id": 1
+            }),
+        );
+        assert_eq!(
+            error_code(json!({
+                "jsonrpc": "2.0",
+                "method": "repeat_list",
+                "params": [[1], [12]],
+                "id": 1
+            }),),
+            jsonrpc_core::ErrorCode::InvalidParams,
+        );
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "fail",
+                "params": [],
+                "id": 1
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "error": {
+                    "code": super::APPLICATION_ERROR,
+                    "message": "tada!"
+                },
+                "id": 1
+            }),
+        );
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "succeed",
+                "params": [],
+                "id": 1
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "result": 1,
+                "id": 1
+            }),
+        );
+    }
+
+    #[test]
+    fn embedded_errors() {
+        #[easy_jsonrpc::rpc(embed_errors)]
+        trait Embedded {
+            fn fail(&self) -> Result<isize, String>;
+        }
+
+        struct EmbeddedImpl;
+        impl Embedded for EmbeddedImpl {
+            fn fail(&self) -> Result<isize, String> {
+                Err("tada!".to_owned())
+            }
+        }
+
+        assert_eq!(
+            (&EmbeddedImpl as &dyn Embedded)
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": "fail",
+                    "params": [],
+                    "id": 1
+                }))
+                .as_option(),
+            Some(json!({
+                "jsonrpc": "2.0",
+                "result": {
+                    "Err": "tada!"
+                },
+                "id": 1
+            }))
+        );
+        let _: easy_jsonrpc::BoundMethod<Result<isize, String>> = embedded::fail().unwrap();
+    }
+
+    #[test]
+    fn custom_error() {
+        #[derive(serde::Serialize)]
+        struct NotFound {
+            key: String,
+        }
+
+        impl easy_jsonrpc::IntoRpcError for NotFound {
+            fn into_error(self) -> easy_jsonrpc::Error {
+                easy_jsonrpc::Error {
+                    code: easy_jsonrpc::ErrorCode::ServerError(-32010),
+                    message: "Not found".into(),
+                    data: Some(serde_json::to_value(self).unwrap()),
+                }
+            }
+        }
+
+        #[easy_jsonrpc::rpc]
+        trait Lookup {
+            fn lookup(&self, key: String) -> Result<usize, NotFound>;
+        }
+
+        struct LookupImpl;
+        impl Lookup for LookupImpl {
+            fn lookup(&self, key: String) -> Result<usize, NotFound> {
+                match key.as_str() {
+                    "one" => Ok(1),
+                    _ => Err(NotFound { key }),
+                }
+            }
+        }
+
+        let handler = &LookupImpl as &dyn Lookup;
+        let bound = lookup::lookup("one".into()).unwrap();
+        let (call, tracker) = bound.call();
+        let mut response = easy_jsonrpc::Response::from_json_response(
+            handler
+                .handle_request(call.as_request())
+                .as_option()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(tracker.get_return(&mut response), Ok(1));
+
+        let bound = lookup::lookup("two".into()).unwrap();
+        let (call, tracker) = bound.call();
+        let mut response = easy_jsonrpc::Response::from_json_response(
+            handler
+                .handle_request(call.as_request())
+                .as_option()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            tracker.get_return(&mut response),
+            Err(easy_jsonrpc::ResponseFail::RpcError(easy_jsonrpc::Error {
+                code: easy_jsonrpc::ErrorCode::ServerError(-32010),
+                message: "Not found".into(),
+                data: Some(json!({ "key": "two" })),
+            }))
+        );
+    }
+
+    #[test]
+    fn before_dispatch() {
+        use super::{Error, ErrorCode};
+        use std::cell::Cell;
+
+        #[easy_jsonrpc::rpc]
+        trait Service {
+            #[rpc(before_dispatch)]
+            fn check(&self, method: &str) -> Result<(), Error>;
+            fn status(&self) -> String;
+            fn ping(&self);
+        }
+
+        struct ServiceImpl {
+            maintenance: bool,
+            pings: Cell<usize>,
+        }
+        impl Service for ServiceImpl {
+            fn check(&self, method: &str) -> Result<(), Error> {
+                match self.maintenance && method != "status" {
+                    true => Err(Error {
+                        code: ErrorCode::ServerError(-32050),
+                        message: "Down for maintenance".into(),
+                        data: None,
+                    }),
+                    false => Ok(()),
+                }
+            }
+
+            fn status(&self) -> String {
+                if self.maintenance {
+                    "maintenance"
+                } else {
+                    "ok"
+                }
+                .into()
+            }
+
+            fn ping(&self) {
+                self.pings.set(self.pings.get() + 1);
+            }
+        }
+
+        let request = json!([
+            {"jsonrpc": "2.0", "method": "status", "id": 1},
+            {"jsonrpc": "2.0", "method": "ping", "id": 2},
+            {"jsonrpc": "2.0", "method": "ping"},
+        ]);
+        let service = ServiceImpl {
+            maintenance: false,
+            pings: Cell::new(0),
+        };
+        let handler = &service as &dyn Service;
+        assert_eq!(handler.methods(), vec!["status", "ping"]);
+        assert_eq!(
+            handler.handle_request(request.clone()),
+            MaybeReply::Reply(json!([
+                {"jsonrpc": "2.0", "result": "ok", "id": 1},
+                {"jsonrpc": "2.0", "result": null, "id": 2},
+            ]))
+        );
+        assert_eq!(service.pings.get(), 2);
+
+        let service = ServiceImpl {
+            maintenance: true,
+            pings: Cell::new(0),
+        };
+        let handler = &service as &dyn Service;
+        let expected = json!([
+            {"jsonrpc": "2.0", "result": "maintenance", "id": 1},
+            {
+                "jsonrpc": "2.0",
+                "error": {"code": -32050, "message": "Down for maintenance"},
+                "id": 2
+            },
+        ]);
+        assert_eq!(
+            handler.handle_request(request.clone()),
+            MaybeReply::Reply(expected.clone())
+        );
+        assert_eq!(
+            handler
+                .handle_bytes(request.to_string().as_bytes())
+                .map(|bytes| serde_json::from_slice::<Value>(&bytes).unwrap()),
+            Some(expected.clone())
+        );
+        // routed calls pass through the hook of the handler they are routed to
+        assert_eq!(service.pings.get(), 0);
+        let router = super::Router::new().with(Box::new(ServiceImpl {
+            maintenance: true,
+            pings: Cell::new(0),
+        }) as Box<dyn Service>);
+        assert_eq!(router.handle_request(request), MaybeReply::Reply(expected));
+    }
+
+    #[test]
+    fn skipped_methods() {
+        #[easy_jsonrpc::rpc]
+        trait Greeter {
+            fn greet(&self, name: String) -> String {
+                format!("{}, {}", self.greeting(), name)
+            }
+            #[rpc(skip)]
+            fn greeting(&self) -> &str {
+                "Hello"
+            }
+            // would make the handler a HandlerMut if it were not skipped
+            #[rpc(skip)]
+            fn reset(&mut self, _to: &mut String);
+        }
+
+        struct GreeterImpl;
+        impl Greeter for GreeterImpl {
+            fn reset(&mut self, _to: &mut String) {}
+        }
+
+        let handler = &GreeterImpl as &dyn Greeter;
+        assert_eq!(handler.methods(), vec!["greet"]);
+        let response = |method: &str| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": ["World"],
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(
+            response("greet"),
+            json!({"jsonrpc": "2.0", "result": "Hello, World", "id": 1})
+        );
+        assert_eq!(response("greeting")["error"]["code"], json!(-32601));
+        assert!(!greeter::handles_method("greeting"));
+        GreeterImpl.reset(&mut String::new());
+    }
+
+    #[test]
+    fn instantiated_methods() {
+        use serde::{de::DeserializeOwned, Serialize};
+
+        #[easy_jsonrpc::rpc]
+        trait Echo {
+            #[rpc(instantiate(echo_string = "echo::<String>", echo_pair = "echo::<(u8, bool)>"))]
+            fn echo<T: Serialize + DeserializeOwned>(&self, value: T) -> T;
+            fn echo_string(&self, value: String) -> String;
+            fn echo_pair(&self, value: (u8, bool)) -> (u8, bool);
+        }
+
+        struct EchoImpl;
+        impl Echo for EchoImpl {
+            fn echo<T: Serialize + DeserializeOwned>(&self, value: T) -> T {
+                value
+            }
+            fn echo_string(&self, value: String) -> String {
+                self.echo(value)
+            }
+            fn echo_pair(&self, value: (u8, bool)) -> (u8, bool) {
+                self.echo(value)
+            }
+        }
+
+        let handler = &EchoImpl as &dyn Echo;
+        assert_eq!(handler.methods(), vec!["echo_string", "echo_pair"]);
+        let call = |method: &str, params: Value| {
+            let request = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1});
+            handler.handle_request(request).as_option().unwrap().clone()
+        };
+        assert_eq!(call("echo_string", json!(["a"]))["result"], json!("a"));
+        assert_eq!(
+            call("echo_pair", json!([[1, true]]))["result"],
+            json!([1, true])
+        );
+        assert_eq!(call("echo", json!(["a"]))["error"]["code"], json!(-32601));
+        let bound = echo::echo_string("b".into()).unwrap();
+        let (request, tracker) = bound.call();
+        let reply = handler.handle_request(request.as_request());
+        let mut response =
+            super::Response::from_json_response(reply.as_option().unwrap().clone()).unwrap();
+        assert_eq!(tracker.get_return(&mut response), Ok("b".to_string()));
+    }
+
+    #[test]
+    fn trait_lifetimes() {
+        #[easy_jsonrpc::rpc]
+        trait Store<'a> {
+            fn len(&self, key: String) -> usize;
+            #[rpc(skip)]
+            fn first(&self, items: &'a [String]) -> Option<&'a String> {
+                items.first()
+            }
+        }
+
+        #[easy_jsonrpc::rpc]
+        trait Tally<'a, 'b: 'a> {
+            fn bump(&mut self) -> u64;
+            #[rpc(skip)]
+            fn pick(&self, a: &'a str, _b: &'b str) -> &'a str {
+                a
+            }
+        }
+
+        struct StoreImpl(u64);
+        impl<'a> Store<'a> for StoreImpl {
+            fn len(&self, key: String) -> usize {
+                key.len()
+            }
+        }
+        impl<'a, 'b: 'a> Tally<'a, 'b> for StoreImpl {
+            fn bump(&mut self) -> u64 {
+                self.0 += 1;
+                self.0
+            }
+        }
+
+        let handler = &StoreImpl(0) as &dyn Store;
+        assert_eq!(
+            handler
+                .handle_request(
+                    json!({"jsonrpc": "2.0", "method": "len", "params": ["ab"], "id": 1})
+                )
+                .as_option()
+                .unwrap()["result"],
+            json!(2)
+        );
+        assert_eq!(handler.first(&["x".to_string()]), Some(&"x".to_string()));
+        let mut tally = StoreImpl(0);
+        let handler = &mut tally as &mut dyn Tally;
+        assert_eq!(
+            handler
+                .handle_request_mut(json!({"jsonrpc": "2.0", "method": "bump", "id": 1}))
+                .as_option()
+                .unwrap()["result"],
+            json!(1)
+        );
+        assert_eq!(handler.pick("a", "b"), "a");
+        assert_eq!(
+            store::len("ab".into()).unwrap().call().0.as_request()["method"],
+            json!("len")
+        );
+    }
+
+    #[test]
+    fn lenient_numbers() {
+        #[easy_jsonrpc::rpc(tuple_args)]
+        trait Ledger {
+            #[rpc(lenient_numbers)]
+            fn credit(&self, amount: u64, scale: f64, memo: String) -> (u64, f64, String);
+            fn debit(&self, amount: u64) -> u64;
+        }
+
+        struct LedgerImpl;
+        impl Ledger for LedgerImpl {
+            fn credit(&self, amount: u64, scale: f64, memo: String) -> (u64, f64, String) {
+                (amount, scale, memo)
+            }
+
+            fn debit(&self, amount: u64) -> u64 {
+                amount
+            }
+        }
+
+        let handler = &LedgerImpl as &dyn Ledger;
+        let call = |method: &str, params: Value| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": params,
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(
+            call("credit", json!(["9007199254740993", "0.5", "12"]))["result"],
+            json!([9007199254740993u64, 0.5, "12"])
+        );
+        assert_eq!(
+            call("credit", json!({"amount": 3, "scale": 2, "memo": ""}))["result"],
+            json!([3, 2.0, ""])
+        );
+        // String arguments don't accept numbers in turn, nor numeric ones other strings
+        assert_eq!(
+            call("credit", json!([1, 1, 12]))["error"]["code"],
+            json!(-32602)
+        );
+        for bad in ["NaN", "inf", "1.5", "-1", " 1", "one"].iter() {
+            assert_eq!(
+                call("credit", json!([bad, 1, ""]))["error"]["code"],
+                json!(-32602),
+                "{}",
+                bad
+            );
+        }
+        assert_eq!(
+            call("credit", json!([1, "NaN", ""]))["error"]["code"],
+            json!(-32602)
+        );
+        // only methods marked lenient_numbers are lenient
+        assert_eq!(call("debit", json!(["5"]))["error"]["code"], json!(-32602));
+        assert_eq!(call("debit", json!([5]))["result"], json!(5));
+    }
+
+    #[test]
+    fn base64_bytes() {
+        use crate::{Base64, Base64Bytes};
+
+        #[easy_jsonrpc::rpc(client)]
+        trait Blobs {
+            #[rpc(base64_result)]
+            fn reverse(&self, #[rpc(base64)] blob: Vec<u8>) -> Vec<u8>;
+            #[rpc(base64_result = "url_safe")]
+            fn digest(&self, #[rpc(base64 = "url_safe")] blob: &[u8]) -> Result<Vec<u8>, String>;
+            #[rpc(params_struct)]
+            fn len(&self, #[rpc(base64)] blob: Vec<u8>) -> usize;
+        }
+
+        struct BlobsImpl;
+        impl Blobs for BlobsImpl {
+            fn reverse(&self, mut blob: Vec<u8>) -> Vec<u8> {
+                blob.reverse();
+                blob
+            }
+
+            fn digest(&self, blob: &[u8]) -> Result<Vec<u8>, String> {
+                if blob.is_empty() {
+                    return Err("empty".into());
+                }
+                Ok(vec![0xfb, 0xff, blob.len() as u8])
+            }
+
+            fn len(&self, blob: Vec<u8>) -> usize {
+                blob.len()
+            }
+        }
+
+        assert_eq!(Base64::Standard.encode(b""), "");
+        assert_eq!(Base64::Standard.encode(b"f"), "Zg==");
+        assert_eq!(Base64::Standard.encode(b"fo"), "Zm8=");
+        assert_eq!(Base64::Standard.encode(b"foo"), "Zm9v");
+        assert_eq!(Base64::Standard.encode(&[0xfb, 0xff]), "+/8=");
+        assert_eq!(Base64::UrlSafe.encode(&[0xfb, 0xff]), "-_8");
+
+        let handler = &BlobsImpl as &dyn Blobs;
+        let call = |method: &str, params: Value| {
+            handler
+                .handle_request(
+                    json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1}),
+                )
+                .as_option()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(call("reverse", json!(["Zm9v"]))["result"], json!("b29m"));
+        assert_eq!(
+            call("reverse", json!({"blob": "Zm8"}))["result"],
+            json!("b2Y=")
+        );
+        // either alphabet is accepted, padded or not
+        assert_eq!(call("digest", json!(["+/8="]))["result"], json!("-_8C"));
+        assert_eq!(call("digest", json!(["-_8"]))["result"], json!("-_8C"));
+        assert_eq!(call("len", json!({"blob": "Zm9vYg=="}))["result"], json!(4));
+        assert_eq!(
+            call("reverse", json!(["Zm9v!A"]))["error"],
+            json!({
+                "code": -32602,
+                "message": "InvalidArgStructure blob at position 0: \
+                            invalid base64 character at position 4"
+            })
+        );
+        assert_eq!(
+            call("reverse", json!([[1, 2]]))["error"]["code"],
+            json!(-32602)
+        );
+
+        // client helpers encode arguments and decode results
+        let request = blobs::reverse(b"foo".to_vec()).unwrap();
+        let (call, tracker) = request.call();
+        let request = call.as_request();
+        assert_eq!(request["params"], json!(["Zm9v"]));
+        let mut response = super::Response::from_json_response(
+            handler.handle_request(request).as_option().unwrap().clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            tracker.get_return(&mut response),
+            Ok(Base64Bytes(b"oof".to_vec()))
+        );
+        assert_eq!(
+            LenParams {
+                blob: vec![0xfb, 0xff]
+            }
+            .request()
+            .unwrap()
+            .call()
+            .0
+            .as_request()["params"],
+            json!(["+/8="])
+        );
+        let client = BlobsClient(super::InMemoryTransport::new(&handler));
+        assert_eq!(client.digest(&[0xfb, 0xff]), Ok(vec![0xfb, 0xff, 2]));
+        assert_eq!(client.reverse(vec![1, 2, 3]), Ok(vec![3, 2, 1]));
+    }
+
+    #[test]
+    fn response_meta() {
+        use crate::RpcResponse;
+
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct Page {
+            items: Vec<u64>,
+        }
+
+        #[easy_jsonrpc::rpc(client, openrpc)]
+        trait Listing {
+            fn page(&self, cursor: Option<String>) -> RpcResponse<Page>;
+            fn count(&self, with_meta: bool) -> RpcResponse<u64>;
+            fn keyed(&self) -> RpcResponse<Page>;
+        }
+
+        struct ListingImpl;
+        impl Listing for ListingImpl {
+            fn page(&self, cursor: Option<String>) -> RpcResponse<Page> {
+                let page = Page { items: vec![1, 2] };
+                match cursor {
+                    Some(_) => RpcResponse::new(page),
+                    None => RpcResponse::new(page).with_meta(json!({"cursor": "abc"})),
+                }
+            }
+
+            fn count(&self, with_meta: bool) -> RpcResponse<u64> {
+                let response = RpcResponse::new(2);
+                if with_meta {
+                    response.with_meta(json!(null))
+                } else {
+                    response
+                }
+            }
+
+            fn keyed(&self) -> RpcResponse<Page> {
+                RpcResponse::new(Page { items: vec![] })
+                    .with_meta(json!(1))
+                    .meta_key("items")
+            }
+        }
+
+        let handler = &ListingImpl as &dyn Listing;
+        let call = |method: &str, params: Value| {
+            handler
+                .handle_request(
+                    json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1}),
+                )
+                .as_option()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(
+            call("page", json!([null]))["result"],
+            json!({"items": [1, 2], "_meta": {"cursor": "abc"}})
+        );
+        assert_eq!(
+            call("page", json!(["abc"]))["result"],
+            json!({"items": [1, 2]})
+        );
+        assert_eq!(call("count", json!([false]))["result"], json!(2));
+        // metadata can't be folded into results that are not objects, or over their members
+        assert_eq!(
+            call("count", json!([true]))["error"]["code"],
+            json!(super::SERIALIZATION_ERROR)
+        );
+        assert_eq!(
+            call("keyed", json!([]))["error"]["data"],
+            json!("the result already has a member named \"items\", the key of the metadata")
+        );
+
+        let client = ListingClient(super::InMemoryTransport::new(&handler));
+        let page = client.page(None).unwrap();
+        assert_eq!(page.result, Page { items: vec![1, 2] });
+        assert_eq!(page.meta, Some(json!({"cursor": "abc"})));
+        assert_eq!(client.page(Some("abc".into())).unwrap().meta, None);
+        assert_eq!(client.count(false).unwrap().into_result(), 2);
+
+        let custom =
+            RpcResponse::<Page>::from_value(json!({"items": [3], "next": "def"}), "next").unwrap();
+        assert_eq!(custom.result, Page { items: vec![3] });
+        assert_eq!(custom.meta, Some(json!("def")));
+
+        assert_eq!(
+            listing::openrpc_schema()["methods"][1]["result"]["schema"],
+            json!({"type": "integer"})
+        );
+    }
+
+    #[test]
+    fn omitted_option_args() {
+        #[easy_jsonrpc::rpc]
+        trait Search {
+            fn find(&self, limit: Option<u8>, query: String) -> (Option<u8>, String);
+            #[rpc(params = "named")]
+            fn count(&self, filter: std::option::Option<String>) -> Option<String>;
+        }
+
+        struct SearchImpl;
+        impl Search for SearchImpl {
+            fn find(&self, limit: Option<u8>, query: String) -> (Option<u8>, String) {
+                (limit, query)
+            }
+
+            fn count(&self, filter: Option<String>) -> Option<String> {
+                filter
+            }
+        }
+
+        let handler = &SearchImpl as &dyn Search;
+        let call = |method: &str, params: Value| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": params,
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(
+            call("find", json!({"query": "q"}))["result"],
+            json!([null, "q"])
+        );
+        assert_eq!(
+            call("find", json!({"query": "q", "limit": 3}))["result"],
+            json!([3, "q"])
+        );
+        assert_eq!(call("count", json!({}))["result"], json!(null));
+        assert_eq!(call("count", json!({"filter": "f"}))["result"], json!("f"));
+        // positional parameters must list every argument
+        assert_eq!(
+            call("find", json!(["q"]))["error"]["message"],
+            json!("WrongNumberOfArgs. Expected 2. Actual 1")
+        );
+        assert_eq!(
+            call("find", json!([null, "q"]))["result"],
+            json!([null, "q"])
+        );
+        // other arguments are still required
+        assert_eq!(
+            call("find", json!({"limit": 3}))["error"]["message"],
+            json!("MissingNamedParameter query")
+        );
+    }
+
+    #[test]
+    fn params_struct() {
+        #[easy_jsonrpc::rpc]
+        trait Calculator {
+            #[rpc(params_struct)]
+            fn wrapping_add(&self, a: isize, b: isize) -> isize;
+            #[rpc(params_struct, lenient_numbers)]
+            fn label(
+                &self,
+                text: &str,
+                #[rpc(rename = "fontSize")] font_size: u8,
+                #[rpc(default)] bold: bool,
+            ) -> String;
+        }
+
+        struct CalculatorImpl;
+        impl Calculator for CalculatorImpl {
+            fn wrapping_add(&self, a: isize, b: isize) -> isize {
+                a.wrapping_add(b)
+            }
+
+            fn label(&self, text: &str, font_size: u8, bold: bool) -> String {
+                format!("{} {} {}", text, font_size, bold)
+            }
+        }
+
+        let handler = &CalculatorImpl as &dyn Calculator;
+        let call = |method: &str, params: Value| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": params,
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(call("wrapping_add", json!([1, 2]))["result"], json!(3));
+        assert_eq!(
+            call("wrapping_add", json!({"b": 2, "a": 1}))["result"],
+            json!(3)
+        );
+        assert_eq!(
+            call("label", json!({"text": "hi", "fontSize": "12"}))["result"],
+            json!("hi 12 false")
+        );
+        assert_eq!(
+            call("label", json!(["hi", 12, true]))["result"],
+            json!("hi 12 true")
+        );
+        for bad in [
+            json!([1]),
+            json!([1, 2, 3]),
+            json!({"a": 1}),
+            json!({"a": 1, "b": 2, "c": 3}),
+            json!({"a": 1, "b": "2"}),
+        ]
+        .iter()
+        {
+            let error = &call("wrapping_add", bad.clone())["error"];
+            assert_eq!(error["code"], json!(-32602), "{}", bad);
+            assert!(error["message"]
+                .as_str()
+                .unwrap()
+                .starts_with("InvalidParamsStructure: "));
+        }
+
+        // the struct builds requests on the client side
+        let params = WrappingAddParams { a: 5, b: -2 };
+        let transport = easy_jsonrpc::InMemoryTransport::new(&handler);
+        assert_eq!(transport.call(&params.request().unwrap()).unwrap(), 3);
+        let label = LabelParams {
+            text: "x".into(),
+            font_size: 1,
+            bold: false,
+        };
+        assert_eq!(
+            label.notification().unwrap().as_request()["params"],
+            json!(["x", 1, false])
+        );
+    }
+
+    #[test]
+    fn typed_client() {
+        use super::{ClientError, InMemoryTransport};
+
+        #[easy_jsonrpc::rpc(client)]
+        trait Store {
+            fn checked_add(&self, a: u8, b: u8) -> Result<u8, String>;
+            fn clear(&self);
+            fn get(&self, key: &str) -> String;
+        }
+
+        struct StoreImpl;
+        impl Store for StoreImpl {
+            fn checked_add(&self, a: u8, b: u8) -> Result<u8, String> {
+                a.checked_add(b).ok_or_else(|| "overflow".to_string())
+            }
+            fn clear(&self) {}
+            fn get(&self, key: &str) -> String {
+                key.to_uppercase()
+            }
+        }
+
+        let client = StoreClient(InMemoryTransport::new(&StoreImpl as &dyn Store));
+        assert_eq!(client.checked_add(1, 2), Ok(3));
+        match client.checked_add(255, 1) {
+            Err(ClientError::Rpc(err)) => assert_eq!(err.message, "overflow"),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(client.clear(), Ok(()));
+        assert_eq!(client.get("a"), Ok("A".to_string()));
+
+        // a server answering every call with the same result
+        let replying = |result: Value| {
+            move |request: &Value| -> Result<Option<Value>, String> {
+                Ok(Some(
+                    json!({"jsonrpc": "2.0", "result": result, "id": request["id"]}),
+                ))
+            }
+        };
+        assert_eq!(StoreClient(replying(json!(null))).clear(), Ok(()));
+        assert_eq!(
+            StoreClient(replying(json!({"Ok": 4}))).checked_add(2, 2),
+            Ok(4)
+        );
+        assert_eq!(
+            StoreClient(replying(json!({"Err": "overflow"}))).checked_add(255, 1),
+            Err(ClientError::EmbeddedErr(json!("overflow")))
+        );
+        assert!(matches!(
+            StoreClient(replying(json!("four"))).checked_add(2, 2),
+            Err(ClientError::Deserialize { .. })
+        ));
+        let failing = |_: &Value| -> Result<Option<Value>, String> { Err("offline".into()) };
+        assert_eq!(
+            StoreClient(failing).clear(),
+            Err(ClientError::Transport("offline".to_string()))
+        );
+        let silent = |_: &Value| -> Result<Option<Value>, String> { Ok(None) };
+        assert_eq!(
+            StoreClient(silent).clear(),
+            Err(ClientError::ResultNotFound)
+        );
+        let garbled = |_: &Value| -> Result<Option<Value>, String> { Ok(Some(json!(1))) };
+        assert_eq!(
+            StoreClient(garbled).clear(),
+            Err(ClientError::InvalidResponse)
+        );
+    }
+
+    #[test]
+    fn value_passthrough() {
+        #[easy_jsonrpc::rpc]
+        trait Proxy {
+            fn forward(&self, value: Value) -> easy_jsonrpc::Value;
+            fn fetch(&self, key: String) -> Result<serde_json::Value, String>;
+        }
+
+        struct ProxyImpl;
+        impl Proxy for ProxyImpl {
+            fn forward(&self, value: Value) -> easy_jsonrpc::Value {
+                value
+            }
+
+            fn fetch(&self, key: String) -> Result<serde_json::Value, String> {
+                match key.as_str() {
+                    "upstream" => Ok(json!({"nested": [1, "two", null]})),
+                    _ => Err(format!("no such key {}", key)),
+                }
+            }
+        }
+
+        let handler = &ProxyImpl as &dyn Proxy;
+        let call = |method: &str, params: Value| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": params,
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(
+            call("forward", json!([{"a": 18446744073709551615u64}])),
+            json!({"jsonrpc": "2.0", "result": {"a": 18446744073709551615u64}, "id": 1})
+        );
+        assert_eq!(
+            call("fetch", json!(["upstream"])),
+            json!({"jsonrpc": "2.0", "result": {"nested": [1, "two", null]}, "id": 1})
+        );
+        assert_eq!(
+            call("fetch", json!(["other"]))["error"]["message"],
+            json!("no such key other")
+        );
+    }
+
+    #[test]
+    fn unit_results() {
+        #[easy_jsonrpc::rpc]
+        trait Files {
+            fn delete(&self, id: u64) -> Result<(), String>;
+            fn delete_all(&self) -> std::result::Result<(), String>;
+        }
+
+        struct FilesImpl;
+        impl Files for FilesImpl {
+            fn delete(&self, id: u64) -> Result<(), String> {
+                match id {
+                    0 => Err("no file with id 0".into()),
+                    _ => Ok(()),
+                }
+            }
+
+            fn delete_all(&self) -> std::result::Result<(), String> {
+                Ok(())
+            }
+        }
+
+        let handler = &FilesImpl as &dyn Files;
+        let call = |method: &str, params: Value| {
+            handler.handle_request(json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": params,
+                "id": 1
+            }))
+        };
+        assert_eq!(
+            call("delete", json!([1])),
+            MaybeReply::Reply(json!({"jsonrpc": "2.0", "result": null, "id": 1}))
+        );
+        assert_eq!(
+            call("delete_all", json!([])),
+            MaybeReply::Reply(json!({"jsonrpc": "2.0", "result": null, "id": 1}))
+        );
+        assert_eq!(
+            call("delete", json!([0])).as_option().unwrap()["error"]["message"],
+            json!("no file with id 0")
+        );
+        assert!(call("delete", json!([0]))
+            .as_option()
+            .unwrap()
+            .get("result")
+            .is_none());
+
+        // the client helper expects null for the unit result
+        let bound = files::delete(1).unwrap();
+        let (request, tracker) = bound.call();
+        let mut response = super::Response::from_json_response(
+            handler
+                .handle_request(request.as_request())
+                .as_option()
+                .unwrap()
+                .clone(),
+        )
+        .unwrap();
+        assert_eq!(tracker.get_return(&mut response), Ok(()));
+    }
+
+    #[test]
+    fn rpc_error_code() {
+        use std::fmt;
+
+        enum LookupError {
+            NotFound(String),
+            Forbidden,
+        }
+
+        impl fmt::Display for LookupError {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match self {
+                    LookupError::NotFound(key) => write!(f, "{} not found", key),
+                    LookupError::Forbidden => f.write_str("Forbidden"),
+                }
+            }
+        }
+
+        impl easy_jsonrpc::RpcErrorCode for LookupError {
+            fn code(&self) -> easy_jsonrpc::ErrorCode {
+                match self {
+                    LookupError::NotFound(_) => easy_jsonrpc::ErrorCode::ServerError(-32010),
+                    LookupError::Forbidden => easy_jsonrpc::ErrorCode::ServerError(-32011),
+                }
+            }
+
+            fn data(&self) -> Option<Value> {
+                match self {
+                    LookupError::NotFound(key) => Some(json!({ "key": key })),
+                    LookupError::Forbidden => None,
+                }
+            }
+        }
+
+        #[easy_jsonrpc::rpc]
+        trait Lookup {
+            fn lookup(&self, key: String) -> Result<usize, LookupError>;
+        }
+
+        struct LookupImpl;
+        impl Lookup for LookupImpl {
+            fn lookup(&self, key: String) -> Result<usize, LookupError> {
+                match key.as_str() {
+                    "one" => Ok(1),
+                    "secret" => Err(LookupError::Forbidden),
+                    _ => Err(LookupError::NotFound(key)),
+                }
+            }
+        }
+
+        let handler = &LookupImpl as &dyn Lookup;
+        let error = |key: &str| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": "lookup",
+                    "params": [key],
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()["error"]
+                .clone()
+        };
+        assert_eq!(
+            error("two"),
+            json!({"code": -32010, "message": "two not found", "data": {"key": "two"}})
+        );
+        assert_eq!(
+            error("secret"),
+            json!({"code": -32011, "message": "Forbidden"})
+        );
+    }
+
+    #[test]
+    fn notification() {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "method": "succeed",
+            "params": []
+        });
+        assert_eq!(
+            (&AdderImpl {} as &dyn Adder).handle_request(request),
+            MaybeReply::DontReply
+        );
+    }
+
+    #[test]
+    fn adder_client_non_macro() {
+        #[easy_jsonrpc::rpc]
+        trait Adder {
+            fn checked_add(&self, a: usize, b: usize) -> Option<usize> {
+                a.checked_add(b)
+            }
+        }
+
+        #[allow(non_camel_case_types)]
+        pub enum adder_client {}
+        impl adder_client {
+            fn checked_add(
+                arg0: usize,
+                arg1: usize,
+            ) -> Result<
+                easy_jsonrpc::BoundMethod<'static, Option<usize>>,
+                easy_jsonrpc::ArgSerializeError,
+            > {
+                Ok(easy_jsonrpc::BoundMethod::new(
+                    "checked_add",
+                    vec![
+                        serde_json::to_value(arg0).map_err(|_| easy_jsonrpc::ArgSerializeError)?,
+                        serde_json::to_value(arg1).map_err(|_| easy_jsonrpc::ArgSerializeError)?,
+                    ],
+                ))
+            }
+        }
+
+        impl Adder for () {}
+        let handler = &() as &dyn Adder;
+
+        let bind = adder_client::checked_add(1, 2).unwrap();
+        let (call, tracker) = bind.call();
+        let raw_response = handler
+            .handle_request(call.as_request())
+            .as_option()
+            .unwrap();
+        let mut response = easy_jsonrpc::Response::from_json_response(raw_response).unwrap();
+        let result: Option<usize> = tracker.get_return(&mut response).unwrap();
+        assert_eq!(result, Some(3));
+
+        assert_eq!(
+            handler.handle_request(
+                adder_client::checked_add(1, 2)
+                    .unwrap()
+                    .notification()
+                    .as_request()
+            ),
+            MaybeReply::DontReply
+        );
+    }
+
+    #[test]
+    fn adder_client_with_macro() {
+        #[easy_jsonrpc::rpc]
+        trait Adder {
+            fn checked_add(&self, a: usize, b: usize) -> Option<usize> {
+                a.checked_add(b)
+            }
+        }
+
+        impl Adder for () {}
+        let handler = &() as &dyn Adder;
+
+        let bind = adder::checked_add(1, 2).unwrap();
+        let (call, tracker) = bind.call();
+        let raw_response = handler
+            .handle_request(call.as_request())
+            .as_option()
+            .unwrap();
+        let mut response = easy_jsonrpc::Response::from_json_response(raw_response).unwrap();
+        let result: Option<usize> = tracker.get_return(&mut response).unwrap();
+        assert_eq!(result, Some(3));
+
+        let call = adder::checked_add(1, 2).unwrap();
+        assert_eq!(
+            handler.handle_request(call.notification().as_request()),
+            MaybeReply::DontReply
+        );
+    }
+
+    #[test]
+    fn client_with_reference_args() {
+        let handler = &AdderImpl {} as &dyn Adder;
+
+        let bind = adder::echo_ref(&2).unwrap();
+        let (call, tracker) = bind.call();
+        let raw_response = handler
+            .handle_request(call.as_request())
+            .as_option()
+            .unwrap();
+        let mut response = easy_jsonrpc::Response::from_json_response(raw_response).unwrap();
+        assert_eq!(tracker.get_return(&mut response).unwrap(), 2);
+
+        let call = adder::echo_ref(&2).unwrap();
+        assert_eq!(
+            handler.handle_request(call.notification().as_request()),
+            MaybeReply::DontReply
+        );
+    }
+
+    #[test]
+    fn unsized_reference_args() {
+        use std::borrow::Cow;
+
+        #[easy_jsonrpc::rpc]
+        trait Text {
+            fn concat(&self, a: &str, b: &[String], c: Cow<'_, str>) -> String;
+            fn repeat(&self, s: &str, #[rpc(default)] suffix: &str) -> String;
+            fn shout<'a>(&self, s: &'a str, t: &'a str) -> String;
+        }
+
+        #[easy_jsonrpc::rpc(tuple_args)]
+        trait TupleText {
+            fn tuple_concat(&self, a: &str, b: &[String]) -> String;
+        }
+
+        struct TextImpl;
+        impl Text for TextImpl {
+            fn concat(&self, a: &str, b: &[String], c: Cow<'_, str>) -> String {
+                format!("{}{}{}", a, b.concat(), c)
+            }
+            fn repeat(&self, s: &str, suffix: &str) -> String {
+                format!("{}{}{}", s, s, suffix)
+            }
+            fn shout<'a>(&self, s: &'a str, t: &'a str) -> String {
+                (s.to_owned() + t).to_uppercase()
+            }
+        }
+        impl TupleText for TextImpl {
+            fn tuple_concat(&self, a: &str, b: &[String]) -> String {
+                format!("{}{}", a, b.concat())
+            }
+        }
+
+        let call = |handler: &dyn Handler, method: &str, params: Value| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": params,
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()["result"]
+                .clone()
+        };
+        let text = &TextImpl as &dyn Text;
+        assert_eq!(
+            call(&text, "concat", json!(["a", ["b", "c"], "d"])),
+            json!("abcd")
+        );
+        assert_eq!(call(&text, "repeat", json!(["a"])), json!("aa"));
+        assert_eq!(call(&text, "repeat", json!(["a", "!"])), json!("aa!"));
+        assert_eq!(call(&text, "shout", json!(["a", "b"])), json!("AB"));
+        assert_eq!(
+            call(
+                &(&TextImpl as &dyn TupleText),
+                "tuple_concat",
+                json!(["a", ["b", "c"]])
+            ),
+            json!("abc")
+        );
+        assert_eq!(
+            text::concat("a", &["b".into()], Cow::Borrowed("c"))
+                .unwrap()
+                .call()
+                .0
+                .as_request()["params"],
+            json!(["a", ["b"], "c"])
+        );
+    }
+
+    #[test]
+    fn in_memory_transport() {
+        let handler = &AdderImpl {} as &dyn Adder;
+        let transport = easy_jsonrpc::InMemoryTransport::new(&handler);
+
+        assert_eq!(transport.call(&adder::wrapping_add(1, 2).unwrap()), Ok(3));
+        assert_eq!(
+            transport.call(&adder::fail().unwrap()),
+            Err(easy_jsonrpc::ResponseFail::RpcError(easy_jsonrpc::Error {
+                code: easy_jsonrpc::ErrorCode::ServerError(easy_jsonrpc::APPLICATION_ERROR),
+                message: "tada!".into(),
+                data: None,
+            }))
+        );
+
+        let bind0 = adder::checked_add(1, 2).unwrap();
+        let (call0, tracker0) = bind0.call();
+        let bind1 = adder::swallow().unwrap();
+        let call1 = bind1.notification();
+        let mut response = transport
+            .send(&easy_jsonrpc::Call::batch_request(&[call0, call1]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(tracker0.get_return(&mut response), Ok(Some(3)));
+        assert!(response.outputs.is_empty());
+
+        let bind = adder::swallow().unwrap();
+        assert!(transport
+            .send(&bind.notification().as_request())
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            transport.call(&easy_jsonrpc::BoundMethod::<()>::new(
+                "swallow",
+                vec![json!(1)]
+            )),
+            Err(easy_jsonrpc::ResponseFail::RpcError(
+                easy_jsonrpc::Error::invalid_params("WrongNumberOfArgs. Expected 0. Actual 1")
+            ))
+        );
+    }
+
+    #[test]
+    fn rpc_descriptors() {
+        use easy_jsonrpc::MethodDescriptor;
+
+        #[easy_jsonrpc::rpc(supertraits(Base))]
+        trait Wallet: Base {
+            #[rpc(name = "wallet.send")]
+            fn send(
+                &self,
+                #[rpc(rename = "to")] address: &str,
+                amounts: Vec<(u64, Option<String>)>,
+                #[rpc(default)] memo: std::borrow::Cow<'static, str>,
+            );
+        }
+
+        #[easy_jsonrpc::rpc]
+        trait Base {
+            fn version(&self) -> String;
+        }
+
+        assert_eq!(
+            wallet::rpc_descriptors(),
+            vec![
+                MethodDescriptor {
+                    name: "wallet.send",
+                    params: vec![
+                        ("to", "&str"),
+                        ("amounts", "Vec<(u64, Option<String>)>"),
+                        ("memo", "std::borrow::Cow<'static, str>"),
+                    ],
+                    deprecated: None,
+                },
+                MethodDescriptor {
+                    name: "version",
+                    params: vec![],
+                    deprecated: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn describe() {
+        #[easy_jsonrpc::rpc(describe)]
+        trait Calculator {
+            fn add(&self, a: u64, b: u64) -> u64;
+            fn reset(&self);
+        }
+
+        struct CalculatorImpl;
+        impl Calculator for CalculatorImpl {
+            fn add(&self, a: u64, b: u64) -> u64 {
+                a + b
+            }
+
+            fn reset(&self) {}
+        }
+
+        let handler = &CalculatorImpl as &dyn Calculator;
+        assert_eq!(handler.methods(), vec!["add", "reset", "rpc.describe"]);
+        assert_eq!(
+            handler.handle_request(json!({"jsonrpc": "2.0", "method": "rpc.describe", "id": 1})),
+            MaybeReply::Reply(json!({
+                "jsonrpc": "2.0",
+                "result": [
+                    {
+                        "name": "add",
+                        "params": [{"name": "a", "type": "u64"}, {"name": "b", "type": "u64"}]
+                    },
+                    {"name": "reset", "params": []},
+                ],
+                "id": 1
+            }))
+        );
+        // without the option, rpc.describe is like any other unknown method
+        assert_eq!(
+            (&AdderImpl as &dyn Adder)
+                .handle_request(json!({"jsonrpc": "2.0", "method": "rpc.describe", "id": 1}))
+                .as_option()
+                .unwrap()["error"]["code"],
+            json!(-32601)
+        );
+    }
+
+    #[test]
+    fn rpc_call_macro() {
+        let handler = &AdderImpl {} as &dyn Adder;
+        let (method_call, tracker) = crate::rpc_call!(adder::wrapping_add, 1, 2).unwrap();
+        assert_eq!(method_call.method, adder_methods::WRAPPING_ADD);
+        let response = handler
+            .handle_parsed(easy_jsonrpc::types::Request::Single(
+                easy_jsonrpc::types::Call::MethodCall(method_call),
+            ))
+            .unwrap();
+        let mut response =
+            easy_jsonrpc::Response::from_json_response(serde_json::to_value(response).unwrap())
+                .unwrap();
+        assert_eq!(tracker.get_return(&mut response), Ok(3));
+
+        let (method_call, _) = crate::rpc_call!(adder::greet).unwrap();
+        assert_eq!(
+            serde_json::to_value(method_call).unwrap()["params"],
+            json!([])
+        );
+    }
+
+    #[test]
+    fn context_args() {
+        use easy_jsonrpc::ContextHandler;
+
+        struct Session {
+            user: &'static str,
+        }
+
+        #[easy_jsonrpc::rpc]
+        trait Files {
+            #[rpc(authorize)]
+            fn authorize(
+                &self,
+                #[rpc(context)] session: &Session,
+                method: &str,
+            ) -> Result<(), easy_jsonrpc::Error>;
+            #[rpc(auth)]
+            fn read(&self, #[rpc(context)] session: &Session, path: String) -> String;
+            #[rpc(with_id)]
+            fn whoami(&self, id: Id, #[rpc(context)] session: &Session) -> String;
+            fn version(&self) -> u8;
+        }
+
+        struct FilesImpl;
+        impl Files for FilesImpl {
+            fn authorize(
+                &self,
+                session: &Session,
+                _method: &str,
+            ) -> Result<(), easy_jsonrpc::Error> {
+                match session.user {
+                    "guest" => Err(easy_jsonrpc::Error::invalid_request()),
+                    _ => Ok(()),
+                }
+            }
+            fn read(&self, session: &Session, path: String) -> String {
+                format!("{}/{}", session.user, path)
+            }
+            fn whoami(&self, id: Id, session: &Session) -> String {
+                format!("{} ({:?})", session.user, id)
+            }
+            fn version(&self) -> u8 {
+                1
+            }
+        }
+
+        let handler = &FilesImpl as &dyn Files;
+        let ann = Session { user: "ann" };
+        let guest = Session { user: "guest" };
+        let call = |method: &str, params: Value, session: &Session| {
+            handler
+                .handle_request_with_ctx(
+                    json!({
+                        "jsonrpc": "2.0",
+                        "method": method,
+                        "params": params,
+                        "id": 1
+                    }),
+                    session,
+                )
+                .as_option()
+                .unwrap()
+        };
+        assert_eq!(
+            call("read", json!(["a.txt"]), &ann)["result"],
+            json!("ann/a.txt")
+        );
+        assert_eq!(
+            call("read", json!(["a.txt"]), &guest)["error"]["code"],
+            json!(-32600)
+        );
+        assert_eq!(
+            call("whoami", json!([]), &ann)["result"],
+            json!("ann (Num(1))")
+        );
+        assert_eq!(call("version", json!([]), &guest)["result"], json!(1));
+        // the context is not part of the params
+        assert_eq!(
+            call("read", json!(["ann", "a.txt"]), &ann)["error"]["message"],
+            json!("WrongNumberOfArgs. Expected 1. Actual 2")
+        );
+        assert_eq!(
+            files::read("a.txt".into()).unwrap().call().0.as_request()["params"],
+            json!(["a.txt"])
+        );
+        assert_eq!(
+            handler.methods_with_ctx(),
+            vec!["read", "whoami", "version"]
+        );
+    }
+
+    #[test]
+    fn response_double_get() {
+        let handler = &AdderImpl as &dyn Adder;
+        use easy_jsonrpc::Call;
+        let bind0 = adder::checked_add(0, 0).unwrap();
+        let (call0, tracker0) = bind0.call();
+        let bind1 = adder::checked_add(1, 0).unwrap();
+        let (call1, tracker1) = bind1.call();
+        let bind2 = adder::wrapping_add(1, 1).unwrap();
+        let (call2, tracker2) = bind2.call();
+        let json_request = Call::batch_request(&[call0, call1, call2]);
+        let json_response = handler.handle_request(json_request).as_option().unwrap();
+        let mut response = easy_jsonrpc::Response::from_json_response(json_response).unwrap();
+        assert_eq!(tracker0.get_return(&mut response).unwrap(), Some(0));
+        assert_eq!(tracker2.get_return(&mut response).unwrap(), 2);
+
+        // get_return removes the returned return value
+        assert_eq!(tracker1.get_return(&mut response), Ok(Some(1)));
+        assert_eq!(
+            tracker1.get_return(&mut response),
+            Err(easy_jsonrpc::ResponseFail::ResultNotFound)
+        );
+    }
+
+    #[test]
+    fn local_types() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        pub struct Foo;
+
+        #[easy_jsonrpc::rpc]
+        trait Bar {
+            fn frob(&self) -> Foo;
+            fn borf(&self, foo: Foo);
+        }
+    }
+
+    #[test]
+    fn mutable_handler() {
+        use std::collections::BTreeMap;
+
+        #[easy_jsonrpc::rpc]
+        trait Cache {
+            fn put(&mut self, key: String, val: String);
+            fn get(&self, key: String) -> Option<String>;
+        }
+
+        impl Cache for BTreeMap<String, String> {
+            fn put(&mut self, key: String, val: String) {
+                self.insert(key, val);
+            }
+
+            fn get(&self, key: String) -> Option<String> {
+                BTreeMap::get(self, &key).cloned()
+            }
+        }
+
+        let mut cache = BTreeMap::new();
+        let handler = &mut cache as &mut dyn Cache;
+
+        assert_eq!(
+            handler.handle_request_mut(json!([
+                {
+                    "jsonrpc": "2.0",
+                    "method": "put",
+                    "params": ["a", "b"],
+                    "id": 1
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "method": "get",
+                    "params": ["a"],
+                    "id": 2
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "method": "put",
+                    "params": ["a", "c"]
+                },
+            ])),
+            MaybeReply::Reply(json!([
+                {
+                    "jsonrpc": "2.0",
+                    "result": null,
+                    "id": 1
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "result": "b",
+                    "id": 2
+                }
+            ]))
+        );
+        assert_eq!(cache.get("a").map(String::as_str), Some("c"));
+
+        // every Handler is a HandlerMut
+        let mut adder = AdderImpl;
+        assert_eq!(
+            (&mut adder as &mut dyn Adder)
+                .handle_request_mut(json!({
+                    "jsonrpc": "2.0",
+                    "method": "wrapping_add",
+                    "params": [1, 1],
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap(),
+            json!({
+                "jsonrpc": "2.0",
+                "result": 2,
+                "id": 1
+            })
+        );
+    }
+
+    #[test]
+    fn renamed_method() {
+        #[easy_jsonrpc::rpc]
+        trait Wallet {
+            #[rpc(name = "wallet.getBalance")]
+            fn get_balance(&self, account: String) -> u64;
+        }
+
+        struct WalletImpl;
+        impl Wallet for WalletImpl {
+            fn get_balance(&self, account: String) -> u64 {
+                account.len() as u64
+            }
+        }
+
+        let handler = &WalletImpl as &dyn Wallet;
+        let call = |method: &str| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": ["abc"],
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+        };
+        assert_eq!(
+            call("wallet.getBalance"),
+            json!({
+                "jsonrpc": "2.0",
+                "result": 3,
+                "id": 1
+            })
+        );
+        assert_eq!(
+            call("get_balance")["error"]["code"],
+            json!(jsonrpc_core::ErrorCode::MethodNotFound.code())
+        );
+
+        // client helpers keep the rust name but send the renamed method
+        let bound = wallet::get_balance("abc".into()).unwrap();
+        let (request, _) = bound.call();
+        assert_eq!(request.as_request()["method"], json!("wallet.getBalance"));
+    }
+
+    #[test]
+    fn method_with_id() {
+        use std::cell::RefCell;
+
+        #[easy_jsonrpc::rpc]
+        trait Logger {
+            #[rpc(with_id)]
+            fn log(&self, id: Id, message: String) -> Id;
+        }
+
+        struct LoggerImpl(RefCell<Vec<(Id, String)>>);
+        impl Logger for LoggerImpl {
+            fn log(&self, id: Id, message: String) -> Id {
+                self.0.borrow_mut().push((id.clone(), message));
+                id
+            }
+        }
+
+        let logger = LoggerImpl(RefCell::new(Vec::new()));
+        let handler = &logger as &dyn Logger;
+        assert_eq!(
+            handler.handle_request(json!([
+                {
+                    "jsonrpc": "2.0",
+                    "method": "log",
+                    "params": ["a"],
+                    "id": "first"
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "method": "log",
+                    "params": {"message": "b"},
+                    "id": 2
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "method": "log",
+                    "params": ["c"]
+                },
+            ])),
+            MaybeReply::Reply(json!([
+                {
+                    "jsonrpc": "2.0",
+                    "result": "first",
+                    "id": "first"
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "result": 2,
+                    "id": 2
+                }
+            ]))
+        );
+        assert_eq!(
+            logger.0.into_inner(),
+            vec![
+                (Id::Str("first".into()), "a".into()),
+                (Id::Num(2), "b".into()),
+                (Id::Null, "c".into()),
+            ]
+        );
+
+        // the id is not part of the client helper
+        let bound = logger::log("d".into()).unwrap();
+        let (request, _) = bound.call();
+        assert_eq!(request.as_request()["params"], json!(["d"]));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_option() {
+        use super::AsyncHandler;
+        use futures::executor::block_on;
+
+        #[easy_jsonrpc::rpc(async, prefix = "clock.")]
+        trait Clock: Sync {
+            async fn now(&self) -> u64;
+        }
+
+        struct ClockImpl;
+        #[easy_jsonrpc::async_trait]
+        impl Clock for ClockImpl {
+            async fn now(&self) -> u64 {
+                42
+            }
+        }
+
+        let handler = &ClockImpl as &dyn Clock;
+        assert_eq!(
+            block_on(handler.handle_request_async(
+                json!({"jsonrpc": "2.0", "method": "clock.now", "params": [], "id": 1})
+            )),
+            MaybeReply::Reply(json!({"jsonrpc": "2.0", "result": 42, "id": 1}))
+        );
+        let bound = clock::now().unwrap();
+        let (call, tracker) = bound.call();
+        assert_eq!(call.as_request()["method"], json!("clock.now"));
+        let response = block_on(handler.handle_request_async(call.as_request()));
+        let mut response =
+            super::Response::from_json_response(response.as_option().unwrap().clone()).unwrap();
+        assert_eq!(tracker.get_return(&mut response), Ok(42));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_instantiated_methods() {
+        use super::AsyncHandler;
+        use futures::executor::block_on;
+
+        #[easy_jsonrpc::rpc(async)]
+        trait Echo: Sync {
+            #[rpc(instantiate(echo_u64 = "echo::<u64>"))]
+            async fn echo<T: Send + 'static>(&self, value: T) -> T;
+            async fn echo_u64(&self, value: u64) -> u64;
+        }
+
+        struct EchoImpl;
+        #[easy_jsonrpc::async_trait]
+        impl Echo for EchoImpl {
+            async fn echo<T: Send + 'static>(&self, value: T) -> T {
+                value
+            }
+            async fn echo_u64(&self, value: u64) -> u64 {
+                self.echo(value).await
+            }
+        }
+
+        let handler = &EchoImpl as &dyn Echo;
+        assert_eq!(handler.methods_async(), vec!["echo_u64"]);
+        assert_eq!(
+            block_on(handler.handle_request_async(
+                json!({"jsonrpc": "2.0", "method": "echo_u64", "params": [7], "id": 1})
+            )),
+            MaybeReply::Reply(json!({"jsonrpc": "2.0", "result": 7, "id": 1}))
+        );
+    }
+
+    #[test]
+    fn arc_server() {
+        use super::ArcServer;
+        use std::sync::Arc;
+
+        #[easy_jsonrpc::rpc]
+        trait Squarer: Send + Sync {
+            fn square(&self, n: u64) -> u64;
+        }
+
+        struct SquarerImpl;
+        impl Squarer for SquarerImpl {
+            fn square(&self, n: u64) -> u64 {
+                n * n
+            }
+        }
+
+        let server = ArcServer::new(Arc::new(SquarerImpl) as Arc<dyn Squarer>);
+        let threads = (0..4u64)
+            .map(|n| {
+                let job = server
+                    .job(json!({"jsonrpc": "2.0", "method": "square", "params": [n], "id": n}));
+                std::thread::spawn(job)
+            })
+            .collect::<Vec<_>>();
+        let results = threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap().as_option().unwrap()["result"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(results, vec![json!(0), json!(1), json!(4), json!(9)]);
+
+        let job =
+            server.bytes_job(br#"{"jsonrpc": "2.0", "method": "square", "params": [5]}"#.to_vec());
+        assert_eq!(std::thread::spawn(job).join().unwrap(), None);
+        let job = server.bytes_job(
+            br#"[{"jsonrpc": "2.0", "method": "square", "params": [5], "id": 1}]"#.to_vec(),
+        );
+        assert_eq!(
+            serde_json::from_slice::<Value>(&std::thread::spawn(job).join().unwrap().unwrap())
+                .unwrap(),
+            json!([{"jsonrpc": "2.0", "result": 25, "id": 1}])
+        );
+
+        // the server is a handler itself, sharing the handler with its clones
+        let clone = server.clone();
+        assert!(Arc::ptr_eq(server.handler(), clone.handler()));
+        assert_eq!(clone.methods(), vec!["square"]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn arc_server_async() {
+        use super::{ArcServer, AsyncHandler};
+        use futures::executor::block_on;
+        use std::sync::Arc;
+
+        #[easy_jsonrpc::rpc_async]
+        trait Squarer: Send + Sync {
+            async fn square(&self, n: u64) -> u64;
+        }
+
+        struct SquarerImpl;
+        #[easy_jsonrpc::async_trait]
+        impl Squarer for SquarerImpl {
+            async fn square(&self, n: u64) -> u64 {
+                n * n
+            }
+        }
+
+        let server = ArcServer::new(Arc::new(SquarerImpl) as Arc<dyn Squarer>);
+        let reply = server.handle_request_owned(json!([
+            {"jsonrpc": "2.0", "method": "square", "params": [2], "id": 1},
+            {"jsonrpc": "2.0", "method": "square", "params": [3], "id": 2}
+        ]));
+        // the future borrows nothing, it may complete on another thread
+        let reply = std::thread::spawn(move || block_on(reply)).join().unwrap();
+        assert_eq!(
+            reply,
+            MaybeReply::Reply(json!([
+                {"jsonrpc": "2.0", "result": 4, "id": 1},
+                {"jsonrpc": "2.0", "result": 9, "id": 2}
+            ]))
+        );
+        assert_eq!(server.methods_async(), vec!["square"]);
+        assert_eq!(server.handler().methods_async(), vec!["square"]);
+    }
+
+    #[test]
+    fn canonical_replies() {
+        use std::collections::HashMap;
+
+        #[easy_jsonrpc::rpc]
+        trait Inventory {
+            fn counts(&self) -> HashMap<String, HashMap<String, u32>>;
+        }
+
+        struct InventoryImpl;
+        impl Inventory for InventoryImpl {
+            fn counts(&self) -> HashMap<String, HashMap<String, u32>> {
+                let bins = |names: &[&str]| -> HashMap<String, u32> {
+                    names
+                        .iter()
+                        .map(|n| (n.to_string(), n.len() as u32))
+                        .collect()
+                };
+                ["pears", "apples", "figs", "kiwis", "dates"]
+                    .iter()
+                    .map(|fruit| (fruit.to_string(), bins(&["zz", "a", "mmm"])))
+                    .collect()
+            }
+        }
+
+        let handler = &InventoryImpl as &dyn Inventory;
+        let bins = r#"{"a":1,"mmm":3,"zz":2}"#;
+        assert_eq!(
+            handler.handle_raw_canonical(r#"{"jsonrpc": "2.0", "method": "counts", "id": 1}"#),
+            Some(format!(
+                r#"{{"id":1,"jsonrpc":"2.0","result":{{"apples":{0},"dates":{0},"figs":{0},"kiwis":{0},"pears":{0}}}}}"#,
+                bins
+            ))
+        );
+        // replies that answer no particular call are sorted too
+        assert_eq!(
+            handler.handle_raw_canonical("{"),
+            Some(
+                r#"{"error":{"code":-32700,"message":"Parse error"},"id":null,"jsonrpc":"2.0"}"#
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            handler.handle_raw_canonical(r#"{"jsonrpc": "2.0", "method": "counts"}"#),
+            None
+        );
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn metered_server() {
+        use crate::{MeteredServer, MethodMetrics};
+
+        let server = MeteredServer::new(&AdderImpl as &dyn Adder);
+        assert_eq!(server.snapshot()["wrapping_add"], MethodMetrics::default());
+        server.handle_request(json!([
+            {"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1},
+            {"jsonrpc": "2.0", "method": "wrapping_add", "params": [1], "id": 2},
+            {"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2]},
+            {"jsonrpc": "2.0", "method": "fail", "params": [], "id": 3},
+            {"jsonrpc": "2.0", "method": "missing", "params": [], "id": 4},
+            {"jsonrpc": "2.0", "method": "also_missing", "id": 5},
+        ]));
+        let snapshot = server.snapshot();
+        assert_eq!(
+            snapshot["wrapping_add"],
+            MethodMetrics {
+                calls: 3,
+                successes: 2,
+                failures: 1
+            }
+        );
+        assert_eq!(
+            snapshot["fail"],
+            MethodMetrics {
+                calls: 1,
+                successes: 0,
+                failures: 1
+            }
+        );
+        assert_eq!(snapshot["checked_add"].calls, 0);
+        assert!(!snapshot.contains_key("missing"));
+        assert_eq!(
+            server.unlisted(),
+            MethodMetrics {
+                calls: 2,
+                successes: 0,
+                failures: 2
+            }
+        );
+    }
+
+    #[test]
+    fn streamed_results() {
+        use super::{BoundMethod, Response, Streamed};
+
+        #[easy_jsonrpc::rpc]
+        trait Logs {
+            fn logs(&self, since: u64) -> Streamed<Box<dyn Iterator<Item = String>>>;
+        }
+
+        struct LogsImpl;
+
+        impl Logs for LogsImpl {
+            fn logs(&self, since: u64) -> Streamed<Box<dyn Iterator<Item = String>>> {
+                Streamed::new(Box::new((since..since + 3).map(|n| format!("entry {}", n))))
+            }
+        }
+
+        let handler = &LogsImpl as &dyn Logs;
+        let bound: BoundMethod<Streamed<Box<dyn Iterator<Item = String>>>> = logs::logs(4).unwrap();
+        let (call, tracker) = bound.call();
+        let reply = handler
+            .handle_request(call.as_request())
+            .as_option()
+            .unwrap();
+        assert_eq!(reply["result"], json!(["entry 4", "entry 5", "entry 6"]));
+        let mut response = Response::from_json_response(reply).unwrap();
+        let entries: Vec<String> = tracker.get_return(&mut response).unwrap().collect();
+        assert_eq!(entries, vec!["entry 4", "entry 5", "entry 6"]);
+
+        let once = Streamed::new(vec![1, 2].into_iter());
+        assert_eq!(serde_json::to_value(&once).unwrap(), json!([1, 2]));
+        assert!(serde_json::to_value(&once).is_err());
+    }
+
+    #[test]
+    fn subscriptions() {
+        use crate::{collect_subscriptions, Subscription, SubscriptionId};
+
+        #[easy_jsonrpc::rpc(client, openrpc)]
+        trait Ticker {
+            fn subscribe(&self, count: u64) -> Subscription<u64>;
+        }
+
+        struct TickerImpl;
+        impl Ticker for TickerImpl {
+            fn subscribe(&self, count: u64) -> Subscription<u64> {
+                Subscription::new("tick", 0..count)
+            }
+        }
+
+        let handler = &TickerImpl as &dyn Ticker;
+        let subscribe =
+            |id: u64| json!({"jsonrpc": "2.0", "method": "subscribe", "params": [2], "id": id});
+        let (reply, subscriptions) =
+            collect_subscriptions(|| handler.handle_request(json!([subscribe(1), subscribe(2)])));
+        let reply = reply.as_option().unwrap().clone();
+        let ids: Vec<SubscriptionId> = subscriptions.iter().map(|sub| sub.id()).collect();
+        assert_eq!(ids.len(), 2);
+        assert_ne!(ids[0], ids[1]);
+        assert_eq!(reply[0]["result"], json!(ids[0].0));
+        assert_eq!(reply[1]["result"], json!(ids[1].0));
+        let notifications: Vec<Value> = subscriptions
+            .into_iter()
+            .next()
+            .unwrap()
+            .map(|notification| notification.as_request())
+            .collect();
+        assert_eq!(
+            notifications,
+            vec![
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "tick",
+                    "params": {"subscription": ids[0].0, "result": 0}
+                }),
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "tick",
+                    "params": {"subscription": ids[0].0, "result": 1}
+                }),
+            ]
+        );
+
+        // notifications get no id to refer to the subscription by, nothing is collected
+        let (_, subscriptions) = collect_subscriptions(|| {
+            handler.handle_request(json!({"jsonrpc": "2.0", "method": "subscribe", "params": [2]}))
+        });
+        assert!(subscriptions.is_empty());
+
+        // outside of a transport pushing notifications, subscribing fails
+        assert_eq!(
+            handler.handle_request(subscribe(3)).as_option().unwrap()["error"],
+            json!({
+                "code": super::SERIALIZATION_ERROR,
+                "message": "Serialization error",
+                "data": "subscriptions are only served by transports pushing notifications"
+            })
+        );
+
+        // clients receive the id
+        let subscribe = ticker::subscribe(2).unwrap();
+        let (call, tracker) = subscribe.call();
+        let (reply, _) = collect_subscriptions(|| handler.handle_request(call.as_request()));
+        let mut response =
+            super::Response::from_json_response(reply.as_option().unwrap().clone()).unwrap();
+        let subscription = tracker.get_return(&mut response).unwrap();
+        assert!(subscription.id().0 > ids[1].0);
+        assert_eq!(
+            ticker::openrpc_schema()["methods"][0]["result"]["schema"],
+            json!({"type": "integer"})
+        );
+    }
+
+    #[cfg(feature = "catch_unwind")]
+    #[test]
+    fn panicking_calls() {
+        use crate::PANIC_ERROR;
+
+        #[easy_jsonrpc::rpc]
+        trait Fragile {
+            fn explode(&self);
+            fn fine(&self) -> u8;
+        }
+
+        struct FragileImpl;
+
+        impl Fragile for FragileImpl {
+            fn explode(&self) {
+                panic!("boom");
+            }
+
+            fn fine(&self) -> u8 {
+                1
+            }
+        }
+
+        let handler = &FragileImpl as &dyn Fragile;
+        assert_eq!(
+            handler.handle_request(json!([
+                {"jsonrpc": "2.0", "method": "fine", "id": 1},
+                {"jsonrpc": "2.0", "method": "explode", "id": 2},
+                {"jsonrpc": "2.0", "method": "explode"},
+                {"jsonrpc": "2.0", "method": "fine", "id": 3},
+            ])),
+            MaybeReply::Reply(json!([
+                {"jsonrpc": "2.0", "result": 1, "id": 1},
+                {
+                    "jsonrpc": "2.0",
+                    "error": {
+                        "code": PANIC_ERROR,
+                        "message": "Method panicked",
+                        "data": "method 'explode' panicked: boom"
+                    },
+                    "id": 2
+                },
+                {"jsonrpc": "2.0", "result": 1, "id": 3},
+            ]))
+        );
+    }
+
+    #[cfg(all(feature = "async", feature = "catch_unwind"))]
+    #[test]
+    fn panicking_async_calls() {
+        use crate::{AsyncHandler, PANIC_ERROR};
+        use futures::executor::block_on;
+
+        #[easy_jsonrpc::rpc_async]
+        trait Jobs: Sync {
+            async fn explode(&self);
+            async fn fine(&self) -> u8;
+        }
+
+        struct JobsImpl;
+        #[easy_jsonrpc::async_trait]
+        impl Jobs for JobsImpl {
+            async fn explode(&self) {
+                futures::future::ready(()).await;
+                panic!("boom")
+            }
+
+            async fn fine(&self) -> u8 {
+                1
+            }
+        }
+
+        let handler = &JobsImpl as &dyn Jobs;
+        assert_eq!(
+            block_on(handler.handle_request_async(json!([
+                {"jsonrpc": "2.0", "method": "fine", "id": 1},
+                {"jsonrpc": "2.0", "method": "explode", "id": 2},
+                {"jsonrpc": "2.0", "method": "explode"},
+                {"jsonrpc": "2.0", "method": "fine", "id": 3},
+            ]))),
+            MaybeReply::Reply(json!([
+                {"jsonrpc": "2.0", "result": 1, "id": 1},
+                {
+                    "jsonrpc": "2.0",
+                    "error": {
+                        "code": PANIC_ERROR,
+                        "message": "Method panicked",
+                        "data": "method 'explode' panicked: boom"
+                    },
+                    "id": 2
+                },
+                {"jsonrpc": "2.0", "result": 1, "id": 3},
+            ]))
+        );
+    }
+
+    #[test]
+    fn catch_unwind_server() {
+        use crate::{CatchUnwindServer, PANIC_ERROR};
+
+        #[easy_jsonrpc::rpc]
+        trait Fragile {
+            fn explode(&self, code: u8);
+            fn fine(&self) -> u8;
+        }
+
+        struct FragileImpl;
+
+        impl Fragile for FragileImpl {
+            fn explode(&self, code: u8) {
+                match code {
+                    0 => panic!("boom"),
+                    1 => panic!("boom {}", code),
+                    _ => std::panic::panic_any(code),
+                }
+            }
+
+            fn fine(&self) -> u8 {
+                1
+            }
+        }
+
+        let server = CatchUnwindServer::new(&FragileImpl as &dyn Fragile);
+        let panicked = |id: u64, data: &str| {
+            json!({
+                "jsonrpc": "2.0",
+                "error": {"code": PANIC_ERROR, "message": "Method panicked", "data": data},
+                "id": id
+            })
+        };
+        assert_eq!(
+            server.handle_request(json!([
+                {"jsonrpc": "2.0", "method": "fine", "id": 1},
+                {"jsonrpc": "2.0", "method": "explode", "params": [0], "id": 2},
+                {"jsonrpc": "2.0", "method": "explode", "params": [1], "id": 3},
+                {"jsonrpc": "2.0", "method": "explode", "params": [2], "id": 4},
+                {"jsonrpc": "2.0", "method": "explode", "params": [0]},
+            ])),
+            MaybeReply::Reply(json!([
+                {"jsonrpc": "2.0", "result": 1, "id": 1},
+                panicked(2, "method 'explode' panicked: boom"),
+                panicked(3, "method 'explode' panicked: boom 1"),
+                panicked(4, "method 'explode' panicked"),
+            ]))
+        );
+        // single calls and direct calls are covered too
+        assert_eq!(
+            server.handle_request(
+                json!({"jsonrpc": "2.0", "method": "explode", "params": [0], "id": 5})
+            ),
+            MaybeReply::Reply(panicked(5, "method 'explode' panicked: boom"))
+        );
+        assert_eq!(
+            server
+                .handle("explode", Params::Positional(vec![json!(0)]))
+                .unwrap_err()
+                .code,
+            jsonrpc_core::ErrorCode::ServerError(PANIC_ERROR)
+        );
+        assert_eq!(server.methods(), vec!["explode", "fine"]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn catch_unwind_server_async() {
+        use crate::{AsyncHandler, CatchUnwindServer, PANIC_ERROR};
+        use futures::executor::block_on;
+
+        #[easy_jsonrpc::rpc_async]
+        trait Jobs: Sync {
+            async fn explode(&self);
+        }
+
+        struct JobsImpl;
+        #[easy_jsonrpc::async_trait]
+        impl Jobs for JobsImpl {
+            async fn explode(&self) {
+                futures::future::ready(()).await;
+                panic!("boom")
+            }
+        }
+
+        let server = CatchUnwindServer::new(&JobsImpl as &dyn Jobs);
+        assert_eq!(
+            block_on(
+                server
+                    .handle_request_async(json!({"jsonrpc": "2.0", "method": "explode", "id": 1}))
+            ),
+            MaybeReply::Reply(json!({
+                "jsonrpc": "2.0",
+                "error": {
+                    "code": PANIC_ERROR,
+                    "message": "Method panicked",
+                    "data": "method 'explode' panicked: boom"
+                },
+                "id": 1
+            }))
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn timeout_server() {
+        use crate::{AsyncHandler, TimeoutServer};
+        use futures::executor::block_on;
+        use std::time::{Duration, Instant};
+
+        #[easy_jsonrpc::rpc_async]
+        trait Jobs: Sync {
+            async fn hang(&self);
+            async fn quick(&self) -> u8;
+        }
+
+        struct JobsImpl;
+        #[easy_jsonrpc::async_trait]
+        impl Jobs for JobsImpl {
+            async fn hang(&self) {
+                futures::future::pending::<()>().await
+            }
+
+            async fn quick(&self) -> u8 {
+                1
+            }
+        }
+
+        let server = TimeoutServer::new(&JobsImpl as &dyn Jobs, Duration::from_millis(50));
+        assert_eq!(server.methods_async(), vec!["hang", "quick"]);
+        let started = Instant::now();
+        assert_eq!(
+            block_on(server.handle_request_async(json!([
+                {"jsonrpc": "2.0", "method": "hang", "params": [], "id": 1},
+                {"jsonrpc": "2.0", "method": "hang", "params": []},
+                {"jsonrpc": "2.0", "method": "quick", "params": [], "id": 2},
+            ]))),
+            MaybeReply::Reply(json!([
+                {
+                    "jsonrpc": "2.0",
+                    "error": {
+                        "code": crate::TIMEOUT_ERROR,
+                        "message": "Timeout",
+                        "data": "method 'hang' did not complete within 50ms"
+                    },
+                    "id": 1
+                },
+                {"jsonrpc": "2.0", "result": 1, "id": 2}
+            ]))
+        );
+        // notifications are bounded as well
+        assert_eq!(
+            block_on(
+                server.handle_request_async(
+                    json!({"jsonrpc": "2.0", "method": "hang", "params": []})
+                )
+            ),
+            MaybeReply::DontReply
+        );
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn rate_limited_server() {
+        use crate::{RateLimit, RateLimitedServer};
+        use std::cell::Cell;
+        use std::time::{Duration, Instant};
+
+        let now = Cell::new(Instant::now());
+        let advance = |secs: u64| now.set(now.get() + Duration::from_secs(secs));
+        let server = RateLimitedServer::with_clock(
+            &AdderImpl as &dyn Adder,
+            vec![("wrapping_add", RateLimit::per_minute(2))],
+            || now.get(),
+        );
+        let add = |id: u64| {
+            server
+                .handle_request(
+                    json!({"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": id}),
+                )
+                .as_option()
+                .unwrap()
+                .clone()
+        };
+        let limited = |id: u64| {
+            json!({
+                "jsonrpc": "2.0",
+                "error": {
+                    "code": crate::RATE_LIMIT_ERROR,
+                    "message": "Rate limited",
+                    "data": "method 'wrapping_add' allows 2 calls per 60s"
+                },
+                "id": id
+            })
+        };
+        let added = |id: u64| json!({"jsonrpc": "2.0", "result": 3, "id": id});
+
+        // a full bucket allows a burst, then refills at 2 calls per minute
+        assert_eq!(add(1), added(1));
+        assert_eq!(add(2), added(2));
+        assert_eq!(add(3), limited(3));
+        advance(29);
+        assert_eq!(add(4), limited(4));
+        advance(1);
+        assert_eq!(add(5), added(5));
+        assert_eq!(add(6), limited(6));
+        // the bucket holds no more than the limit however long it has been
+        advance(3600);
+        assert_eq!(add(7), added(7));
+        assert_eq!(add(8), added(8));
+        assert_eq!(add(9), limited(9));
+
+        // other methods are not limited, notifications take tokens
+        for id in 10..15 {
+            assert_eq!(
+                server
+                    .handle_request(json!({"jsonrpc": "2.0", "method": "greet", "id": id}))
+                    .as_option()
+                    .unwrap()["result"],
+                json!("hello")
+            );
+        }
+        advance(30);
+        server
+            .handle_request(json!({"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2]}));
+        assert_eq!(add(15), limited(15));
+        assert_eq!(server.methods(), (&AdderImpl as &dyn Adder).methods());
+
+        // zero calls per period rejects every call, a zero period allows every call
+        let server = RateLimitedServer::new(
+            &AdderImpl as &dyn Adder,
+            vec![
+                ("wrapping_add".to_owned(), RateLimit::per_second(0)),
+                (
+                    "greet".to_owned(),
+                    RateLimit::new(1, Duration::from_secs(0)),
+                ),
+            ],
+        );
+        let call = |method: &str| {
+            server
+                .handle_request(json!({"jsonrpc": "2.0", "method": method, "id": 1}))
+                .as_option()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(
+            call("wrapping_add")["error"]["code"],
+            json!(crate::RATE_LIMIT_ERROR)
+        );
+        assert_eq!(call("greet")["result"], json!("hello"));
+        assert_eq!(call("greet")["result"], json!("hello"));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn rate_limited_metered_server() {
+        use crate::{MeteredServer, MethodMetrics, RateLimit, RateLimitedServer};
+
+        let server = MeteredServer::new(RateLimitedServer::new(
+            &AdderImpl as &dyn Adder,
+            vec![("wrapping_add", RateLimit::per_minute(1))],
+        ));
+        for id in 0..3 {
+            server.handle_request(
+                json!({"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": id}),
+            );
+        }
+        assert_eq!(
+            server.snapshot()["wrapping_add"],
+            MethodMetrics {
+                calls: 3,
+                successes: 1,
+                failures: 2,
+            }
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn rate_limited_timeout_server() {
+        use crate::{AsyncHandler, RateLimit, RateLimitedServer, TimeoutServer};
+        use futures::executor::block_on;
+        use std::time::Duration;
+
+        #[easy_jsonrpc::rpc_async]
+        trait Exporter: Sync {
+            async fn export_all(&self) -> u8;
+        }
+
+        struct ExporterImpl;
+        #[easy_jsonrpc::async_trait]
+        impl Exporter for ExporterImpl {
+            async fn export_all(&self) -> u8 {
+                1
+            }
+        }
+
+        let server = TimeoutServer::new(
+            RateLimitedServer::new(
+                &ExporterImpl as &dyn Exporter,
+                vec![("export_all", RateLimit::per_minute(1))],
+            ),
+            Duration::from_secs(5),
+        );
+        assert_eq!(server.methods_async(), vec!["export_all"]);
+        assert_eq!(
+            block_on(server.handle_request_async(json!([
+                {"jsonrpc": "2.0", "method": "export_all", "params": [], "id": 1},
+                {"jsonrpc": "2.0", "method": "export_all", "params": [], "id": 2},
+            ]))),
+            MaybeReply::Reply(json!([
+                {"jsonrpc": "2.0", "result": 1, "id": 1},
+                {
+                    "jsonrpc": "2.0",
+                    "error": {
+                        "code": crate::RATE_LIMIT_ERROR,
+                        "message": "Rate limited",
+                        "data": "method 'export_all' allows 1 calls per 60s"
+                    },
+                    "id": 2
+                }
+            ]))
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_handler() {
+        use super::AsyncHandler;
+        use futures::executor::block_on;
+        use std::collections::BTreeMap;
+
+        #[easy_jsonrpc::rpc_async]
+        trait Store: Sync {
+            async fn get(&self, key: String) -> Option<String>;
+            #[rpc(name = "store.len", with_id)]
+            async fn len(&self, id: Id) -> (Id, usize);
+        }
+
+        struct StoreImpl(BTreeMap<String, String>);
+        #[easy_jsonrpc::async_trait]
+        impl Store for StoreImpl {
+            async fn get(&self, key: String) -> Option<String> {
+                self.0.get(&key).cloned()
+            }
+
+            async fn len(&self, id: Id) -> (Id, usize) {
+                (id, self.0.len())
+            }
+        }
+
+        let mut map = BTreeMap::new();
+        map.insert("a".to_string(), "b".to_string());
+        let store = StoreImpl(map);
+        let handler = &store as &dyn Store;
+
+        assert_eq!(
+            block_on(handler.handle_request_async(json!([
+                {
+                    "jsonrpc": "2.0",
+                    "method": "get",
+                    "params": ["a"],
+                    "id": 1
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "method": "get",
+                    "params": ["b"]
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "method": "store.len",
+                    "params": [],
+                    "id": 3
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "method": "len",
+                    "params": [],
+                    "id": 4
+                },
+            ]))),
+            MaybeReply::Reply(json!([
+                {
+                    "jsonrpc": "2.0",
+                    "result": "b",
+                    "id": 1
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "result": [3, 1],
+                    "id": 3
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "error": {
+                        "code": -32601,
+                        "message": "Method not found",
+                        "data": "method 'len' not found"
+                    },
+                    "id": 4
+                }
+            ]))
+        );
+        assert_eq!(
+            block_on(handler.handle_request_async(json!("not a request"))),
+            MaybeReply::Reply(json!({
+                "jsonrpc": "2.0",
+                "error": {
+                    "code": -32600,
+                    "message": "Invalid request"
+                },
+                "id": null
+            }))
+        );
+
+        let bound = store::get("a".into()).unwrap();
+        let (request, _) = bound.call();
+        assert_eq!(request.as_request()["method"], json!("get"));
+    }
+
+    #[test]
+    fn handle_bytes() {
+        let handler = &AdderImpl as &dyn Adder;
+        let bytes_response = |request: &[u8]| {
+            handler
+                .handle_bytes(request)
+                .map(|bytes| serde_json::from_slice::<Value>(&bytes).unwrap())
+        };
+
+        assert_eq!(
+            bytes_response(
+                br#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 1], "id": 1}"#
+            ),
+            Some(json!({
+                "jsonrpc": "2.0",
+                "result": 2,
+                "id": 1
+            }))
+        );
+        assert_eq!(
+            bytes_response(br#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 1]}"#),
+            None
+        );
+
+        let parse_error = json!({
+            "jsonrpc": "2.0",
+            "error": {
+                "code": -32700,
+                "message": "Parse error"
+            },
+            "id": null
+        });
+        assert_eq!(bytes_response(b"{"), Some(parse_error.clone()));
+        assert_eq!(bytes_response(b"\xff\xfe"), Some(parse_error));
+    }
+
+    #[test]
+    fn neither_call_nor_batch() {
+        let handler = &AdderImpl as &dyn Adder;
+        let not_a_request = json!({
+            "jsonrpc": "2.0",
+            "error": {"code": -32600, "message": "Invalid request"},
+            "id": null
+        });
+        for raw in &["true", "\"string\"", "1", "null"] {
+            let value: Value = serde_json::from_str(raw).unwrap();
+            assert_eq!(
+                handler.handle_request(value).as_option(),
+                Some(not_a_request.clone())
+            );
+            let reply = handler.handle_bytes(raw.as_bytes()).unwrap();
+            assert_eq!(
+                serde_json::from_slice::<Value>(&reply).unwrap(),
+                not_a_request
+            );
+        }
+        // an object that is not a call, or a batch of things that aren't calls, are answered
+        // call by call
+        assert_eq!(
+            handler
+                .handle_request(json!({"jsonrpc": "2.0", "params": []}))
+                .as_option(),
+            Some(json!({"error": {"code": -32600, "message": "Invalid request"}, "id": null}))
+        );
+        assert_eq!(
+            handler
+                .handle_request(json!([
+                    true,
+                    {"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 1], "id": 1}
+                ]))
+                .as_option(),
+            Some(json!([
+                {"error": {"code": -32600, "message": "Invalid request"}, "id": null},
+                {"jsonrpc": "2.0", "result": 2, "id": 1}
+            ]))
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn handle_parsed_parallel() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[easy_jsonrpc::rpc]
+        trait Counter: Sync {
+            fn count(&self, add: usize) -> usize;
+        }
+
+        struct CounterImpl(AtomicUsize);
+        impl Counter for CounterImpl {
+            fn count(&self, add: usize) -> usize {
+                self.0.fetch_add(add, Ordering::SeqCst);
+                add
+            }
+        }
+
+        let counter = CounterImpl(AtomicUsize::new(0));
+        let handler = &counter as &dyn Counter;
+        let mut batch = (0..100)
+            .map(|i| json!({"jsonrpc": "2.0", "method": "count", "params": [i], "id": i}))
+            .collect::<Vec<_>>();
+        batch.push(json!({"jsonrpc": "2.0", "method": "count", "params": [1000]}));
+        let request = serde_json::from_value(Value::Array(batch)).unwrap();
+        let response = serde_json::to_value(handler.handle_parsed_parallel(request)).unwrap();
+        let expected = (0..100)
+            .map(|i| json!({"jsonrpc": "2.0", "result": i, "id": i}))
+            .collect::<Vec<_>>();
+        assert_eq!(response, Value::Array(expected));
+        assert_eq!(counter.0.load(Ordering::SeqCst), 4950 + 1000);
+
+        let notification = serde_json::from_value(json!({
+            "jsonrpc": "2.0",
+            "method": "count",
+            "params": [1]
+        }))
+        .unwrap();
+        assert_eq!(handler.handle_parsed_parallel(notification), None);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_spans() {
+        use std::fmt::Debug;
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id as SpanId, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        // records the fields of every span as "name=value" strings
+        #[derive(Clone, Default)]
+        struct Recorder(Arc<Mutex<Vec<Vec<String>>>>);
+
+        struct Fields<'a>(&'a mut Vec<String>);
+
+        impl Visit for Fields<'_> {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.0.push(format!("{}={:?}", field.name(), value));
+            }
+        }
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> SpanId {
+                let mut spans = self.0.lock().unwrap();
+                let mut fields = vec![span.metadata().name().to_string()];
+                span.record(&mut Fields(&mut fields));
+                spans.push(fields);
+                SpanId::from_u64(spans.len() as u64)
+            }
+            fn record(&self, span: &SpanId, values: &Record<'_>) {
+                let mut spans = self.0.lock().unwrap();
+                values.record(&mut Fields(&mut spans[span.into_u64() as usize - 1]));
+            }
+            fn record_follows_from(&self, _: &SpanId, _: &SpanId) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &SpanId) {}
+            fn exit(&self, _: &SpanId) {}
+        }
+
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let handler = &AdderImpl as &dyn Adder;
+            handler.handle_request(json!({
+                "jsonrpc": "2.0",
+                "method": "wrapping_add",
+                "params": [1, 1],
+                "id": 1
+            }));
+            handler.handle_request(json!({
+                "jsonrpc": "2.0",
+                "method": "wrapping_add",
+                "params": [1]
+            }));
+        });
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![
+                vec![
+                    "rpc_call",
+                    "method=\"wrapping_add\"",
+                    "notification=false",
+                    "success=true"
+                ],
+                vec![
+                    "rpc_call",
+                    "method=\"wrapping_add\"",
+                    "notification=true",
+                    "success=false",
+                    "error_code=-32602"
+                ],
+            ]
+        );
+    }
+
+    #[cfg(feature = "transport")]
+    #[test]
+    fn serve_lines() {
+        let input = concat!(
+            r#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2]}"#,
+            "\n\n",
+            "{\n",
+            r#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [3, 4], "id": 2}"#,
+        );
+        let mut output = Vec::new();
+        crate::server::serve(&AdderImpl as &dyn Adder, input.as_bytes(), &mut output).unwrap();
+        let replies = output
+            .split(|b| *b == b'\n')
+            .map(|line| serde_json::from_slice::<Value>(line).ok())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            replies,
+            vec![
+                Some(json!({"jsonrpc": "2.0", "result": 3, "id": 1})),
+                Some(json!({
+                    "jsonrpc": "2.0",
+                    "error": {"code": -32700, "message": "Parse error"},
+                    "id": null
+                })),
+                Some(json!({"jsonrpc": "2.0", "result": 7, "id": 2})),
+                None, // output ends with a newline
+            ]
+        );
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn websocket_session() {
+        use crate::server::ws;
+        use std::net::{TcpListener, TcpStream};
+        use tungstenite::Message;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (notifiers, notifier) = std::sync::mpsc::channel();
+        std::thread::scope(|scope| {
+            let server = scope.spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                let session = ws::accept(stream).unwrap();
+                notifiers.send(session.notifier()).unwrap();
+                session.run(&AdderImpl as &dyn Adder)
+            });
+            let (mut client, _) =
+                tungstenite::client("ws://localhost/", TcpStream::connect(addr).unwrap()).unwrap();
+            let read = |client: &mut tungstenite::WebSocket<TcpStream>| match client.read() {
+                Ok(Message::Text(text)) => serde_json::from_str::<Value>(&text).unwrap(),
+                other => panic!("unexpected message {:?}", other),
+            };
+
+            client.send(Message::Ping(vec![7])).unwrap();
+            assert_eq!(client.read().unwrap(), Message::Pong(vec![7]));
+            client
+                .send(Message::Text(
+                    r#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1}"#
+                        .into(),
+                ))
+                .unwrap();
+            client
+                .send(Message::Text(
+                    r#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2]}"#.into(),
+                ))
+                .unwrap();
+            assert_eq!(
+                read(&mut client),
+                json!({"jsonrpc": "2.0", "result": 3, "id": 1})
+            );
+
+            let notifier: ws::Notifier = notifier.recv().unwrap();
+            notifier
+                .notify(&super::BoundNotification::new("tick", vec![json!(1)]))
+                .unwrap();
+            assert_eq!(
+                read(&mut client),
+                json!({"jsonrpc": "2.0", "method": "tick", "params": [1]})
+            );
+
+            client.close(None).unwrap();
+            while client.read().is_ok() {}
+            server.join().unwrap().unwrap();
+            assert_eq!(
+                notifier.notify(&super::BoundNotification::new("tick", vec![])),
+                Err(ws::SessionClosed)
+            );
+        });
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn websocket_subscriptions() {
+        use crate::server::ws;
+        use crate::Subscription;
+        use std::net::{TcpListener, TcpStream};
+        use tungstenite::Message;
+
+        #[easy_jsonrpc::rpc]
+        trait Feed {
+            fn subscribe(&self, count: u64) -> Subscription<String>;
+        }
+
+        struct FeedImpl;
+        impl Feed for FeedImpl {
+            fn subscribe(&self, count: u64) -> Subscription<String> {
+                Subscription::new("feed.item", (0..count).map(|n| format!("item {}", n)))
+            }
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::scope(|scope| {
+            let server = scope.spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                ws::accept(stream).unwrap().run(&FeedImpl as &dyn Feed)
+            });
+            let (mut client, _) =
+                tungstenite::client("ws://localhost/", TcpStream::connect(addr).unwrap()).unwrap();
+            let read = |client: &mut tungstenite::WebSocket<TcpStream>| match client.read() {
+                Ok(Message::Text(text)) => serde_json::from_str::<Value>(&text).unwrap(),
+                other => panic!("unexpected message {:?}", other),
+            };
+
+            client
+                .send(Message::Text(
+                    r#"{"jsonrpc": "2.0", "method": "subscribe", "params": [2], "id": 1}"#.into(),
+                ))
+                .unwrap();
+            let reply = read(&mut client);
+            let id = reply["result"].clone();
+            assert!(id.is_u64());
+            // the reply comes first, then the items
+            for n in 0..2 {
+                assert_eq!(
+                    read(&mut client),
+                    json!({
+                        "jsonrpc": "2.0",
+                        "method": "feed.item",
+                        "params": {"subscription": id, "result": format!("item {}", n)}
+                    })
+                );
+            }
+
+            client.close(None).unwrap();
+            while client.read().is_ok() {}
+            server.join().unwrap().unwrap();
+        });
+    }
+
+    #[cfg(feature = "tower")]
+    #[test]
+    fn tower_service() {
+        use crate::server::tower::TowerService;
+        use bytes::Bytes;
+        use futures::executor::block_on;
+        use http::{Request, StatusCode};
+        use http_body_util::{BodyExt, Full};
+        use std::sync::Arc;
+        use tower_service::Service;
+
+        #[easy_jsonrpc::rpc]
+        trait Counter: Send + Sync {
+            fn double(&self, n: u64) -> u64;
+        }
+
+        struct CounterImpl;
+        impl Counter for CounterImpl {
+            fn double(&self, n: u64) -> u64 {
+                n * 2
+            }
+        }
+
+        let handler: Arc<dyn Counter> = Arc::new(CounterImpl);
+        let mut service = TowerService::new(handler);
+        let post = |body: &'static str| {
+            Request::post("/rpc")
+                .body(Full::new(Bytes::from(body)))
+                .unwrap()
+        };
+
+        let response = block_on(service.call(post(
+            r#"{"jsonrpc": "2.0", "method": "double", "params": [21], "id": 1}"#,
+        )))
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let body = block_on(response.into_body().collect()).unwrap().to_bytes();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&body).unwrap(),
+            json!({"jsonrpc": "2.0", "result": 42, "id": 1})
+        );
+
+        let notification = r#"{"jsonrpc": "2.0", "method": "double", "params": [21]}"#;
+        let response = block_on(service.call(post(notification))).unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(response.headers().get("content-type").is_none());
+
+        let mut service = service.no_reply_status(StatusCode::OK);
+        let response = block_on(service.call(post(notification))).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = block_on(response.into_body().collect()).unwrap().to_bytes();
+        assert!(body.is_empty());
+
+        let status = |service: &mut TowerService<dyn Counter>, body: &'static str| {
+            block_on(service.call(post(body))).unwrap().status()
+        };
+        let missing = r#"{"jsonrpc": "2.0", "method": "triple", "params": [1], "id": 2}"#;
+        assert_eq!(status(&mut service, "{"), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            status(&mut service, r#"{"id": 2}"#),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(status(&mut service, missing), StatusCode::OK);
+        assert_eq!(status(&mut service, "[{}]"), StatusCode::OK);
+        let mut service = service.status_for(|error| match error.code {
+            jsonrpc_core::ErrorCode::MethodNotFound => 404,
+            _ => 1000,
+        });
+        assert_eq!(status(&mut service, missing), StatusCode::NOT_FOUND);
+        assert_eq!(status(&mut service, "{"), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn version_field() {
+        use super::{Config, VersionField};
+
+        let handler = &AdderImpl as &dyn Adder;
+        let response = |request: &[u8], version_field: VersionField| {
+            handler
+                .handle_bytes_with(request, &Config::new().version_field(version_field))
+                .map(|bytes| serde_json::from_slice::<Value>(&bytes).unwrap())
+        };
+        let batch = br#"[
+            {"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1},
+            {"jsonrpc": "2.0", "method": "nope", "id": 2},
+            {"method": 5, "id": 3}
+        ]"#;
+        let outputs = |version: Option<&str>| {
+            let mut outputs = vec![
+                json!({"result": 3, "id": 1}),
+                json!({
+                    "error": {
+                        "code": -32601,
+                        "message": "Method not found",
+                        "data": "method 'nope' not found"
+                    },
+                    "id": 2
+                }),
+                json!({"error": {"code": -32600, "message": "Invalid request"}, "id": 3}),
+            ];
+            for output in outputs.iter_mut() {
+                if let Some(version) = version {
+                    output["jsonrpc"] = json!(version);
+                }
+            }
+            Value::Array(outputs)
+        };
+
+        let mut echoed = outputs(Some("2.0"));
+        echoed[2].as_object_mut().unwrap().remove("jsonrpc");
+        assert_eq!(response(batch, VersionField::Echo), Some(echoed));
+        assert_eq!(
+            response(batch, VersionField::V2),
+            Some(outputs(Some("2.0")))
+        );
+        assert_eq!(response(batch, VersionField::Omit), Some(outputs(None)));
+        assert_eq!(
+            response(b"{", VersionField::Omit),
+            Some(json!({"error": {"code": -32700, "message": "Parse error"}, "id": null}))
+        );
+        assert_eq!(
+            response(
+                br#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2]}"#,
+                VersionField::V2
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn capped_responses() {
+        use super::{Config, ResponseTooLarge};
+
+        #[easy_jsonrpc::rpc]
+        trait Bulk {
+            fn zeros(&self, len: usize) -> Vec<u8>;
+        }
+
+        struct BulkImpl;
+        impl Bulk for BulkImpl {
+            fn zeros(&self, len: usize) -> Vec<u8> {
+                vec![0; len]
+            }
+        }
+
+        let handler = &BulkImpl as &dyn Bulk;
+        let request = |len: usize| {
+            json!({"jsonrpc": "2.0", "method": "zeros", "params": [len], "id": 1}).to_string()
+        };
+        let small = br#"{"jsonrpc":"2.0","result":[0,0],"id":1}"#;
+        let capped = |request: &[u8], max_bytes| {
+            handler.handle_bytes_capped(request, &Config::new(), max_bytes)
+        };
+        assert_eq!(
+            capped(request(2).as_bytes(), small.len()),
+            Some(Ok(small.to_vec()))
+        );
+        assert_eq!(
+            capped(request(2).as_bytes(), small.len() - 1),
+            Some(Err(ResponseTooLarge {
+                max_bytes: small.len() - 1
+            }))
+        );
+        assert_eq!(
+            capped(request(1_000_000).as_bytes(), 1024),
+            Some(Err(ResponseTooLarge { max_bytes: 1024 }))
+        );
+        // error replies are capped as well, notifications need no reply at all
+        assert_eq!(
+            capped(b"{", 10),
+            Some(Err(ResponseTooLarge { max_bytes: 10 }))
+        );
+        assert_eq!(
+            capped(
+                br#"{"jsonrpc": "2.0", "method": "zeros", "params": [3]}"#,
+                0
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn max_depth() {
+        use super::Config;
+
+        let handler = &AdderImpl as &dyn Adder;
+        let limited = Config::new().max_depth(3);
+        let response = |request: &[u8], config: &Config| {
+            handler
+                .handle_bytes_with(request, config)
+                .map(|bytes| serde_json::from_slice::<Value>(&bytes).unwrap())
+        };
+        let deep = format!(
+            r#"{{"jsonrpc": "2.0", "method": "wrapping_add", "params": {}1{}, "id": 1}}"#,
+            "[".repeat(10_000),
+            "]".repeat(10_000)
+        );
+        let too_deep = json!({
+            "jsonrpc": "2.0",
+            "error": {
+                "code": -32602,
+                "message": "Invalid params",
+                "data": "request exceeds the maximum nesting depth of 3"
+            },
+            "id": null
+        });
+        assert_eq!(response(deep.as_bytes(), &limited), Some(too_deep));
+        // serde_json's own recursion limit makes such requests unparseable anyway
+        assert_eq!(
+            response(deep.as_bytes(), &Config::new()).unwrap()["error"]["code"],
+            json!(-32700)
+        );
+
+        let shallow = br#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1}"#;
+        assert_eq!(
+            response(shallow, &Config::new().max_depth(2)),
+            Some(json!({"jsonrpc": "2.0", "result": 3, "id": 1}))
+        );
+        // brackets within strings don't count
+        let brackets = br#"{"jsonrpc": "2.0", "method": "[[[[", "params": [1, "]]{{"], "id": 1}"#;
+        assert_eq!(
+            response(brackets, &Config::new().max_depth(2)).unwrap()["error"]["code"],
+            json!(-32601)
+        );
+        let batch = format!("[{}]", std::str::from_utf8(shallow).unwrap());
+        // the batch adds a level
+        assert_eq!(
+            response(batch.as_bytes(), &Config::new().max_depth(2)).unwrap()["error"]["data"],
+            json!("request exceeds the maximum nesting depth of 2")
+        );
+    }
+
+    #[test]
+    fn strict_keys() {
+        let handler = &AdderImpl as &dyn Adder;
+        let strict = super::Config::new().strict_keys(true);
+        let response = |request: &[u8], config: &super::Config| {
+            handler
+                .handle_bytes_with(request, config)
+                .map(|bytes| serde_json::from_slice::<Value>(&bytes).unwrap())
+        };
+        let duplicated =
+            br#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": {"a": 1, "b": 2, "a": 3}, "id": 1}"#;
+        assert_eq!(
+            response(duplicated, &super::Config::new()),
+            Some(json!({"jsonrpc": "2.0", "result": 5, "id": 1}))
+        );
+        assert_eq!(
+            response(duplicated, &strict),
+            Some(json!({
+                "jsonrpc": "2.0",
+                "error": {"code": -32602, "message": "DuplicateNamedParameter a"},
+                "id": 1
+            }))
+        );
+        assert_eq!(
+            response(
+                br#"[
+                    {"jsonrpc": "2.0", "method": "wrapping_add", "params": {"a": 1, "b": 1}, "id": 2},
+                    {"jsonrpc": "2.0", "method": "wrapping_add", "params": {"a": 1, "a": 1}},
+                    {"jsonrpc": "2.0", "method": "wrapping_add", "params": {"b": 1, "b": 1, "a": 1, "a": 1}, "id": 3}
+                ]"#,
+                &strict
+            ),
+            Some(json!([
+                {"jsonrpc": "2.0", "result": 2, "id": 2},
+                {
+                    "jsonrpc": "2.0",
+                    "error": {"code": -32602, "message": "DuplicateNamedParameter b, a"},
+                    "id": 3
+                }
+            ]))
+        );
+    }
+
+    #[test]
+    fn strict_version() {
+        let handler = &AdderImpl as &dyn Adder;
+        let strict = super::Config::new().strict_version(true);
+        let response = |request: &[u8], config: &super::Config| {
+            handler
+                .handle_bytes_with(request, config)
+                .map(|bytes| serde_json::from_slice::<Value>(&bytes).unwrap())
+        };
+        let absent = br#"{"method": "wrapping_add", "params": [1, 2], "id": 1}"#;
+        let wrong = br#"{"jsonrpc": "1.0", "method": "wrapping_add", "params": [1, 2], "id": 1}"#;
+        let invalid = json!({"error": {"code": -32600, "message": "Invalid request"}, "id": 1});
+
+        // by default calls without a version are handled as jsonrpc 1.0 calls
+        assert_eq!(
+            response(absent, &super::Config::new()),
+            Some(json!({"result": 3, "id": 1}))
+        );
+        assert_eq!(response(absent, &strict), Some(invalid.clone()));
+        assert_eq!(
+            response(wrong, &super::Config::new()),
+            Some(invalid.clone())
+        );
+        assert_eq!(response(wrong, &strict), Some(invalid));
+        assert_eq!(
+            response(
+                br#"[
+                    {"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 1], "id": 2},
+                    {"method": "wrapping_add", "params": [1, 1], "id": 3},
+                    {"method": "wrapping_add", "params": [1, 1]}
+                ]"#,
+                &strict
+            ),
+            Some(json!([
+                {"jsonrpc": "2.0", "result": 2, "id": 2},
+                {"error": {"code": -32600, "message": "Invalid request"}, "id": 3}
+            ]))
+        );
+        // strict keys are still checked along with the version
+        assert_eq!(
+            response(
+                br#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": {"a": 1, "a": 1}, "id": 4}"#,
+                &strict.strict_keys(true)
+            ),
+            Some(json!({
+                "jsonrpc": "2.0",
+                "error": {"code": -32602, "message": "DuplicateNamedParameter a"},
+                "id": 4
+            }))
+        );
+    }
+
+    #[test]
+    fn max_batch_size() {
+        let handler = &AdderImpl as &dyn Adder;
+        let limited = super::Config::new().max_batch_size(2);
+        let response = |request: &[u8]| {
+            handler
+                .handle_bytes_with(request, &limited)
+                .map(|bytes| serde_json::from_slice::<Value>(&bytes).unwrap())
+        };
+        let call = r#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 1], "id": 1}"#;
+        assert_eq!(
+            response(format!("[{}, {}]", call, call).as_bytes()),
+            Some(json!([
+                {"jsonrpc": "2.0", "result": 2, "id": 1},
+                {"jsonrpc": "2.0", "result": 2, "id": 1}
+            ]))
+        );
+        assert_eq!(
+            response(format!("[{}, {}, {}]", call, call, call).as_bytes()),
+            Some(json!({
+                "jsonrpc": "2.0",
+                "error": {
+                    "code": -32600,
+                    "message": "Invalid request",
+                    "data": "batch exceeds the maximum of 2 calls"
+                },
+                "id": null
+            }))
+        );
+        assert_eq!(
+            response(call.as_bytes()),
+            Some(json!({"jsonrpc": "2.0", "result": 2, "id": 1}))
+        );
+    }
+
+    #[test]
+    fn method_docs() {
+        #[easy_jsonrpc::rpc]
+        trait Base {
+            /// Says hello.
+            fn hello(&self) -> String;
+        }
+
+        #[easy_jsonrpc::rpc(supertraits(Base))]
+        trait Help: Base {
+            /// Adds two numbers.
+            ///
+            /// Wraps around on overflow.
+            fn wrapping_add(&self, a: isize, b: isize) -> isize;
+            #[doc = "Negates a number."]
+            #[rpc(name = "neg")]
+            fn negate(&self, a: isize) -> isize;
+            fn undocumented(&self);
+        }
+
+        assert_eq!(
+            help::method_doc("wrapping_add"),
+            Some("Adds two numbers.\n\nWraps around on overflow.")
+        );
+        assert_eq!(help::method_doc("neg"), Some("Negates a number."));
+        assert_eq!(help::method_doc("negate"), None);
+        assert_eq!(help::method_doc("undocumented"), None);
+        assert_eq!(help::method_doc("hello"), Some("Says hello."));
+        assert_eq!(help::method_doc("missing"), None);
+    }
+
+    #[test]
+    fn idempotent_methods() {
+        #[easy_jsonrpc::rpc]
+        trait Base {
+            #[rpc(idempotent)]
+            fn get(&self, key: String) -> Option<String>;
+        }
+
+        #[easy_jsonrpc::rpc(supertraits(Base))]
+        trait Store: Base {
+            #[rpc(idempotent, params_struct)]
+            fn put(&self, key: String, value: String);
+            fn append(&self, key: String, value: String);
+        }
+
+        assert!(base::get("k".into()).unwrap().is_idempotent());
+        assert!(store::put("k".into(), "v".into()).unwrap().is_idempotent());
+        assert!(!store::append("k".into(), "v".into())
+            .unwrap()
+            .is_idempotent());
+        let put = PutParams {
+            key: "k".into(),
+            value: "v".into(),
+        };
+        assert!(put.request().unwrap().is_idempotent());
+
+        assert!(store::is_idempotent("put"));
+        assert!(store::is_idempotent("get"));
+        assert!(!store::is_idempotent("append"));
+        assert!(!store::is_idempotent("missing"));
+        assert!(!base::is_idempotent("put"));
+    }
+
+    #[test]
+    fn deprecated_methods() {
+        #[easy_jsonrpc::rpc]
+        trait Base {
+            #[rpc(deprecated)]
+            fn old(&self) -> u64;
+        }
+
+        #[easy_jsonrpc::rpc(describe, openrpc, request_enum, supertraits(Base))]
+        trait Math: Base {
+            #[rpc(deprecated = "use v2_add instead")]
+            fn add(&self, a: u64, b: u64) -> u64;
+            fn v2_add(&self, a: u64, b: u64) -> u64;
+        }
+
+        struct MathImpl;
+        impl Base for MathImpl {
+            fn old(&self) -> u64 {
+                0
+            }
+        }
+        impl Math for MathImpl {
+            fn add(&self, a: u64, b: u64) -> u64 {
+                a + b
+            }
+
+            fn v2_add(&self, a: u64, b: u64) -> u64 {
+                a + b
+            }
+        }
+
+        assert_eq!(math::deprecation("add"), Some("use v2_add instead"));
+        assert_eq!(math::deprecation("old"), Some(""));
+        assert_eq!(math::deprecation("v2_add"), None);
+        assert_eq!(math::deprecation("missing"), None);
+        let descriptors = math::rpc_descriptors();
+        assert_eq!(descriptors[0].deprecated, Some("use v2_add instead"));
+        assert_eq!(descriptors[1].deprecated, None);
+        assert_eq!(descriptors[2].deprecated, Some(""));
+
+        // deprecated methods are still dispatched
+        let handler = &MathImpl as &dyn Math;
+        for _ in 0..2 {
+            assert_eq!(
+                handler.handle_request(
+                    json!({"jsonrpc": "2.0", "method": "add", "params": [1, 2], "id": 1})
+                ),
+                MaybeReply::Reply(json!({"jsonrpc": "2.0", "result": 3, "id": 1}))
+            );
+        }
+        assert_eq!(
+            handler.handle_request(json!({"jsonrpc": "2.0", "method": "old", "id": 1})),
+            MaybeReply::Reply(json!({"jsonrpc": "2.0", "result": 0, "id": 1}))
+        );
+        assert_eq!(
+            MathRequest::Add { a: 2, b: 3 }.dispatch(handler),
+            Ok(json!(5))
+        );
+
+        let MaybeReply::Reply(described) =
+            handler.handle_request(json!({"jsonrpc": "2.0", "method": "rpc.describe", "id": 1}))
+        else {
+            panic!("rpc.describe is answered");
+        };
+        assert_eq!(
+            described["result"][0],
+            json!({
+                "name": "add",
+                "params": [{"name": "a", "type": "u64"}, {"name": "b", "type": "u64"}],
+                "deprecated": "use v2_add instead",
+            })
+        );
+        assert_eq!(
+            described["result"][1],
+            json!({
+                "name": "v2_add",
+                "params": [{"name": "a", "type": "u64"}, {"name": "b", "type": "u64"}],
+            })
+        );
+
+        let schema = math::openrpc_schema();
+        assert_eq!(schema["methods"][0]["deprecated"], json!(true));
+        assert_eq!(
+            schema["methods"][0]["description"],
+            json!("use v2_add instead")
+        );
+        assert_eq!(schema["methods"][1].get("deprecated"), None);
+    }
+
+    #[test]
+    fn integer_precision() {
+        #[easy_jsonrpc::rpc]
+        trait Numbers {
+            fn unsigned(&self, a: u64) -> u64;
+            fn signed(&self, a: i64) -> i64;
+            fn wide(&self, a: u128) -> u128;
+        }
+
+        struct NumbersImpl;
+        impl Numbers for NumbersImpl {
+            fn unsigned(&self, a: u64) -> u64 {
+                a
+            }
+
+            fn signed(&self, a: i64) -> i64 {
+                a
+            }
+
+            fn wide(&self, a: u128) -> u128 {
+                a
+            }
+        }
+
+        // parse from bytes as a server would, rather than building a Value by hand
+        let handler = &NumbersImpl as &dyn Numbers;
+        let call = |method: &str, arg: &str| {
+            let request = format!(
+                r#"{{"jsonrpc": "2.0", "method": "{}", "params": [{}], "id": 1}}"#,
+                method, arg
+            );
+            let reply = handler.handle_bytes(request.as_bytes()).unwrap();
+            let reply: Value = serde_json::from_slice(&reply).unwrap();
+            reply["result"].to_string()
+        };
+        assert_eq!(
+            call("unsigned", "18446744073709551615"),
+            "18446744073709551615"
+        );
+        assert_eq!(call("unsigned", "9007199254740993"), "9007199254740993");
+        assert_eq!(
+            call("signed", "-9223372036854775808"),
+            "-9223372036854775808"
+        );
+        assert_eq!(call("signed", "9223372036854775807"), "9223372036854775807");
+        #[cfg(feature = "arbitrary_precision")]
+        assert_eq!(
+            call("wide", "340282366920938463463374607431768211455"),
+            "340282366920938463463374607431768211455"
+        );
+    }
+
+    #[test]
+    fn error_codes() {
+        use std::fmt;
+
+        #[derive(easy_jsonrpc::RpcErrorCode)]
+        enum WalletError {
+            #[rpc_error(code = -32010, label = "Insufficient funds")]
+            InsufficientFunds { needed: u64 },
+            #[rpc_error(code = -32011)]
+            Locked,
+            #[rpc_error(label = "Busy", code = 7)]
+            Busy(u8),
+        }
+
+        impl fmt::Display for WalletError {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match self {
+                    WalletError::InsufficientFunds { needed } => write!(f, "{} needed", needed),
+                    WalletError::Locked => f.write_str("locked"),
+                    WalletError::Busy(retry) => write!(f, "retry in {}", retry),
+                }
+            }
+        }
+
+        #[easy_jsonrpc::rpc]
+        trait Base {
+            fn version(&self) -> Result<String, String>;
+        }
+
+        #[easy_jsonrpc::rpc(supertraits(Base))]
+        trait Wallet: Base {
+            fn send(&self, amount: u64) -> Result<(), WalletError>;
+            fn unlock(&self) -> Result<(), WalletError>;
+            fn raw(&self) -> Result<(), super::Error>;
+        }
+
+        assert_eq!(
+            wallet::error_codes(),
+            vec![
+                (-32011, "Locked"),
+                (-32010, "Insufficient funds"),
+                (super::APPLICATION_ERROR, "Application error"),
+                (7, "Busy"),
+            ]
+        );
+        assert_eq!(
+            base::error_codes(),
+            vec![(super::APPLICATION_ERROR, "Application error")]
+        );
+
+        let error = super::IntoRpcError::into_error(WalletError::InsufficientFunds { needed: 1 });
+        assert_eq!(error.code, super::ErrorCode::ServerError(-32010));
+        assert_eq!(error.message, "1 needed");
+        assert_eq!(error.data, None);
+        let error = super::IntoRpcError::into_error(WalletError::Busy(0));
+        assert_eq!(error.code, super::ErrorCode::ServerError(7));
+        assert_eq!(
+            super::IntoRpcError::into_error(WalletError::Locked).code,
+            super::ErrorCode::ServerError(-32011)
+        );
+    }
+
+    #[test]
+    fn notification_results() {
+        use std::sync::Mutex;
+
+        // observes the notifications handled by an Adder
+        struct Observed {
+            results: Mutex<Vec<(String, Result<Value, super::Error>)>>,
+        }
+
+        impl Handler for Observed {
+            fn handle(&self, method: &str, params: Params) -> Result<Value, super::Error> {
+                (&AdderImpl as &dyn Adder).handle(method, params)
+            }
+
+            fn methods(&self) -> Vec<&'static str> {
+                (&AdderImpl as &dyn Adder).methods()
+            }
+
+            fn on_notification_result(&self, method: &str, result: Result<Value, super::Error>) {
+                self.results
+                    .lock()
+                    .unwrap()
+                    .push((method.to_owned(), result));
+            }
+        }
+
+        let observed = Observed {
+            results: Mutex::new(Vec::new()),
+        };
+        let reply = observed.handle_request(json!([
+            {"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2]},
+            {"jsonrpc": "2.0", "method": "fail", "params": []},
+            {"jsonrpc": "2.0", "method": "wrapping_add", "params": [3, 4], "id": 1},
+        ]));
+        // only calls with an id are answered, or observed as notifications
+        assert_eq!(
+            reply,
+            MaybeReply::Reply(json!([{"jsonrpc": "2.0", "result": 7, "id": 1}]))
+        );
+        assert!(observed
+            .handle_bytes(br#"{"jsonrpc": "2.0", "method": "missing"}"#)
+            .is_none());
+        let results = observed.results.lock().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0], ("wrapping_add".to_owned(), Ok(json!(3))));
+        assert_eq!(results[1].0, "fail");
+        assert_eq!(results[1].1.as_ref().unwrap_err().message, "tada!");
+        assert_eq!(
+            results[2],
+            (
+                "missing".to_owned(),
+                Err(super::method_not_found("missing"))
+            )
+        );
+    }
+
+    #[test]
+    fn compact_notifications() {
+        use std::collections::HashMap;
+
+        #[easy_jsonrpc::rpc]
+        trait Cache {
+            fn snapshot(&self) -> HashMap<(u8, u8), u8>;
+            #[rpc(with_id)]
+            fn whoami(&self, id: super::Id) -> super::Id;
+        }
+
+        struct CacheImpl;
+        impl Cache for CacheImpl {
+            // tuple keys can't be serialized as json
+            fn snapshot(&self) -> HashMap<(u8, u8), u8> {
+                vec![((1, 2), 3)].into_iter().collect()
+            }
+            fn whoami(&self, id: super::Id) -> super::Id {
+                id
+            }
+        }
+
+        let handler = &CacheImpl as &dyn Cache;
+        let params = || Params::Positional(vec![]);
+        assert_eq!(
+            handler.handle("snapshot", params()).unwrap_err().message,
+            "Serialization error"
+        );
+        assert_eq!(
+            handler.handle_notification("snapshot", params()),
+            Ok(Value::Null)
+        );
+        assert_eq!(
+            handler.handle_notification("whoami", params()),
+            Ok(Value::Null)
+        );
+        assert_eq!(
+            handler
+                .handle_notification("snapshot", Params::Positional(vec![json!(1)]))
+                .unwrap_err()
+                .code,
+            super::ErrorCode::InvalidParams
+        );
+        assert_eq!(
+            handler.handle_notification("missing", params()),
+            Err(super::method_not_found("missing"))
+        );
+        assert_eq!(
+            (&AdderImpl as &dyn Adder).handle_notification("fail", params()),
+            Err(super::Error {
+                code: super::ErrorCode::ServerError(super::APPLICATION_ERROR),
+                message: "tada!".into(),
+                data: None,
+            })
+        );
+    }
+
+    #[test]
+    fn flattened_args() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Serialize, Deserialize)]
+        struct CreateRequest {
+            name: String,
+            #[serde(default)]
+            size: u64,
+        }
+
+        #[easy_jsonrpc::rpc]
+        trait Volumes {
+            fn create(&self, #[rpc(flatten)] req: CreateRequest) -> String;
+            #[rpc(params = "positional")]
+            fn create_positional(&self, #[rpc(flatten)] req: CreateRequest) -> String;
+        }
+
+        struct VolumesImpl;
+        impl Volumes for VolumesImpl {
+            fn create(&self, req: CreateRequest) -> String {
+                format!("{}:{}", req.name, req.size)
+            }
+
+            fn create_positional(&self, req: CreateRequest) -> String {
+                self.create(req)
+            }
+        }
+
+        let handler = &VolumesImpl as &dyn Volumes;
+        let call = |method: &str, params: Value| {
+            handler.handle_request(
+                json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1}),
+            )
+        };
+        let result =
+            |result: &str| MaybeReply::Reply(json!({"jsonrpc": "2.0", "result": result, "id": 1}));
+        assert_eq!(
+            call("create", json!({"name": "data", "size": 10})),
+            result("data:10")
+        );
+        assert_eq!(call("create", json!({"name": "logs"})), result("logs:0"));
+        assert_eq!(
+            call("create", json!([{"name": "data", "size": 10}])),
+            result("data:10")
+        );
+        assert_eq!(
+            call("create", json!({"size": 10})),
+            MaybeReply::Reply(json!({
+                "jsonrpc": "2.0",
+                "error": {
+                    "code": -32602,
+                    "message": "InvalidArgStructure req at position 0: missing field `name`"
+                },
+                "id": 1
+            }))
+        );
+
+        // client helpers send the fields as named parameters, unless positional ones are required
+        let create = volumes::create(CreateRequest {
+            name: "data".into(),
+            size: 1,
+        })
+        .unwrap();
+        let (call, tracker) = create.call();
+        let request = call.as_request();
+        assert_eq!(request["params"], json!({"name": "data", "size": 1}));
+        let mut response = super::Response::from_json_response(
+            handler.handle_request(request).as_option().unwrap().clone(),
+        )
+        .unwrap();
+        assert_eq!(tracker.get_return(&mut response), Ok("data:1".to_owned()));
+        let create = volumes::create_positional(CreateRequest {
+            name: "data".into(),
+            size: 2,
+        })
+        .unwrap();
+        let request = create.call().0.as_request();
+        assert_eq!(request["params"], json!([{"name": "data", "size": 2}]));
+        assert_eq!(
+            handler.handle_request(request).as_option().unwrap()["result"],
+            json!("data:2")
+        );
+        assert_eq!(
+            volumes::notify_create(CreateRequest {
+                name: "data".into(),
+                size: 3,
+            })
+            .unwrap()
+            .as_request()["params"],
+            json!({"name": "data", "size": 3})
+        );
+    }
+
+    #[test]
+    fn permissive_args() {
+        use serde_json::Map;
+
+        #[easy_jsonrpc::rpc]
+        trait Options {
+            fn map(&self, opts: Map<String, Value>) -> Value;
+            fn value(&self, v: Value) -> Value;
+            fn flat_map(&self, #[rpc(flatten)] opts: Map<String, Value>) -> Value;
+            fn flat_value(&self, #[rpc(flatten)] v: Value) -> Value;
+        }
+
+        struct OptionsImpl;
+        impl Options for OptionsImpl {
+            fn map(&self, opts: Map<String, Value>) -> Value {
+                Value::Object(opts)
+            }
+
+            fn value(&self, v: Value) -> Value {
+                v
+            }
+
+            fn flat_map(&self, opts: Map<String, Value>) -> Value {
+                Value::Object(opts)
+            }
+
+            fn flat_value(&self, v: Value) -> Value {
+                v
+            }
+        }
+
+        let handler = &OptionsImpl as &dyn Options;
+        let call = |method: &str, params: Option<Value>| {
+            let mut request = json!({"jsonrpc": "2.0", "method": method, "id": 1});
+            if let Some(params) = params {
+                request["params"] = params;
+            }
+            let reply = handler.handle_request(request).as_option().unwrap().clone();
+            match reply.get("result") {
+                Some(result) => Ok(result.clone()),
+                None => Err(reply["error"]["message"].as_str().unwrap().to_owned()),
+            }
+        };
+
+        // without flatten, the argument is a single parameter, whatever its shape
+        assert_eq!(call("map", Some(json!([{"a": 1}]))), Ok(json!({"a": 1})));
+        assert_eq!(
+            call("map", Some(json!({"opts": {"a": 1}}))),
+            Ok(json!({"a": 1}))
+        );
+        assert_eq!(
+            call("map", Some(json!({"a": 1}))),
+            Err("MissingNamedParameter opts".to_owned())
+        );
+        assert_eq!(
+            call("map", Some(json!(["a"]))),
+            Err(
+                "InvalidArgStructure opts at position 0: invalid type: string \"a\", expected a map"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            call("map", Some(json!([{}, {}]))),
+            Err("WrongNumberOfArgs. Expected 1. Actual 2".to_owned())
+        );
+        assert_eq!(call("value", Some(json!([1]))), Ok(json!(1)));
+        assert_eq!(call("value", Some(json!([[1, 2]]))), Ok(json!([1, 2])));
+        assert_eq!(call("value", Some(json!({"v": null}))), Ok(json!(null)));
+
+        // flattened, the named parameters as a whole are the argument
+        for method in &["flat_map", "flat_value"] {
+            assert_eq!(
+                call(method, Some(json!({"a": 1, "b": [2]}))),
+                Ok(json!({"a": 1, "b": [2]}))
+            );
+            assert_eq!(
+                call(method, Some(json!({"opts": {"a": 1}}))),
+                Ok(json!({"opts": {"a": 1}}))
+            );
+            assert_eq!(call(method, Some(json!([{"a": 1}]))), Ok(json!({"a": 1})));
+            assert_eq!(call(method, Some(json!({}))), Ok(json!({})));
+            assert_eq!(call(method, Some(json!([]))), Ok(json!({})));
+            assert_eq!(call(method, None), Ok(json!({})));
+        }
+        assert_eq!(call("flat_value", Some(json!([1]))), Ok(json!(1)));
+        assert!(call("flat_map", Some(json!([1]))).is_err());
+
+        // client helpers send a flattened dictionary as the named parameters
+        let mut opts = Map::new();
+        opts.insert("a".into(), json!(1));
+        let request = options::flat_map(opts.clone())
+            .unwrap()
+            .call()
+            .0
+            .as_request();
+        assert_eq!(request["params"], json!({"a": 1}));
+        let request = options::map(opts).unwrap().call().0.as_request();
+        assert_eq!(request["params"], json!([{"a": 1}]));
+    }
+
+    #[test]
+    fn rest_args() {
+        use std::collections::BTreeMap;
+
+        #[easy_jsonrpc::rpc]
+        trait Plugins {
+            fn call(&self, name: String, #[rpc(rest)] extra: BTreeMap<String, Value>) -> Value;
+            #[rpc(params = "named")]
+            fn call_named(
+                &self,
+                name: String,
+                #[rpc(rest)] extra: BTreeMap<String, Value>,
+            ) -> Value;
+        }
+
+        struct PluginsImpl;
+        impl Plugins for PluginsImpl {
+            fn call(&self, name: String, extra: BTreeMap<String, Value>) -> Value {
+                json!([name, extra])
+            }
+
+            fn call_named(&self, name: String, extra: BTreeMap<String, Value>) -> Value {
+                self.call(name, extra)
+            }
+        }
+
+        let handler = &PluginsImpl as &dyn Plugins;
+        let call = |method: &str, params: Value| {
+            handler
+                .handle_request(
+                    json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1}),
+                )
+                .as_option()
+                .unwrap()["result"]
+                .clone()
+        };
+        assert_eq!(
+            call("call", json!({"name": "resize", "width": 10, "height": 20})),
+            json!(["resize", {"width": 10, "height": 20}])
+        );
+        assert_eq!(call("call", json!({"name": "noop"})), json!(["noop", {}]));
+        assert_eq!(
+            call("call", json!(["resize", {"width": 10}])),
+            json!(["resize", {"width": 10}])
+        );
+        assert_eq!(call("call", json!(["noop"])), json!(["noop", {}]));
+
+        // named client helpers send the extras alongside the other arguments
+        let extra = vec![("width".to_owned(), json!(10))].into_iter().collect();
+        let request = plugins::call_named("resize".into(), extra)
+            .unwrap()
+            .call()
+            .0
+            .as_request();
+        assert_eq!(request["params"], json!({"name": "resize", "width": 10}));
+        assert_eq!(
+            handler.handle_request(request).as_option().unwrap()["result"],
+            json!(["resize", {"width": 10}])
+        );
+    }
+
+    #[test]
+    fn shorthand_requests() {
+        let handler = &AdderImpl as &dyn Adder;
+        let reply = |raw: &str| {
+            handler
+                .handle_raw_lenient(raw)
+                .map(|reply| serde_json::from_str::<Value>(&reply).unwrap())
+        };
+        assert_eq!(
+            reply(r#" ["wrapping_add", 1, 2] "#),
+            Some(json!({"jsonrpc": "2.0", "result": 3, "id": null}))
+        );
+        assert_eq!(
+            reply(r#"["greet"]"#),
+            Some(json!({"jsonrpc": "2.0", "result": "hello", "id": null}))
+        );
+        assert_eq!(
+            reply(r#"["wrapping_add", 1]"#).unwrap()["error"]["code"],
+            json!(-32602)
+        );
+        // regular requests are unaffected
+        assert_eq!(
+            reply(r#"[{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1}]"#),
+            Some(json!([{"jsonrpc": "2.0", "result": 3, "id": 1}]))
+        );
+        assert_eq!(
+            reply(r#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2]}"#),
+            None
+        );
+        assert_eq!(reply("[1, 2]").unwrap().as_array().unwrap().len(), 2);
+        assert_eq!(reply("[").unwrap()["error"]["code"], json!(-32700));
+    }
+
+    #[test]
+    fn parse_errors() {
+        use std::sync::Mutex;
+
+        // records the malformed input given to an Adder
+        struct Logged {
+            errors: Mutex<Vec<(String, String)>>,
+        }
+
+        impl Handler for Logged {
+            fn handle(&self, method: &str, params: Params) -> Result<Value, super::Error> {
+                (&AdderImpl as &dyn Adder).handle(method, params)
+            }
+
+            fn on_parse_error(&self, raw: &str, err: &serde_json::Error) {
+                self.errors
+                    .lock()
+                    .unwrap()
+                    .push((raw.to_owned(), err.to_string()));
+            }
+        }
+
+        let logged = Logged {
+            errors: Mutex::new(Vec::new()),
+        };
+        let take = || std::mem::take(&mut *logged.errors.lock().unwrap());
+
+        assert!(logged.handle_bytes(b"{\"jsonrpc\": ").is_some());
+        let errors = take();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "{\"jsonrpc\": ");
+        assert!(errors[0].1.contains("EOF"), "{}", errors[0].1);
+
+        // well formed calls in the same batch are not reported
+        let reply = logged.handle_request(json!([
+            {"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1},
+            {"jsonrpc": "2.0", "params": [1, 2], "id": 2},
+            {"jsonrpc": "2.0", "method": "wrapping_add", "id": {}},
+        ]));
+        assert_eq!(
+            reply,
+            MaybeReply::Reply(json!([
+                {"jsonrpc": "2.0", "result": 3, "id": 1},
+                {"error": {"code": -32600, "message": "Invalid request"}, "id": 2},
+                {"error": {"code": -32600, "message": "Invalid request"}, "id": null},
+            ]))
+        );
+        let errors = take();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            serde_json::from_str::<Value>(&errors[0].0).unwrap(),
+            json!({"jsonrpc": "2.0", "params": [1, 2], "id": 2})
+        );
+        assert!(errors[0].1.contains("method"), "{}", errors[0].1);
+        assert_eq!(
+            serde_json::from_str::<Value>(&errors[1].0).unwrap(),
+            json!({"jsonrpc": "2.0", "method": "wrapping_add", "id": {}})
+        );
+
+        assert!(logged.handle_bytes(b"12").is_some());
+        assert_eq!(take().len(), 1);
+        // nothing gets reported for valid requests
+        logged.handle_bytes(br#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2]}"#);
+        assert!(take().is_empty());
+    }
+
+    #[test]
+    fn request_enum() {
+        #[easy_jsonrpc::rpc(request_enum)]
+        trait Api {
+            fn wrapping_add(&self, a: isize, b: isize) -> isize;
+            fn greet(&self) -> String;
+            #[rpc(name = "echo")]
+            fn echo_str(
+                &self,
+                #[rpc(rename = "msg")] message: &str,
+                #[rpc(default)] loud: bool,
+            ) -> String;
+            fn fail(&self) -> Result<(), String>;
+        }
+
+        struct ApiImpl;
+        impl Api for ApiImpl {
+            fn wrapping_add(&self, a: isize, b: isize) -> isize {
+                a.wrapping_add(b)
+            }
+
+            fn greet(&self) -> String {
+                "hello".into()
+            }
+
+            fn echo_str(&self, message: &str, loud: bool) -> String {
+                if loud {
+                    message.to_uppercase()
+                } else {
+                    message.to_owned()
+                }
+            }
+
+            fn fail(&self) -> Result<(), String> {
+                Err("failed".into())
+            }
+        }
+
+        let handler = &ApiImpl as &dyn Api;
+        let request = ApiRequest::WrappingAdd { a: 1, b: 2 };
+        assert_eq!(request.method(), "wrapping_add");
+        assert_eq!(request.dispatch(handler), Ok(json!(3)));
+        assert_eq!(ApiRequest::Greet {}.dispatch(handler), Ok(json!("hello")));
+        assert_eq!(
+            ApiRequest::Fail {}.dispatch(handler).unwrap_err().message,
+            "failed"
+        );
+
+        // params are parsed once, the way the generated handler parses them
+        let parse = |method: &str, params: Value| {
+            let params = match params {
+                Value::Array(ar) => Params::Positional(ar),
+                Value::Object(ma) => Params::Named(ma),
+                _ => unreachable!(),
+            };
+            ApiRequest::parse(method, params)
+        };
+        let request = parse("echo", json!({"msg": "hi", "loud": true})).unwrap();
+        assert_eq!(request.method(), "echo");
+        assert_eq!(request.dispatch(handler), Ok(json!("HI")));
+        let request = parse("echo", json!(["hi"])).unwrap();
+        assert_eq!(request.dispatch(handler), Ok(json!("hi")));
+        match parse("wrapping_add", json!([4, 5])).unwrap() {
+            ApiRequest::WrappingAdd { a, b } => assert_eq!((a, b), (4, 5)),
+            _ => panic!("expected wrapping_add"),
+        }
+        assert_eq!(parse("greet", json!([])).unwrap().method(), "greet");
+        assert_eq!(
+            parse("missing", json!([])).map(|r| r.method()),
+            Err(super::method_not_found("missing"))
+        );
+        assert_eq!(
+            parse("wrapping_add", json!([1]))
+                .map(|r| r.method())
+                .unwrap_err()
+                .code,
+            super::ErrorCode::InvalidParams
+        );
+        assert!(parse("echo", json!({"message": "hi"})).is_err());
+
+        // both ends can share the enum, it serializes as the method name and params
+        assert_eq!(
+            serde_json::to_value(ApiRequest::WrappingAdd { a: 1, b: 2 }).unwrap(),
+            json!({"method": "wrapping_add", "params": {"a": 1, "b": 2}})
+        );
+    }
+
+    #[test]
+    fn batch_builder() {
+        use super::{BatchBuilder, BatchResult, ResponseFail};
+
+        let handler = &AdderImpl as &dyn Adder;
+        let mut batch = BatchBuilder::new();
+        let sum = batch.call(&adder::wrapping_add(1, 2).unwrap());
+        batch.notify(&adder::notify_wrapping_add(0, 0).unwrap());
+        let checked = batch.call(&adder::checked_add(isize::MAX, 1).unwrap());
+        let missing = batch.call(&super::BoundMethod::<u8>::new("missing", vec![]));
+        let request = batch.build();
+        match &request {
+            super::types::Request::Batch(calls) => assert_eq!(calls.len(), 4),
+            _ => panic!("expected a batch"),
+        }
+
+        // ids are unique within the batch
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json[0]["id"], json!(0));
+        assert_eq!(json[1].get("id"), None);
+        assert_eq!(json[2]["id"], json!(1));
+        assert_eq!(json[3]["id"], json!(2));
+
+        let response = handler.handle_parsed(request).unwrap();
+        let mut result = BatchResult::new(response).unwrap();
+        assert_eq!(result.get(&checked), Ok(None));
+        assert_eq!(result.get(&sum), Ok(3));
+        assert_eq!(
+            result.get(&missing),
+            Err(ResponseFail::RpcError(super::method_not_found("missing")))
+        );
+        // each result can only be taken once
+        assert_eq!(result.get(&sum), Err(ResponseFail::ResultNotFound));
+
+        let response = handler.handle_request(json).as_option().unwrap().clone();
+        assert_eq!(
+            BatchResult::from_json_response(response).unwrap().get(&sum),
+            Ok(3)
+        );
+    }
+
+    #[test]
+    fn id_generators() {
+        use super::{BatchBuilder, BatchResult, IdGenerator, MonotonicIds, Response};
+
+        let ids = MonotonicIds::default();
+        assert_eq!(ids.next(), Id::Num(0));
+        assert_eq!(ids.next(), Id::Num(1));
+
+        let handler = &AdderImpl as &dyn Adder;
+        let uuids = || Id::Str("6f1c0b5e-0d7a-4d56-9a3e-2c1f4b8e9d01".into());
+        let method = adder::wrapping_add(1, 2).unwrap();
+        let (call, tracker) = method.call_with(&uuids);
+        let request = call.as_request();
+        assert_eq!(request["id"], json!("6f1c0b5e-0d7a-4d56-9a3e-2c1f4b8e9d01"));
+        let response = handler.handle_request(request).as_option().unwrap().clone();
+        let mut response = Response::from_json_response(response).unwrap();
+        assert_eq!(tracker.get_return(&mut response), Ok(3));
+
+        let (method_call, _) = method.method_call_with(&ids);
+        assert_eq!(method_call.id, Id::Num(2));
+
+        let count = std::cell::Cell::new(0);
+        let mut batch = BatchBuilder::with_id_generator(move || {
+            count.set(count.get() + 1);
+            Id::Str(format!("req-{}", count.get()))
+        });
+        let sum = batch.call(&method);
+        let some = batch.call(&adder::checked_add(1, 1).unwrap());
+        let request = batch.build();
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json[0]["id"], json!("req-1"));
+        assert_eq!(json[1]["id"], json!("req-2"));
+        let mut result = BatchResult::new(handler.handle_parsed(request).unwrap()).unwrap();
+        assert_eq!(result.get(&some), Ok(Some(2)));
+        assert_eq!(result.get(&sum), Ok(3));
+    }
+
+    #[test]
+    fn method_not_found_names_method() {
+        let handler = &AdderImpl as &dyn Adder;
+        assert_eq!(
+            handler.handle_request(
+                json!({"jsonrpc": "2.0", "method": "wrappingadd", "params": [1, 2], "id": 1})
+            ),
+            MaybeReply::Reply(json!({
+                "jsonrpc": "2.0",
+                "error": {
+                    "code": -32601,
+                    "message": "Method not found",
+                    "data": "method 'wrappingadd' not found"
+                },
+                "id": 1
+            }))
+        );
+    }
+
+    #[test]
+    fn ids_round_trip() {
+        let handler = &AdderImpl as &dyn Adder;
+        let ids = [
+            json!("abc"),
+            json!("1"),
+            json!(""),
+            json!(0),
+            json!(u64::MAX),
+        ];
+        for id in ids.iter() {
+            let call = |method: &str, params: Value| json!({"jsonrpc": "2.0", "method": method, "params": params, "id": id});
+            let success = json!({"jsonrpc": "2.0", "result": 3, "id": id});
+            let failure = json!({
+                "jsonrpc": "2.0",
+                "error": {
+                    "code": -32601,
+                    "message": "Method not found",
+                    "data": "method 'nope' not found"
+                },
+                "id": id
+            });
+            let invalid =
+                json!({"error": {"code": -32600, "message": "Invalid request"}, "id": id});
+
+            let single = call("wrapping_add", json!([1, 2]));
+            assert_eq!(
+                handler.handle_request(single.clone()).as_option(),
+                Some(success.clone())
+            );
+            assert_eq!(
+                handler
+                    .handle_bytes(single.to_string().as_bytes())
+                    .map(|bytes| serde_json::from_slice::<Value>(&bytes).unwrap()),
+                Some(success.clone())
+            );
+            assert_eq!(
+                handler.handle_request(call("nope", json!([]))).as_option(),
+                Some(failure.clone())
+            );
+            assert_eq!(
+                handler
+                    .handle_request(call("wrapping_add", json!(5)))
+                    .as_option(),
+                Some(invalid.clone())
+            );
+            assert_eq!(
+                handler
+                    .handle_request(json!([
+                        single,
+                        call("nope", json!([])),
+                        call("wrapping_add", json!(5))
+                    ]))
+                    .as_option(),
+                Some(json!([success, failure, invalid]))
+            );
+        }
+
+        // jsonrpc_core ids are unsigned integers or strings, calls with other numeric ids are
+        // invalid and their id can't be echoed
+        for id in [json!(-1), json!(1.5)].iter() {
+            assert_eq!(
+                handler
+                    .handle_request(
+                        json!({"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": id})
+                    )
+                    .as_option(),
+                Some(json!({"error": {"code": -32600, "message": "Invalid request"}, "id": null}))
+            );
+        }
+    }
+
+    #[test]
+    fn recover_invalid_call_ids() {
+        let handler = &AdderImpl as &dyn Adder;
+        let invalid_request = |id: Value| {
+            json!({
+                "error": {"code": -32600, "message": "Invalid request"},
+                "id": id
+            })
+        };
+        let valid = json!({"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 1], "id": 3});
+
+        // the id is echoed even though params are malformed
+        assert_eq!(
+            handler
+                .handle_request(
+                    json!({"jsonrpc": "2.0", "method": "wrapping_add", "params": 5, "id": 1})
+                )
+                .as_option(),
+            Some(invalid_request(json!(1)))
+        );
+        // an id that is not valid can't be echoed
+        assert_eq!(
+            handler
+                .handle_request(json!({"jsonrpc": "2.0", "method": "wrapping_add", "id": {}}))
+                .as_option(),
+            Some(invalid_request(Value::Null))
+        );
+        // malformed calls don't spoil the rest of the batch
+        let batch = json!([1, {"method": 5, "id": 2}, valid]);
+        let expected = json!([
+            invalid_request(Value::Null),
+            invalid_request(json!(2)),
+            {"jsonrpc": "2.0", "result": 2, "id": 3}
+        ]);
+        assert_eq!(
+            handler.handle_request(batch.clone()).as_option(),
+            Some(expected.clone())
+        );
+        assert_eq!(
+            handler
+                .handle_bytes(batch.to_string().as_bytes())
+                .map(|bytes| serde_json::from_slice::<Value>(&bytes).unwrap()),
+            Some(expected.clone())
+        );
+        assert_eq!(
+            handler
+                .handle_parsed(super::parse_request(batch.to_string().as_bytes()))
+                .map(|response| serde_json::to_value(response).unwrap()),
+            Some(expected)
+        );
+    }
+
+    #[test]
+    fn empty_batch() {
+        let handler = &AdderImpl as &dyn Adder;
+        assert_eq!(
+            handler.handle_request(json!([])),
+            MaybeReply::Reply(json!({
+                "jsonrpc": "2.0",
+                "error": {"code": -32600, "message": "Invalid request"},
+                "id": null
+            }))
+        );
+        assert_eq!(
+            handler
+                .handle_bytes(b"[]")
+                .map(|bytes| serde_json::from_slice::<Value>(&bytes).unwrap()),
+            Some(handler.handle_request(json!([])).as_option().unwrap())
+        );
+        // a batch of notifications is not empty, it just needs no reply
+        assert_eq!(
+            handler.handle_request(json!([
+                {"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 1]},
+                {"jsonrpc": "2.0", "method": "swallow"}
+            ])),
+            MaybeReply::DontReply
+        );
+    }
+
+    #[test]
+    fn parse_single_result() {
+        use easy_jsonrpc::{parse_single_result, ResponseFail};
+
+        let handler = &AdderImpl as &dyn Adder;
+        let response = |request: &[u8]| {
+            handler
+                .handle_parsed(super::parse_request(request))
+                .unwrap()
+        };
+        assert_eq!(
+            parse_single_result::<isize>(response(
+                br#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1}"#
+            )),
+            Ok(3)
+        );
+        assert_eq!(
+            parse_single_result::<isize>(response(
+                br#"{"jsonrpc": "2.0", "method": "nope", "id": 1}"#
+            )),
+            Err(ResponseFail::RpcError(easy_jsonrpc::method_not_found(
+                "nope"
+            )))
+        );
+        assert_eq!(
+            parse_single_result::<String>(response(
+                br#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1}"#
+            )),
+            Err(ResponseFail::InvalidResponse)
+        );
+        assert_eq!(
+            parse_single_result::<isize>(response(
+                br#"[{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1}]"#
+            )),
+            Err(ResponseFail::InvalidResponse)
+        );
+    }
+
+    #[test]
+    fn custom_transport() {
+        let handler = &AdderImpl as &dyn Adder;
+        let transport = |request: &[u8]| {
+            handler
+                .handle_parsed(super::parse_request(request))
+                .map(|response| {
+                    serde_json::from_str::<Value>(&super::serialize_response(&response)).unwrap()
+                })
+        };
+        assert_eq!(
+            transport(
+                br#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1}"#
+            ),
+            Some(json!({"jsonrpc": "2.0", "result": 3, "id": 1}))
+        );
+        assert_eq!(
+            transport(br#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2]}"#),
+            None
+        );
+        assert_eq!(
+            transport(b"{"),
+            Some(json!({
+                "error": {
+                    "code": -32600,
+                    "message": "Invalid request"
+                },
+                "id": null
+            }))
+        );
+
+        // bytes and strings hold the same json
+        let response = handler
+            .handle_parsed(super::parse_request(
+                br#"[{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1},
+                     {"jsonrpc": "2.0", "method": "nope", "id": 2}]"#,
+            ))
+            .unwrap();
+        let text = super::response_to_string(&response);
+        assert_eq!(super::response_to_bytes(&response), text.as_bytes());
+        assert_eq!(super::serialize_response(&response), text);
+        assert_eq!(
+            serde_json::from_str::<Value>(&text).unwrap(),
+            json!([
+                {"jsonrpc": "2.0", "result": 3, "id": 1},
+                {
+                    "jsonrpc": "2.0",
+                    "error": {
+                        "code": -32601,
+                        "message": "Method not found",
+                        "data": "method 'nope' not found"
+                    },
+                    "id": 2
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn default_args() {
+        #[easy_jsonrpc::rpc]
+        trait Lister {
+            fn list(
+                &self,
+                dir: String,
+                #[rpc(default)] verbose: bool,
+                #[rpc(default)] limit: Option<usize>,
+            ) -> (String, bool, Option<usize>);
+        }
+
+        struct ListerImpl;
+        impl Lister for ListerImpl {
+            fn list(
+                &self,
+                dir: String,
+                verbose: bool,
+                limit: Option<usize>,
+            ) -> (String, bool, Option<usize>) {
+                (dir, verbose, limit)
+            }
+        }
+
+        let handler = &ListerImpl as &dyn Lister;
+        let call = |params: Value| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": "list",
+                    "params": params,
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+        };
+        assert_eq!(call(json!(["a"]))["result"], json!(["a", false, null]));
+        assert_eq!(call(json!(["a", true]))["result"], json!(["a", true, null]));
+        assert_eq!(call(json!(["a", true, 2]))["result"], json!(["a", true, 2]));
+        assert_eq!(
+            call(json!({"dir": "a"}))["result"],
+            json!(["a", false, null])
+        );
+        assert_eq!(
+            call(json!({"dir": "a", "limit": 2}))["result"],
+            json!(["a", false, 2])
+        );
+        assert_eq!(
+            call(json!([]))["error"]["message"],
+            json!("WrongNumberOfArgs. Expected 1. Actual 0")
+        );
+        assert_eq!(
+            call(json!(["a", true, 2, 3]))["error"]["message"],
+            json!("WrongNumberOfArgs. Expected 3. Actual 4")
+        );
+        assert_eq!(
+            call(json!({"verbose": true}))["error"]["message"],
+            json!("MissingNamedParameter dir")
+        );
+    }
+
+    #[test]
+    fn openrpc_schema() {
+        #[easy_jsonrpc::rpc(openrpc)]
+        trait Wallet {
+            #[rpc(name = "wallet.getBalance")]
+            fn get_balance(&self, account: String, #[rpc(default)] pending: bool) -> u64;
+            fn history(
+                &self,
+                accounts: Vec<String>,
+                limit: Option<usize>,
+            ) -> Result<Vec<(u64, f64)>, String>;
+            fn forget(&self, account: Account);
+        }
+
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Account;
+
+        assert_eq!(
+            wallet::openrpc_schema(),
+            json!({
+                "openrpc": "1.2.6",
+                "info": {
+                    "title": "Wallet",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "methods": [
+                    {
+                        "name": "wallet.getBalance",
+                        "params": [
+                            {
+                                "name": "account",
+                                "required": true,
+                                "schema": { "type": "string" }
+                            },
+                            {
+                                "name": "pending",
+                                "required": false,
+                                "schema": { "type": "boolean" }
+                            }
+                        ],
+                        "result": {
+                            "name": "result",
+                            "schema": { "type": "integer" }
+                        }
+                    },
+                    {
+                        "name": "history",
+                        "params": [
+                            {
+                                "name": "accounts",
+                                "required": true,
+                                "schema": { "type": "array", "items": { "type": "string" } }
+                            },
+                            {
+                                "name": "limit",
+                                "required": true,
+                                "schema": { "oneOf": [{ "type": "integer" }, { "type": "null" }] }
+                            }
+                        ],
+                        "result": {
+                            "name": "result",
+                            "schema": {
+                                "type": "array",
+                                "items": {
+                                    "type": "array",
+                                    "items": [{ "type": "integer" }, { "type": "number" }]
+                                }
+                            }
+                        }
+                    },
+                    {
+                        "name": "forget",
+                        "params": [
+                            {
+                                "name": "account",
+                                "required": true,
+                                "schema": {}
+                            }
+                        ],
+                        "result": {
+                            "name": "result",
+                            "schema": { "type": "null" }
+                        }
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn supertraits() {
+        #[easy_jsonrpc::rpc]
+        trait Accounts {
+            fn balance(&self, account: String) -> u64;
+        }
+
+        #[easy_jsonrpc::rpc]
+        trait Signing {
+            #[rpc(name = "signing.sign")]
+            fn sign(&self, message: String) -> String;
+        }
+
+        #[easy_jsonrpc::rpc(supertraits(Accounts, Signing))]
+        trait Wallet: Accounts + Signing {
+            fn send(&self, to: String, amount: u64) -> bool;
+        }
+
+        struct WalletImpl;
+        impl Accounts for WalletImpl {
+            fn balance(&self, account: String) -> u64 {
+                account.len() as u64
+            }
+        }
+        impl Signing for WalletImpl {
+            fn sign(&self, message: String) -> String {
+                format!("signed {}", message)
+            }
+        }
+        impl Wallet for WalletImpl {
+            fn send(&self, _to: String, amount: u64) -> bool {
+                amount < 10
+            }
+        }
+
+        let handler = &WalletImpl as &dyn Wallet;
+        let call = |method: &str, params: Value| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": params,
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+        };
+        assert_eq!(call("send", json!(["bob", 3]))["result"], json!(true));
+        assert_eq!(call("balance", json!(["alice"]))["result"], json!(5));
+        assert_eq!(
+            call("signing.sign", json!({"message": "hi"}))["result"],
+            json!("signed hi")
+        );
+        assert_eq!(call("sign", json!(["hi"]))["error"]["code"], json!(-32601));
+        assert!(wallet::handles_method("balance"));
+        assert!(!wallet::handles_method("sign"));
+        assert_eq!(handler.methods(), vec!["send", "balance", "signing.sign"]);
+    }
+
+    #[test]
+    fn fallback() {
+        #[easy_jsonrpc::rpc]
+        trait Gateway {
+            fn local(&self) -> u8;
+            #[rpc(fallback)]
+            fn forward(&self, method: String, params: Params)
+                -> Result<Value, jsonrpc_core::Error>;
+        }
+
+        struct GatewayImpl;
+        impl Gateway for GatewayImpl {
+            fn local(&self) -> u8 {
+                0
+            }
+            fn forward(
+                &self,
+                method: String,
+                params: Params,
+            ) -> Result<Value, jsonrpc_core::Error> {
+                match params {
+                    Params::Positional(args) if method == "remote" => Ok(json!(args.len())),
+                    _ => Err(jsonrpc_core::Error::method_not_found()),
+                }
+            }
+        }
+
+        let handler = &GatewayImpl as &dyn Gateway;
+        let call = |method: &str, params: Value| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": params,
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+        };
+        assert_eq!(call("local", json!([]))["result"], json!(0));
+        assert_eq!(call("remote", json!([1, 2]))["result"], json!(2));
+        assert_eq!(call("other", json!([]))["error"]["code"], json!(-32601));
+        assert_eq!(
+            call("forward", json!(["remote", []]))["error"]["code"],
+            json!(-32601)
+        );
+        assert!(!gateway::handles_method("forward"));
+        assert_eq!(handler.methods(), vec!["local"]);
+    }
+
+    #[test]
+    fn cfg_methods() {
+        #[easy_jsonrpc::rpc(client, describe, openrpc, request_enum)]
+        trait Gated {
+            fn status(&self) -> String;
+            #[cfg(feature = "metrics")]
+            fn stats(&self) -> u64;
+            #[cfg(any())]
+            #[rpc(params_struct, idempotent)]
+            fn hidden(&self, key: Missing) -> Result<Missing, String>;
+        }
+
+        struct GatedImpl;
+        impl Gated for GatedImpl {
+            fn status(&self) -> String {
+                "ok".into()
+            }
+
+            #[cfg(feature = "metrics")]
+            fn stats(&self) -> u64 {
+                3
+            }
+        }
+
+        let handler = &GatedImpl as &dyn Gated;
+        let mut expected = vec!["status"];
+        if cfg!(feature = "metrics") {
+            expected.push("stats");
+        }
+        assert_eq!(gated::RPC_METHODS, &expected[..]);
+        expected.push("rpc.describe");
+        assert_eq!(handler.methods(), expected);
+        assert_eq!(gated::rpc_descriptors().len(), gated::RPC_METHODS.len());
+        assert_eq!(
+            gated::openrpc_schema()["methods"].as_array().unwrap().len(),
+            gated::RPC_METHODS.len()
+        );
+        assert!(!gated::is_idempotent("hidden"));
+
+        let call = |method: &str| {
+            handler
+                .handle_request(json!({"jsonrpc": "2.0", "method": method, "params": [], "id": 1}))
+                .as_option()
+                .unwrap()
+        };
+        assert_eq!(call("status")["result"], json!("ok"));
+        assert_eq!(call("hidden")["error"]["code"], json!(-32601));
+        #[cfg(feature = "metrics")]
+        assert_eq!(call("stats")["result"], json!(3));
+        #[cfg(not(feature = "metrics"))]
+        assert_eq!(call("stats")["error"]["code"], json!(-32601));
+        assert!(GatedRequest::parse("hidden", Params::Positional(vec![])).is_err());
+    }
+
+    #[test]
+    fn tuple_args() {
+        #[easy_jsonrpc::rpc(tuple_args)]
+        trait Mixer {
+            fn mix(&self, a: String, b: &u8, c: Vec<bool>) -> (String, u8, usize);
+            fn scale(&self, a: u8, #[rpc(default)] factor: u8) -> u8;
+        }
+
+        struct MixerImpl;
+        impl Mixer for MixerImpl {
+            fn mix(&self, a: String, b: &u8, c: Vec<bool>) -> (String, u8, usize) {
+                (a, *b, c.len())
+            }
+            fn scale(&self, a: u8, factor: u8) -> u8 {
+                a * factor
+            }
+        }
+
+        let handler = &MixerImpl as &dyn Mixer;
+        let call = |method: &str, params: Value| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": params,
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+        };
+        assert_eq!(
+            call("mix", json!(["a", 2, [true, false]]))["result"],
+            json!(["a", 2, 2])
+        );
+        assert_eq!(
+            call("mix", json!({"c": [], "b": 3, "a": "b"}))["result"],
+            json!(["b", 3, 0])
+        );
+        assert_eq!(
+            call("mix", json!(["a", "b", []]))["error"],
+            json!({
+                "code": -32602,
+                "message": "InvalidArgStructure b at position 1: invalid type: string \"b\", expected u8"
+            })
+        );
+        assert_eq!(
+            call("mix", json!(["a", 2]))["error"]["message"],
+            json!("WrongNumberOfArgs. Expected 3. Actual 2")
+        );
+        assert_eq!(call("scale", json!([2]))["result"], json!(0));
+        assert_eq!(call("scale", json!([2, 3]))["result"], json!(6));
+    }
+
+    #[test]
+    #[allow(clippy::too_many_arguments)] // the generated client helpers take each argument
+    fn tuple_args_match_per_argument() {
+        #[easy_jsonrpc::rpc(tuple_args)]
+        trait WideTuple {
+            fn wide(
+                &self,
+                a: u8,
+                b: u16,
+                c: u32,
+                d: u64,
+                e: i8,
+                f: i16,
+                g: i32,
+                h: String,
+                i: &str,
+                j: Vec<bool>,
+            ) -> usize;
+        }
+
+        #[easy_jsonrpc::rpc]
+        trait Wide {
+            fn wide(
+                &self,
+                a: u8,
+                b: u16,
+                c: u32,
+                d: u64,
+                e: i8,
+                f: i16,
+                g: i32,
+                h: String,
+                i: &str,
+                j: Vec<bool>,
+            ) -> usize;
+        }
+
+        struct WideImpl;
+        impl WideTuple for WideImpl {
+            fn wide(
+                &self,
+                a: u8,
+                b: u16,
+                c: u32,
+                d: u64,
+                e: i8,
+                f: i16,
+                g: i32,
+                h: String,
+                i: &str,
+                j: Vec<bool>,
+            ) -> usize {
+                let ints = a as i64 + b as i64 + c as i64 + d as i64 + e as i64 + f as i64;
+                (ints + g as i64) as usize + h.len() + i.len() + j.len()
+            }
+        }
+        impl Wide for WideImpl {
+            fn wide(
+                &self,
+                a: u8,
+                b: u16,
+                c: u32,
+                d: u64,
+                e: i8,
+                f: i16,
+                g: i32,
+                h: String,
+                i: &str,
+                j: Vec<bool>,
+            ) -> usize {
+                WideTuple::wide(self, a, b, c, d, e, f, g, h, i, j)
+            }
+        }
+
+        fn call<H: Handler + ?Sized>(handler: &H, params: Value) -> Value {
+            let request = json!({"jsonrpc": "2.0", "method": "wide", "params": params, "id": 1});
+            handler.handle_request(request).as_option().unwrap().clone()
+        }
+        // the reply of the tuple_args handler, checked against the reply of the other
+        let both = |params: Value| {
+            let reply = call(&WideImpl as &dyn WideTuple, params.clone());
+            assert_eq!(reply, call(&WideImpl as &dyn Wide, params));
+            reply
+        };
+        let names = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+        let named = |args: &[Value]| {
+            Value::Object(
+                names
+                    .iter()
+                    .map(|name| name.to_string())
+                    .zip(args.to_vec())
+                    .collect(),
+            )
+        };
+
+        let args: Vec<Value> =
+            serde_json::from_value(json!([1, 2, 3, 4, -1, -2, -3, "ab", "c", [true]])).unwrap();
+        assert_eq!(both(Value::Array(args.clone()))["result"], json!(8));
+        assert_eq!(both(named(&args))["result"], json!(8));
+
+        // whichever argument is wrong, both report it at the same position
+        for (index, name) in names.iter().enumerate() {
+            let mut wrong = args.clone();
+            wrong[index] = json!({});
+            let expected = format!("InvalidArgStructure {} at position {}: ", name, index);
+            for params in [Value::Array(wrong.clone()), named(&wrong)] {
+                let reply = both(params);
+                let message = reply["error"]["message"].as_str().unwrap();
+                assert!(message.starts_with(&expected), "{}", message);
+            }
+        }
+
+        assert_eq!(
+            both(Value::Array(args[..9].to_vec()))["error"]["message"],
+            json!("WrongNumberOfArgs. Expected 10. Actual 9")
+        );
+        assert_eq!(
+            both(named(&args[..9]))["error"]["message"],
+            json!("MissingNamedParameter j")
+        );
+    }
+
+    #[test]
+    #[allow(clippy::too_many_arguments)] // the generated client helpers take each argument
+    fn tuple_args_wider_than_serde_tuples() {
+        // serde deserializes tuples of up to 16 elements, the 17 arguments are taken one by one
+        #[easy_jsonrpc::rpc(tuple_args)]
+        trait Wider {
+            fn sum(
+                &self,
+                a: u8,
+                b: u8,
+                c: u8,
+                d: u8,
+                e: u8,
+                f: u8,
+                g: u8,
+                h: u8,
+                i: u8,
+                j: u8,
+                k: u8,
+                l: u8,
+                m: u8,
+                n: u8,
+                o: u8,
+                p: u8,
+                q: u8,
+            ) -> u64;
+        }
+
+        struct WiderImpl;
+        impl Wider for WiderImpl {
+            fn sum(
+                &self,
+                a: u8,
+                b: u8,
+                c: u8,
+                d: u8,
+                e: u8,
+                f: u8,
+                g: u8,
+                h: u8,
+                i: u8,
+                j: u8,
+                k: u8,
+                l: u8,
+                m: u8,
+                n: u8,
+                o: u8,
+                p: u8,
+                q: u8,
+            ) -> u64 {
+                [a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p, q]
+                    .iter()
+                    .map(|arg| u64::from(*arg))
+                    .sum()
+            }
+        }
+
+        let handler = &WiderImpl as &dyn Wider;
+        let call = |params: Value| {
+            let request = json!({"jsonrpc": "2.0", "method": "sum", "params": params, "id": 1});
+            handler.handle_request(request).as_option().unwrap().clone()
+        };
+        assert_eq!(
+            call(json!((1..=17).collect::<Vec<u8>>()))["result"],
+            json!(153)
+        );
+        let mut args: Vec<Value> = (1..=17).map(|arg: u8| json!(arg)).collect();
+        args[16] = json!("q");
+        assert_eq!(
+            call(Value::Array(args))["error"]["message"],
+            json!("InvalidArgStructure q at position 16: invalid type: string \"q\", expected u8")
+        );
+    }
+
+    #[test]
+    fn authorize() {
+        #[easy_jsonrpc::rpc]
+        trait Admin {
+            #[rpc(authorize)]
+            fn authorize(&self, method: &str) -> Result<(), jsonrpc_core::Error>;
+            #[rpc(auth)]
+            fn shutdown(&self) -> bool;
+            #[rpc(auth, name = "admin.reboot")]
+            fn reboot(&self) -> bool;
+            fn status(&self) -> String;
+        }
+
+        struct AdminImpl {
+            allowed: &'static [&'static str],
+        }
+        impl Admin for AdminImpl {
+            fn authorize(&self, method: &str) -> Result<(), jsonrpc_core::Error> {
+                if self.allowed.contains(&method) {
+                    Ok(())
+                } else {
+                    Err(jsonrpc_core::Error {
+                        code: jsonrpc_core::ErrorCode::ServerError(-32099),
+                        message: "Unauthorized".into(),
+                        data: None,
+                    })
+                }
+            }
+            fn shutdown(&self) -> bool {
+                true
+            }
+            fn reboot(&self) -> bool {
+                true
+            }
+            fn status(&self) -> String {
+                "ok".into()
+            }
+        }
+
+        let call = |handler: &AdminImpl, method: &str| {
+            (handler as &dyn Admin)
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": [],
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+        };
+        let admin = AdminImpl {
+            allowed: &["shutdown"],
+        };
+        assert_eq!(call(&admin, "shutdown")["result"], json!(true));
+        assert_eq!(
+            call(&admin, "admin.reboot")["error"],
+            json!({"code": -32099, "message": "Unauthorized"})
+        );
+        assert_eq!(call(&admin, "status")["result"], json!("ok"));
+        assert_eq!(call(&admin, "authorize")["error"]["code"], json!(-32601));
+        let guest = AdminImpl { allowed: &[] };
+        assert_eq!(call(&guest, "shutdown")["error"]["code"], json!(-32099));
+        assert_eq!(call(&guest, "status")["result"], json!("ok"));
+    }
+
+    #[test]
+    fn invalid_arg_detail() {
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "wrapping_add",
+                "params": [1, "two"],
+                "id": 1
+            }),
+            json!({
+                "error": {
+                    "code": -32602,
+                    "message": "InvalidArgStructure b at position 1: \
+                                invalid type: string \"two\", expected isize"
+                },
+                "id": 1,
+                "jsonrpc": "2.0"
+            }),
+        );
+    }
+
+    #[test]
+    fn allow_reserved() {
+        #[easy_jsonrpc::rpc]
+        trait Discoverable {
+            #[rpc(name = "rpc.discover", allow_reserved)]
+            fn discover(&self) -> Value;
+        }
+
+        struct DiscoverableImpl;
+        impl Discoverable for DiscoverableImpl {
+            fn discover(&self) -> Value {
+                json!({"openrpc": "1.2.6"})
+            }
+        }
+
+        let response = (&DiscoverableImpl as &dyn Discoverable)
+            .handle_request(json!({"jsonrpc": "2.0", "method": "rpc.discover", "id": 1}));
+        assert_eq!(
+            response.as_option(),
+            Some(json!({"jsonrpc": "2.0", "result": {"openrpc": "1.2.6"}, "id": 1}))
+        );
+        let bound = discoverable::discover().unwrap();
+        let (request, _) = bound.call();
+        assert_eq!(request.as_request()["method"], json!("rpc.discover"));
+
+        // more prefixes may be reserved, each name starting with one must allow it
+        #[easy_jsonrpc::rpc(reserved_prefixes("sys.", "internal_"))]
+        trait Gateway {
+            #[rpc(name = "sys.health", allow_reserved)]
+            fn health(&self) -> bool;
+            #[rpc(name = "rpc.discover", allow_reserved)]
+            fn discover(&self) -> Value;
+            #[rpc(name = "system.version")]
+            fn version(&self) -> u8;
+        }
+
+        struct GatewayImpl;
+        impl Gateway for GatewayImpl {
+            fn health(&self) -> bool {
+                true
+            }
+
+            fn discover(&self) -> Value {
+                json!({})
+            }
+
+            fn version(&self) -> u8 {
+                1
+            }
+        }
+
+        assert_eq!(
+            (&GatewayImpl as &dyn Gateway).methods(),
+            vec!["sys.health", "rpc.discover", "system.version"]
+        );
+    }
+
+    #[test]
+    fn notify_helpers() {
+        let handler = &AdderImpl as &dyn Adder;
+        let notification = adder::notify_wrapping_add(1, 2).unwrap();
+        assert_eq!(
+            notification.as_request(),
+            json!({"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2]})
+        );
+        assert_eq!(
+            handler.handle_request(notification.as_request()),
+            MaybeReply::DontReply
+        );
+
+        let bound = adder::checked_add(1, 2).unwrap();
+        let (call, tracker) = bound.call();
+        let request = super::Call::batch_request(&[call, notification.as_call()]);
+        let mut response = super::Response::from_json_response(
+            handler.handle_request(request).as_option().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(tracker.get_return(&mut response).unwrap(), Some(3));
+        assert!(response.outputs.is_empty());
+    }
+
+    #[test]
+    fn prefix() {
+        #[easy_jsonrpc::rpc(prefix = "wallet_")]
+        trait Wallet {
+            fn balance(&self) -> u64;
+            #[rpc(name = "send")]
+            fn send(&self, amount: u64) -> u64;
+        }
+
+        struct WalletImpl;
+        impl Wallet for WalletImpl {
+            fn balance(&self) -> u64 {
+                10
+            }
+            fn send(&self, amount: u64) -> u64 {
+                10 - amount
+            }
+        }
+
+        let handler = &WalletImpl as &dyn Wallet;
+        let call = |method: &str, params: Value| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": params,
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+        };
+        assert_eq!(call("wallet_balance", json!([]))["result"], json!(10));
+        assert_eq!(call("balance", json!([]))["error"]["code"], json!(-32601));
+        assert_eq!(call("send", json!([3]))["result"], json!(7));
+        assert_eq!(wallet_methods::WALLET_BALANCE, "wallet_balance");
+        let bound = wallet::balance().unwrap();
+        let (request, _) = bound.call();
+        assert_eq!(request.as_request()["method"], json!("wallet_balance"));
+    }
+
+    #[test]
+    fn router() {
+        #[easy_jsonrpc::rpc]
+        trait Accounts {
+            fn balance(&self, account: String) -> u64;
+        }
+
+        #[easy_jsonrpc::rpc]
+        trait Prices {
+            fn price(&self) -> u64;
+            fn balance(&self) -> String;
+        }
+
+        struct AccountsImpl;
+        impl Accounts for AccountsImpl {
+            fn balance(&self, account: String) -> u64 {
+                account.len() as u64
+            }
+        }
+
+        struct PricesImpl;
+        impl Prices for PricesImpl {
+            fn price(&self) -> u64 {
+                7
+            }
+            fn balance(&self) -> String {
+                "shadowed".into()
+            }
+        }
+
+        let router = super::Router::new()
+            .with(Box::new(AccountsImpl) as Box<dyn Accounts>)
+            .with(std::sync::Arc::new(PricesImpl) as std::sync::Arc<dyn Prices>);
+        let response = router
+            .handle_request(json!([
+                {"jsonrpc": "2.0", "method": "balance", "params": ["abc"], "id": 1},
+                {"jsonrpc": "2.0", "method": "price", "params": [], "id": 2},
+                {"jsonrpc": "2.0", "method": "nope", "params": [], "id": 3},
+            ]))
+            .as_option()
+            .unwrap();
+        assert_eq!(
+            response,
+            json!([
+                {"jsonrpc": "2.0", "result": 3, "id": 1},
+                {"jsonrpc": "2.0", "result": 7, "id": 2},
+                {
+                    "jsonrpc": "2.0",
+                    "error": {
+                        "code": -32601,
+                        "message": "Method not found",
+                        "data": "method 'nope' not found"
+                    },
+                    "id": 3
+                },
+            ])
+        );
+        let mut methods = router.methods();
+        methods.sort();
+        assert_eq!(methods, vec!["balance", "price"]);
+        assert_eq!(
+            super::Router::new()
+                .handle_request(json!({"jsonrpc": "2.0", "method": "price", "id": 1}))
+                .as_option()
+                .unwrap()["error"]["code"],
+            json!(-32601)
+        );
+    }
+
+    #[test]
+    fn http_statuses() {
+        use super::{http_status_for, method_not_found, Error};
+
+        assert_eq!(http_status_for(&Error::parse_error()), 400);
+        assert_eq!(http_status_for(&Error::invalid_request()), 400);
+        assert_eq!(http_status_for(&method_not_found("nope")), 200);
+        assert_eq!(http_status_for(&Error::invalid_params("bad")), 200);
+        assert_eq!(http_status_for(&Error::internal_error()), 200);
+    }
+
+    #[test]
+    fn dispatcher() {
+        use super::{Dispatcher, Params};
+
+        let dispatcher = Dispatcher::new()
+            .method("add", |params: Params| {
+                let (a, b): (i64, i64) = params.deserialize()?;
+                Ok(json!(a + b))
+            })
+            .method("version", |_| Ok(json!("1")))
+            .method("version", |_| Ok(json!("2")));
+        assert_eq!(dispatcher.methods(), vec!["add", "version"]);
+        assert_eq!(
+            dispatcher
+                .handle_request(json!([
+                    {"jsonrpc": "2.0", "method": "add", "params": [1, 2], "id": 1},
+                    {"jsonrpc": "2.0", "method": "add", "params": {"a": 1}, "id": 2},
+                    {"jsonrpc": "2.0", "method": "version", "id": 3},
+                    {"jsonrpc": "2.0", "method": "nope", "id": 4},
+                ]))
+                .as_option()
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|reply| reply.get("result").unwrap_or(&reply["error"]["code"]))
+                .cloned()
+                .collect::<Vec<_>>(),
+            vec![json!(3), json!(-32602), json!("2"), json!(-32601)]
+        );
+    }
+
+    #[test]
+    fn typed_params() {
+        use super::{Params, TypedParams};
+
+        let positional = |args: Value| match args {
+            Value::Array(ar) => Params::Positional(ar),
+            _ => unreachable!(),
+        };
+        let named = |args: Value| match args {
+            Value::Object(map) => Params::Named(map),
+            _ => unreachable!(),
+        };
+        let names = &["key", "limit"];
+        let message = |err: super::Error| err.message;
+
+        let params = TypedParams::new(positional(json!(["a", 10])), names).unwrap();
+        assert_eq!(params.get::<String>(0), Ok("a".to_owned()));
+        assert_eq!(params.get_named::<u8>("limit"), Ok(10));
+        assert_eq!(
+            params.get::<u8>(0).map_err(message),
+            Err(
+                "InvalidArgStructure key at position 0: invalid type: string \"a\", expected u8"
+                    .to_owned()
+            )
+        );
+
+        // omitted arguments are null, which only Option arguments accept
+        let params = TypedParams::new(named(json!({"key": "a"})), names).unwrap();
+        assert!(params.contains(0));
+        assert!(!params.contains(1));
+        assert_eq!(params.get::<Option<u8>>(1), Ok(None));
+        assert_eq!(
+            params.get::<u8>(1).map_err(message),
+            Err("MissingNamedParameter limit".to_owned())
+        );
+        let params = TypedParams::new(positional(json!(["a"])), names).unwrap();
+        assert_eq!(
+            params.get::<u8>(1).map_err(message),
+            Err("WrongNumberOfArgs. Expected 2. Actual 1".to_owned())
+        );
+
+        // parameters matching no name are rejected up front
+        assert_eq!(
+            TypedParams::new(named(json!({"key": "a", "offset": 1})), names)
+                .map_err(message)
+                .err(),
+            Some("ExtraNamedParameter offset".to_owned())
+        );
+        assert_eq!(
+            TypedParams::new(positional(json!(["a", 1, 2])), names)
+                .map_err(message)
+                .err(),
+            Some("WrongNumberOfArgs. Expected 2. Actual 3".to_owned())
+        );
+    }
+
+    #[test]
+    fn renamed_args() {
+        #[easy_jsonrpc::rpc]
+        trait Wallet {
+            fn send(&self, #[rpc(rename = "maxAmount")] max_amount: u64, to: String) -> String;
+        }
+
+        struct WalletImpl;
+        impl Wallet for WalletImpl {
+            fn send(&self, max_amount: u64, to: String) -> String {
+                format!("{} to {}", max_amount, to)
+            }
+        }
+
+        let handler = &WalletImpl as &dyn Wallet;
+        let call = |params: Value| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": "send",
+                    "params": params,
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+        };
+        assert_eq!(
+            call(json!({"maxAmount": 3, "to": "bob"}))["result"],
+            json!("3 to bob")
+        );
+        assert_eq!(call(json!([3, "bob"]))["result"], json!("3 to bob"));
+        assert_eq!(
+            call(json!({"max_amount": 3, "to": "bob"}))["error"]["message"],
+            json!("MissingNamedParameter maxAmount")
+        );
+    }
+
+    #[test]
+    fn params_shape() {
+        #[easy_jsonrpc::rpc]
+        trait Shapes {
+            #[rpc(params = "named")]
+            fn named(&self, #[rpc(rename = "firstName")] first_name: String) -> String;
+            #[rpc(params = "positional")]
+            fn positional(&self, a: u8, b: u8) -> u8;
+        }
+
+        struct ShapesImpl;
+        impl Shapes for ShapesImpl {
+            fn named(&self, first_name: String) -> String {
+                first_name
+            }
+            fn positional(&self, a: u8, b: u8) -> u8 {
+                a + b
+            }
+        }
+
+        let handler = &ShapesImpl as &dyn Shapes;
+        let call = |method: &str, params: Value| {
+            handler
+                .handle_request(json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": params,
+                    "id": 1
+                }))
+                .as_option()
+                .unwrap()
+        };
+        assert_eq!(
+            call("named", json!({"firstName": "ann"}))["result"],
+            json!("ann")
+        );
+        assert_eq!(
+            call("named", json!(["ann"]))["error"],
+            json!({
+                "code": -32602,
+                "message": "this method requires named parameters"
+            })
+        );
+        assert_eq!(call("positional", json!([1, 2]))["result"], json!(3));
+        assert_eq!(
+            call("positional", json!({"a": 1, "b": 2}))["error"],
+            json!({
+                "code": -32602,
+                "message": "this method requires positional parameters"
+            })
+        );
+        // passing no parameters is allowed either way
+        assert_eq!(
+            call("named", json!([]))["error"]["message"],
+            json!("WrongNumberOfArgs. Expected 1. Actual 0")
+        );
+
+        // client helpers pass parameters of the required kind
+        assert_eq!(
+            shapes::named("ann".into()).unwrap().call().0.as_request()["params"],
+            json!({"firstName": "ann"})
+        );
+        assert_eq!(
+            shapes::notify_named("ann".into()).unwrap().as_request()["params"],
+            json!({"firstName": "ann"})
+        );
+        assert_eq!(
+            shapes::positional(1, 2).unwrap().call().0.as_request()["params"],
+            json!([1, 2])
+        );
+    }
+
+    #[test]
+    fn serialization_error() {
+        use std::sync::{Arc, Mutex};
+
+        #[easy_jsonrpc::rpc]
+        trait Locks {
+            fn poisoned(&self) -> Mutex<u8>;
+            fn fine(&self) -> u8;
+        }
+
+        struct LocksImpl;
+        impl Locks for LocksImpl {
+            fn poisoned(&self) -> Mutex<u8> {
+                let lock = Arc::new(Mutex::new(0));
+                let holder = lock.clone();
+                let _ = std::thread::spawn(move || {
+                    let _guard = holder.lock();
+                    panic!("poisoning the mutex");
+                })
+                .join();
+                Arc::try_unwrap(lock).unwrap()
+            }
+            fn fine(&self) -> u8 {
+                1
+            }
+        }
+
+        let response = (&LocksImpl as &dyn Locks)
+            .handle_request(json!({"jsonrpc": "2.0", "method": "poisoned", "id": 1}))
+            .as_option()
+            .unwrap();
+        assert_eq!(response["error"]["code"], json!(super::SERIALIZATION_ERROR));
+        assert_eq!(response["error"]["message"], json!("Serialization error"));
+
+        // only the call whose result can't be serialized fails, the rest of the batch is unaffected
+        let batch = json!([
+            {"jsonrpc": "2.0", "method": "fine", "id": 1},
+            {"jsonrpc": "2.0", "method": "poisoned", "id": 2},
+            {"jsonrpc": "2.0", "method": "fine", "id": 3}
+        ]);
+        let check = |response: Value| {
+            assert_eq!(response[0], json!({"jsonrpc": "2.0", "result": 1, "id": 1}));
+            assert_eq!(
+                response[1]["error"]["code"],
+                json!(super::SERIALIZATION_ERROR)
+            );
+            assert_eq!(response[1]["id"], json!(2));
+            assert_eq!(response[2], json!({"jsonrpc": "2.0", "result": 1, "id": 3}));
+        };
+        let handler = &LocksImpl as &dyn Locks;
+        check(handler.handle_request(batch.clone()).as_option().unwrap());
+        check(
+            serde_json::from_slice(&handler.handle_bytes(batch.to_string().as_bytes()).unwrap())
+                .unwrap(),
+        );
+    }
+
+    #[test]
+    fn method_constants() {
+        #[easy_jsonrpc::rpc]
+        trait Wallet {
+            #[rpc(name = "wallet.getBalance")]
+            fn get_balance(&self) -> u64;
+            fn send_to(&self, to: String);
+            #[rpc(name = "2fa")]
+            fn two_factor(&self);
+        }
+
+        assert_eq!(wallet_methods::WALLET_GET_BALANCE, "wallet.getBalance");
+        assert_eq!(wallet_methods::SEND_TO, "send_to");
+        assert_eq!(wallet_methods::_2FA, "2fa");
+        assert_eq!(adder_methods::WRAPPING_ADD, "wrapping_add");
+    }
+
+    // https://github.com/layer1capital/easy-jsonrpc/issues/8
+    #[test]
+    fn wrong_num_arg_err() {
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "checked_add",
+                "params": [1],
+                "id": 1
+            }),
+            json!({
+                "error": {
+                    "code": -32602,
+                    "message": "WrongNumberOfArgs. Expected 2. Actual 1"
+                },
+                "id": 1,
+                "jsonrpc": "2.0"
+            }),
+        );
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "wrapping_add",
+                "params": [1],
+                "id": 1
+            }),
+            json!({
+                "error": {
+                    "code": -32602,
+                    "message": "WrongNumberOfArgs. Expected 2. Actual 1"
+                },
+                "id": 1,
+                "jsonrpc": "2.0"
+            }),
+        );
+
+        let res = Params::from_rc_params(jsonrpc_core::Params::Array(vec![
+            json!(1),
+            json!(2),
+            json!(3),
+        ]))
+        .get_rpc_args(&["arg_one", "arg_two"]);
+        assert_eq!(
+            res,
+            Err(InvalidArgs::WrongNumberOfArgs {
+                expected: 2,
+                actual: 3
+            })
+        );
+    }
+
+    #[test]
+    fn named_param_errors() {
+        let named = |value: Value| match value {
+            Value::Object(map) => Params::Named(map),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            named(json!({"b": 1, "x": 2, "y": 3})).get_rpc_args(&["a", "b", "c"]),
+            Err(InvalidArgs::MissingNamedParameter {
+                names: vec!["a", "c"]
+            })
+        );
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "checked_add",
+                "params": {"a": 1, "c": 2, "d": 3},
+                "id": 1
+            }),
+            json!({
+                "error": {
+                    "code": -32602,
+                    "message": "MissingNamedParameter b"
+                },
+                "id": 1,
+                "jsonrpc": "2.0"
+            }),
+        );
+        assert_adder_response(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "checked_add",
+                "params": {"a": 1, "b": 2, "c": 3, "d": 4},
+                "id": 1
+            }),
+            json!({
+                "error": {
+                    "code": -32602,
+                    "message": "ExtraNamedParameter c, d"
+                },
+                "id": 1,
+                "jsonrpc": "2.0"
+            }),
+        );
+    }
+
+    #[test]
+    fn invalid_arg_messages() {
+        let message = |invalid_args: InvalidArgs| super::Error::from(invalid_args).message;
+        assert_eq!(
+            message(InvalidArgs::InvalidArgStructure {
+                name: "b",
+                index: 1,
+                detail: "invalid type: string \"two\", expected isize".into(),
+            }),
+            "InvalidArgStructure b at position 1: invalid type: string \"two\", expected isize"
+        );
+        assert_eq!(
+            message(InvalidArgs::MissingNamedParameter {
+                names: vec!["a", "c"]
+            }),
+            "MissingNamedParameter a, c"
+        );
+        assert_eq!(
+            message(InvalidArgs::ExtraNamedParameter {
+                names: vec!["x".into()]
+            }),
+            "ExtraNamedParameter x"
+        );
+        assert_eq!(
+            message(InvalidArgs::DuplicateNamedParameter {
+                names: vec!["a".into()]
+            }),
+            "DuplicateNamedParameter a"
+        );
+
+        // generated handlers name the argument that failed to deserialize
+        let reply = (&AdderImpl as &dyn Adder).handle_request(json!({
+            "jsonrpc": "2.0",
+            "method": "checked_add",
+            "params": {"a": 1, "b": "two"},
+            "id": 1
+        }));
+        assert_eq!(
+            reply.as_option().unwrap()["error"]["message"],
+            json!(
+                "InvalidArgStructure b at position 1: invalid type: string \"two\", expected isize"
+            )
+        );
+    }
+}
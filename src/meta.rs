@@ -0,0 +1,122 @@
+// Metadata sent along with the result of a call, folded into the result object.
+
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer};
+use serde::ser::{self, Serialize, Serializer};
+use serde_json::Value;
+
+/// The key metadata is stored under unless another one is picked with
+/// [meta_key](struct.RpcResponse.html#method.meta_key).
+pub const META_KEY: &str = "_meta";
+
+/// The result of a method along with metadata that is not part of the result itself, such as a
+/// pagination cursor. Methods return it in place of their result type.
+///
+/// Jsonrpc responses only have a `result` member, so the metadata is folded into the result
+/// object under a reserved key, `"_meta"` by default. A result serialized as
+/// `{"items": [1, 2]}` with the metadata `{"cursor": "abc"}` is sent as
+/// `{"items": [1, 2], "_meta": {"cursor": "abc"}}`. Without metadata the result is sent as is.
+/// Serializing fails when there is metadata and the result does not serialize to an object, or
+/// when it already has a member named like the key.
+///
+/// Deserializing moves the member named [META_KEY](constant.META_KEY.html) out of the result
+/// object, if there is one, into meta. Use [from_value](#method.from_value) for results sent
+/// with another key.
+///
+/// ```
+/// use easy_jsonrpc::{serde_json::json, RpcResponse};
+///
+/// #[derive(serde::Serialize)]
+/// struct Page {
+///     items: Vec<u64>,
+/// }
+///
+/// let response = RpcResponse::new(Page { items: vec![1, 2] }).with_meta(json!({"cursor": "abc"}));
+/// assert_eq!(
+///     serde_json::to_value(&response).unwrap(),
+///     json!({"items": [1, 2], "_meta": {"cursor": "abc"}})
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct RpcResponse<T> {
+    /// The result of the method.
+    pub result: T,
+    /// The metadata sent along with the result, if any.
+    pub meta: Option<Value>,
+    meta_key: &'static str,
+}
+
+impl<T> RpcResponse<T> {
+    /// The result, without metadata.
+    pub fn new(result: T) -> RpcResponse<T> {
+        RpcResponse {
+            result,
+            meta: None,
+            meta_key: META_KEY,
+        }
+    }
+
+    /// Send meta along with the result.
+    pub fn with_meta(mut self, meta: Value) -> RpcResponse<T> {
+        self.meta = Some(meta);
+        self
+    }
+
+    /// The key the metadata is stored under in the result object, in place of
+    /// [META_KEY](constant.META_KEY.html).
+    pub fn meta_key(mut self, key: &'static str) -> RpcResponse<T> {
+        self.meta_key = key;
+        self
+    }
+
+    /// The result, dropping the metadata.
+    pub fn into_result(self) -> T {
+        self.result
+    }
+}
+
+impl<T: DeserializeOwned> RpcResponse<T> {
+    /// Parse a result whose metadata was stored under key.
+    pub fn from_value(mut value: Value, key: &'static str) -> Result<Self, serde_json::Error> {
+        let meta = match &mut value {
+            Value::Object(members) => members.remove(key),
+            _ => None,
+        };
+        Ok(RpcResponse {
+            result: serde_json::from_value(value)?,
+            meta,
+            meta_key: key,
+        })
+    }
+}
+
+impl<T: Serialize> Serialize for RpcResponse<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let meta = match &self.meta {
+            Some(meta) => meta,
+            None => return self.result.serialize(serializer),
+        };
+        let mut result = match serde_json::to_value(&self.result).map_err(ser::Error::custom)? {
+            Value::Object(members) => members,
+            _ => {
+                return Err(ser::Error::custom(
+                    "metadata can only be sent along with results serialized as objects",
+                ))
+            }
+        };
+        if result.contains_key(self.meta_key) {
+            return Err(ser::Error::custom(format!(
+                "the result already has a member named \"{}\", the key of the metadata",
+                self.meta_key
+            )));
+        }
+        result.insert(self.meta_key.to_owned(), meta.clone());
+        result.serialize(serializer)
+    }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for RpcResponse<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        RpcResponse::from_value(value, META_KEY).map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,156 @@
+// Counts the calls made to each method of a handler. Requires the "metrics" feature.
+
+use crate::{Handler, Id, Params, Value};
+use jsonrpc_core::Error;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Wraps a [Handler](trait.Handler.html), counting the calls made to each of its methods along
+/// with how many succeeded and how many failed. Notifications are counted like any other call,
+/// as are calls rejected by the before_dispatch hook of the handler, which count as failures.
+///
+/// Counters exist for the methods listed by the handler when wrapped, so counting never takes a
+/// lock. Calls to other methods, for example those answered by a fallback method or with
+/// "Method not found", are counted together, see [unlisted](#method.unlisted), which keeps
+/// clients from growing the set of counters without bound.
+///
+/// ```
+/// # #[easy_jsonrpc::rpc]
+/// # pub trait Adder {
+/// #     fn wrapping_add(&self, a: isize, b: isize) -> isize;
+/// # }
+/// # struct AdderImpl;
+/// # impl Adder for AdderImpl {
+/// #     fn wrapping_add(&self, a: isize, b: isize) -> isize { a.wrapping_add(b) }
+/// # }
+/// use easy_jsonrpc::{Handler, MeteredServer};
+///
+/// let server = MeteredServer::new(&AdderImpl as &dyn Adder);
+/// server.handle_bytes(br#"{"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1}"#);
+/// assert_eq!(server.snapshot()["wrapping_add"].successes, 1);
+/// ```
+pub struct MeteredServer<S> {
+    inner: S,
+    counters: HashMap<&'static str, Counters>,
+    unlisted: Counters,
+}
+
+/// The number of calls made to a method of a [MeteredServer](struct.MeteredServer.html).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MethodMetrics {
+    /// Calls made, successes and failures included.
+    pub calls: u64,
+    /// Calls that returned a result.
+    pub successes: u64,
+    /// Calls that returned an error.
+    pub failures: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    successes: AtomicU64,
+    failures: AtomicU64,
+}
+
+impl Counters {
+    fn record<T>(&self, ret: &Result<T, Error>) {
+        let counter = match ret {
+            Ok(_) => &self.successes,
+            Err(_) => &self.failures,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn load(&self) -> MethodMetrics {
+        let successes = self.successes.load(Ordering::Relaxed);
+        let failures = self.failures.load(Ordering::Relaxed);
+        MethodMetrics {
+            calls: successes + failures,
+            successes,
+            failures,
+        }
+    }
+}
+
+impl<S: Handler> MeteredServer<S> {
+    /// Count the calls made to the methods of inner, starting from zero.
+    pub fn new(inner: S) -> MeteredServer<S> {
+        let counters = inner
+            .methods()
+            .into_iter()
+            .map(|method| (method, Counters::default()))
+            .collect();
+        MeteredServer {
+            inner,
+            counters,
+            unlisted: Counters::default(),
+        }
+    }
+
+    /// The counts of each method listed by the wrapped handler, keyed by method name. Methods
+    /// not called yet have all counts at zero.
+    pub fn snapshot(&self) -> HashMap<String, MethodMetrics> {
+        self.counters
+            .iter()
+            .map(|(method, counters)| (method.to_string(), counters.load()))
+            .collect()
+    }
+
+    /// The counts of calls to methods not listed by the wrapped handler, all methods together.
+    pub fn unlisted(&self) -> MethodMetrics {
+        self.unlisted.load()
+    }
+
+    /// The wrapped handler.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn record<T>(&self, method: &str, ret: &Result<T, Error>) {
+        self.counters
+            .get(method)
+            .unwrap_or(&self.unlisted)
+            .record(ret);
+    }
+}
+
+impl<S: Handler> Handler for MeteredServer<S> {
+    fn handle(&self, method: &str, params: Params) -> Result<Value, Error> {
+        let ret = self.inner.handle(method, params);
+        self.record(method, &ret);
+        ret
+    }
+
+    fn handle_with_id(&self, id: &Id, method: &str, params: Params) -> Result<Value, Error> {
+        let ret = self.inner.handle_with_id(id, method, params);
+        self.record(method, &ret);
+        ret
+    }
+
+    fn handle_notification(&self, method: &str, params: Params) -> Result<Value, Error> {
+        let ret = self.inner.handle_notification(method, params);
+        self.record(method, &ret);
+        ret
+    }
+
+    fn methods(&self) -> Vec<&'static str> {
+        self.inner.methods()
+    }
+
+    // rejected calls are never handled, they are counted here instead
+    fn before_dispatch(&self, method: &str) -> Result<(), Error> {
+        let ret = self.inner.before_dispatch(method);
+        if ret.is_err() {
+            self.record(method, &ret);
+        }
+        ret
+    }
+
+    fn on_notification_result(&self, method: &str, result: Result<Value, Error>) {
+        self.inner.on_notification_result(method, result)
+    }
+
+    fn on_parse_error(&self, raw: &str, err: &serde_json::Error) {
+        self.inner.on_parse_error(raw, err)
+    }
+}
@@ -0,0 +1,243 @@
+// Throttles the calls made to chosen methods of a handler.
+
+#[cfg(feature = "async")]
+use crate::AsyncHandler;
+use crate::{Handler, Id, Params, Value, RATE_LIMIT_ERROR};
+#[cfg(feature = "async")]
+use futures::future::{self, BoxFuture};
+use jsonrpc_core::{Error, ErrorCode};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How many calls a method of a [RateLimitedServer](struct.RateLimitedServer.html) allows over a
+/// period. Calls may come in bursts of up to `calls` at once, the allowance then refills evenly
+/// over the period.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Calls allowed per period.
+    pub calls: u32,
+    /// The period, a zero period leaves the method unlimited.
+    pub per: Duration,
+}
+
+impl RateLimit {
+    /// Allow calls per period.
+    pub fn new(calls: u32, per: Duration) -> RateLimit {
+        RateLimit { calls, per }
+    }
+
+    /// Allow calls each second.
+    pub fn per_second(calls: u32) -> RateLimit {
+        RateLimit::new(calls, Duration::from_secs(1))
+    }
+
+    /// Allow calls each minute.
+    pub fn per_minute(calls: u32) -> RateLimit {
+        RateLimit::new(calls, Duration::from_secs(60))
+    }
+}
+
+/// The time source of a [RateLimitedServer](struct.RateLimitedServer.html). Implemented by
+/// closures returning an `Instant`, so tests can pass a clock they advance themselves.
+pub trait Clock {
+    /// The current time.
+    fn now(&self) -> Instant;
+}
+
+impl<F: Fn() -> Instant> Clock for F {
+    fn now(&self) -> Instant {
+        self()
+    }
+}
+
+/// Wraps a [Handler](trait.Handler.html), answering calls to the methods given a
+/// [RateLimit](struct.RateLimit.html) with an error of code
+/// [RATE_LIMIT_ERROR](constant.RATE_LIMIT_ERROR.html) once they exceed it. Calls to other methods
+/// are passed through. The limits apply to the server as a whole, whoever the callers are.
+///
+/// Each limited method has a token bucket holding up to `calls` tokens, full to begin with. A
+/// call takes a token, or is rejected without being handled when there is none left. Tokens are
+/// added back at a rate of `calls` per period. Notifications take tokens like any other call.
+///
+/// Wrappers compose, e.g. `MeteredServer::new(RateLimitedServer::new(..))` counts the rejected
+/// calls as failures. Async handlers are limited the same way, their calls take a token when
+/// they start.
+///
+/// ```
+/// # #[easy_jsonrpc::rpc]
+/// # pub trait Adder {
+/// #     fn wrapping_add(&self, a: isize, b: isize) -> isize;
+/// # }
+/// # struct AdderImpl;
+/// # impl Adder for AdderImpl {
+/// #     fn wrapping_add(&self, a: isize, b: isize) -> isize { a.wrapping_add(b) }
+/// # }
+/// use easy_jsonrpc::{serde_json::json, Handler, RateLimit, RateLimitedServer, RATE_LIMIT_ERROR};
+///
+/// let server = RateLimitedServer::new(
+///     &AdderImpl as &dyn Adder,
+///     vec![("wrapping_add", RateLimit::per_minute(1))],
+/// );
+/// let call = json!({"jsonrpc": "2.0", "method": "wrapping_add", "params": [1, 2], "id": 1});
+/// let reply = server.handle_request(call.clone()).as_option().unwrap().clone();
+/// assert_eq!(reply["result"], json!(3));
+/// let reply = server.handle_request(call).as_option().unwrap().clone();
+/// assert_eq!(reply["error"]["code"], json!(RATE_LIMIT_ERROR));
+/// ```
+pub struct RateLimitedServer<S, C = fn() -> Instant> {
+    inner: S,
+    buckets: HashMap<String, Bucket>,
+    clock: C,
+}
+
+struct Bucket {
+    limit: RateLimit,
+    state: Mutex<(f64, Instant)>, // tokens left, as of when they were last counted
+}
+
+impl Bucket {
+    fn take(&self, now: Instant) -> bool {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (tokens, counted) = &mut *state;
+        if self.limit.per.is_zero() {
+            return true;
+        }
+        let calls = f64::from(self.limit.calls);
+        let elapsed = now.saturating_duration_since(*counted).as_secs_f64();
+        *tokens = calls.min(*tokens + elapsed * calls / self.limit.per.as_secs_f64());
+        *counted = (*counted).max(now);
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<S> RateLimitedServer<S> {
+    /// Limit the calls passed to inner, each method named in limits to its limit.
+    pub fn new<K: Into<String>>(
+        inner: S,
+        limits: impl IntoIterator<Item = (K, RateLimit)>,
+    ) -> RateLimitedServer<S> {
+        RateLimitedServer::with_clock(inner, limits, Instant::now)
+    }
+}
+
+impl<S, C: Clock> RateLimitedServer<S, C> {
+    /// Same as [new](#method.new), telling the time with clock rather than `Instant::now`.
+    pub fn with_clock<K: Into<String>>(
+        inner: S,
+        limits: impl IntoIterator<Item = (K, RateLimit)>,
+        clock: C,
+    ) -> RateLimitedServer<S, C> {
+        let now = clock.now();
+        let buckets = limits
+            .into_iter()
+            .map(|(method, limit)| {
+                let bucket = Bucket {
+                    limit,
+                    state: Mutex::new((f64::from(limit.calls), now)),
+                };
+                (method.into(), bucket)
+            })
+            .collect();
+        RateLimitedServer {
+            inner,
+            buckets,
+            clock,
+        }
+    }
+
+    /// The wrapped handler.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    // take a token for a call to method, if it is limited
+    fn admit(&self, method: &str) -> Result<(), Error> {
+        let bucket = match self.buckets.get(method) {
+            Some(bucket) => bucket,
+            None => return Ok(()),
+        };
+        if bucket.take(self.clock.now()) {
+            return Ok(());
+        }
+        Err(Error {
+            code: ErrorCode::ServerError(RATE_LIMIT_ERROR),
+            message: "Rate limited".into(),
+            data: Some(Value::String(format!(
+                "method '{}' allows {} calls per {:?}",
+                method, bucket.limit.calls, bucket.limit.per
+            ))),
+        })
+    }
+}
+
+impl<S: Handler, C: Clock> Handler for RateLimitedServer<S, C> {
+    fn handle(&self, method: &str, params: Params) -> Result<Value, Error> {
+        self.admit(method)?;
+        self.inner.handle(method, params)
+    }
+
+    fn handle_with_id(&self, id: &Id, method: &str, params: Params) -> Result<Value, Error> {
+        self.admit(method)?;
+        self.inner.handle_with_id(id, method, params)
+    }
+
+    fn handle_notification(&self, method: &str, params: Params) -> Result<Value, Error> {
+        self.admit(method)?;
+        self.inner.handle_notification(method, params)
+    }
+
+    fn methods(&self) -> Vec<&'static str> {
+        self.inner.methods()
+    }
+
+    fn before_dispatch(&self, method: &str) -> Result<(), Error> {
+        self.inner.before_dispatch(method)
+    }
+
+    fn on_notification_result(&self, method: &str, result: Result<Value, Error>) {
+        self.inner.on_notification_result(method, result)
+    }
+
+    fn on_parse_error(&self, raw: &str, err: &serde_json::Error) {
+        self.inner.on_parse_error(raw, err)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<S: AsyncHandler, C: Clock + Sync> AsyncHandler for RateLimitedServer<S, C> {
+    fn handle_async<'a>(
+        &'a self,
+        method: &'a str,
+        params: Params,
+    ) -> BoxFuture<'a, Result<Value, Error>> {
+        match self.admit(method) {
+            Ok(()) => self.inner.handle_async(method, params),
+            Err(err) => Box::pin(future::ready(Err(err))),
+        }
+    }
+
+    fn handle_async_with_id<'a>(
+        &'a self,
+        id: &'a Id,
+        method: &'a str,
+        params: Params,
+    ) -> BoxFuture<'a, Result<Value, Error>> {
+        match self.admit(method) {
+            Ok(()) => self.inner.handle_async_with_id(id, method, params),
+            Err(err) => Box::pin(future::ready(Err(err))),
+        }
+    }
+
+    fn methods_async(&self) -> Vec<&'static str> {
+        self.inner.methods_async()
+    }
+}
@@ -0,0 +1,59 @@
+//! Blocking server loops for newline delimited jsonrpc. Requires the "transport" feature.
+//!
+//! Each line of input is handled as one jsonrpc request, each reply is written as one line of
+//! output. Requests that need no reply, like notifications, produce no output at all.
+//!
+//! With the "websocket" feature, [ws](ws/index.html) serves jsonrpc over websocket connections.
+//! With the "tower" feature, [tower](tower/index.html) serves jsonrpc over http.
+
+#[cfg(feature = "tower")]
+pub mod tower;
+#[cfg(feature = "websocket")]
+pub mod ws;
+
+use crate::Handler;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, ToSocketAddrs};
+
+/// Serve requests read from stdin, writing replies to stdout. Returns once stdin is closed.
+pub fn serve_stdio<H: ?Sized + Handler>(handler: &H) -> io::Result<()> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    serve(handler, stdin.lock(), stdout.lock())
+}
+
+/// Listen on addr, serving connections one at a time. Never returns unless binding to addr
+/// fails.
+///
+/// Connections are served until the client closes them. An io error on one connection closes
+/// that connection, the server moves on to the next one.
+pub fn serve_tcp<A: ToSocketAddrs, H: ?Sized + Handler>(addr: A, handler: &H) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    for stream in listener.incoming() {
+        let _ =
+            stream.and_then(|stream| serve(handler, BufReader::new(stream.try_clone()?), stream));
+    }
+    unreachable!("TcpListener::incoming never returns None")
+}
+
+/// Serve newline delimited requests read from input, writing replies to output. Returns once
+/// input reaches end of file. Blank lines are ignored.
+pub fn serve<H, R, W>(handler: &H, input: R, mut output: W) -> io::Result<()>
+where
+    H: ?Sized + Handler,
+    R: BufRead,
+    W: Write,
+{
+    for line in input.split(b'\n') {
+        let line = line?;
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        if let Some(mut reply) = handler.handle_bytes(&line) {
+            reply.push(b'\n');
+            output.write_all(&reply)?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
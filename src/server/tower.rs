@@ -0,0 +1,148 @@
+//! Jsonrpc over http as a tower Service, for hyper, axum and other tower based stacks. Requires
+//! the "tower" feature.
+//!
+//! The body of each http request is handled as one jsonrpc request, whatever the http method and
+//! path. Replies are sent with `Content-Type: application/json`. Requests that need no reply,
+//! like notifications, are answered with an empty body, with status 204 No Content unless
+//! configured otherwise. A reply that is a single error gets the status chosen by
+//! [http_status_for](../../fn.http_status_for.html), or by the mapping passed to
+//! [status_for](struct.TowerService.html#method.status_for). Batch replies always get 200 OK,
+//! each of their calls may have failed differently.
+//!
+//! ```no_run
+//! # #[easy_jsonrpc::rpc]
+//! # pub trait Adder: Send + Sync {
+//! #     fn wrapping_add(&self, a: isize, b: isize) -> isize;
+//! # }
+//! # struct AdderImpl;
+//! # impl Adder for AdderImpl {
+//! #     fn wrapping_add(&self, a: isize, b: isize) -> isize { a.wrapping_add(b) }
+//! # }
+//! use easy_jsonrpc::server::tower::TowerService;
+//! use std::sync::Arc;
+//!
+//! let handler: Arc<dyn Adder> = Arc::new(AdderImpl);
+//! let service = TowerService::new(handler);
+//! // e.g. axum::Router::new().route_service("/rpc", service)
+//! ```
+
+use crate::{http_status_for, Error, Handler};
+use bytes::Bytes;
+use http::{header, HeaderValue, Request, Response, StatusCode};
+use http_body::Body;
+use http_body_util::{BodyExt, Full};
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower_service::Service;
+
+/// A tower Service answering http requests carrying jsonrpc.
+///
+/// Requests are handled on the task polling the response future. The handler is called
+/// synchronously, so methods that block for long hold up the executor thread they run on.
+pub struct TowerService<H: ?Sized> {
+    handler: Arc<H>,
+    no_reply_status: StatusCode,
+    status_for: fn(&Error) -> u16,
+}
+
+impl<H: ?Sized + Handler> TowerService<H> {
+    /// Serve requests with handler.
+    pub fn new(handler: Arc<H>) -> TowerService<H> {
+        TowerService {
+            handler,
+            no_reply_status: StatusCode::NO_CONTENT,
+            status_for: http_status_for,
+        }
+    }
+
+    /// The status of responses to requests that need no reply, which have an empty body.
+    /// Defaults to 204 No Content, some clients expect 200 OK instead.
+    pub fn no_reply_status(mut self, status: StatusCode) -> TowerService<H> {
+        self.no_reply_status = status;
+        self
+    }
+
+    /// The status of responses whose reply is a single error, in place of
+    /// [http_status_for](../../fn.http_status_for.html). Statuses outside of 100 to 999 are sent
+    /// as 500 Internal Server Error.
+    pub fn status_for(mut self, status_for: fn(&Error) -> u16) -> TowerService<H> {
+        self.status_for = status_for;
+        self
+    }
+}
+
+impl<H: ?Sized> Clone for TowerService<H> {
+    fn clone(&self) -> Self {
+        TowerService {
+            handler: self.handler.clone(),
+            no_reply_status: self.no_reply_status,
+            status_for: self.status_for,
+        }
+    }
+}
+
+impl<H, B> Service<Request<B>> for TowerService<H>
+where
+    H: ?Sized + Handler + Send + Sync + 'static,
+    B: Body + Send + 'static,
+    B::Data: Send,
+{
+    type Response = Response<Full<Bytes>>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let handler = self.handler.clone();
+        let no_reply_status = self.no_reply_status;
+        let status_for = self.status_for;
+        Box::pin(async move {
+            let body = match request.into_body().collect().await {
+                Ok(body) => body.to_bytes(),
+                // the body could not be read to the end, there is no request to answer
+                Err(_) => return Ok(empty(StatusCode::BAD_REQUEST)),
+            };
+            Ok(match handler.handle_bytes(&body) {
+                Some(reply) => {
+                    let status = reply_error(&reply).map_or(StatusCode::OK, |error| {
+                        StatusCode::from_u16(status_for(&error))
+                            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+                    });
+                    let mut response = Response::new(Full::new(Bytes::from(reply)));
+                    *response.status_mut() = status;
+                    response.headers_mut().insert(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/json"),
+                    );
+                    response
+                }
+                None => empty(no_reply_status),
+            })
+        })
+    }
+}
+
+// The error of a reply answering a single call with an error, None for successes and batches.
+fn reply_error(reply: &[u8]) -> Option<Error> {
+    #[derive(serde::Deserialize)]
+    struct Failure {
+        error: Option<Error>,
+    }
+
+    if reply.first() != Some(&b'{') {
+        return None;
+    }
+    serde_json::from_slice::<Failure>(reply).ok()?.error
+}
+
+fn empty(status: StatusCode) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::new()));
+    *response.status_mut() = status;
+    response
+}
@@ -0,0 +1,175 @@
+//! Jsonrpc over websocket. Requires the "websocket" feature.
+//!
+//! Each text or binary message received is handled as one jsonrpc request, each reply is sent
+//! back as one text message. Besides answering requests, the server may push notifications to
+//! the client at any time through a [Notifier](struct.Notifier.html). The items of the
+//! [subscriptions](../../struct.Subscription.html) returned by methods are pushed the same way,
+//! each subscription drained on a thread of its own.
+//!
+//! ```no_run
+//! # use easy_jsonrpc::{BoundNotification, Handler};
+//! # use easy_jsonrpc::server::ws;
+//! # use std::net::TcpListener;
+//! # fn serve(handler: &dyn Handler) -> std::io::Result<()> {
+//! let listener = TcpListener::bind("127.0.0.1:4444")?;
+//! let (stream, _) = listener.accept()?;
+//! let session = ws::accept(stream)?;
+//! let notifier = session.notifier();
+//! std::thread::spawn(move || {
+//!     // the session has ended once notify fails
+//!     while notifier.notify(&BoundNotification::new("tick", vec![])).is_ok() {
+//!         std::thread::sleep(std::time::Duration::from_secs(1));
+//!     }
+//! });
+//! session.run(handler)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{collect_subscriptions, BoundNotification, Handler};
+use std::fmt;
+use std::io;
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::time::Duration;
+use tungstenite::{Error, Message, WebSocket};
+
+// How long a session blocks waiting for a message before checking for pushed notifications.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Perform the websocket handshake on a freshly accepted connection.
+pub fn accept(stream: TcpStream) -> io::Result<WsSession> {
+    let socket = tungstenite::accept(stream).map_err(|err| match err {
+        tungstenite::HandshakeError::Failure(err) => into_io(err),
+        tungstenite::HandshakeError::Interrupted(_) => io::ErrorKind::WouldBlock.into(),
+    })?;
+    WsSession::new(socket)
+}
+
+// Websocket protocol violations are reported as InvalidData.
+fn into_io(err: Error) -> io::Error {
+    match err {
+        Error::Io(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+/// A websocket connection serving jsonrpc requests.
+pub struct WsSession {
+    socket: WebSocket<TcpStream>,
+    sender: Sender<String>,
+    receiver: Receiver<String>,
+}
+
+impl WsSession {
+    /// Serve jsonrpc on a websocket which has already completed its handshake.
+    pub fn new(socket: WebSocket<TcpStream>) -> io::Result<WsSession> {
+        socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+        let (sender, receiver) = mpsc::channel();
+        Ok(WsSession {
+            socket,
+            sender,
+            receiver,
+        })
+    }
+
+    /// A handle for pushing notifications to the client. Notifiers may be cloned and sent to
+    /// other threads, notifications are delivered while the session is running.
+    pub fn notifier(&self) -> Notifier {
+        Notifier {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Serve requests until the client closes the connection. Websocket protocol errors are
+    /// reported as io errors of kind InvalidData.
+    ///
+    /// Pings are answered and a close from the client is acknowledged, both happen
+    /// automatically. Pushed notifications are sent in the order they were pushed.
+    ///
+    /// The subscriptions returned by methods are drained until they end, or until they produce
+    /// an item after the session has ended. Subscriptions blocking while waiting for items keep
+    /// their thread until then.
+    pub fn run<H: ?Sized + Handler>(mut self, handler: &H) -> io::Result<()> {
+        loop {
+            self.push_notifications()?;
+            let message = match self.socket.read() {
+                Ok(message) => message,
+                Err(Error::ConnectionClosed) | Err(Error::AlreadyClosed) => return Ok(()),
+                Err(Error::Io(ref err))
+                    if err.kind() == io::ErrorKind::WouldBlock
+                        || err.kind() == io::ErrorKind::TimedOut =>
+                {
+                    // nothing received yet, flush any pending pong or close frames
+                    self.socket.flush().map_err(into_io)?;
+                    continue;
+                }
+                Err(err) => return Err(into_io(err)),
+            };
+            let (reply, subscriptions) = match message {
+                Message::Text(text) => {
+                    collect_subscriptions(|| handler.handle_bytes(text.as_bytes()))
+                }
+                Message::Binary(bytes) => collect_subscriptions(|| handler.handle_bytes(&bytes)),
+                Message::Ping(_) | Message::Pong(_) | Message::Close(_) | Message::Frame(_) => {
+                    continue
+                }
+            };
+            if let Some(reply) = reply {
+                // handle_bytes always produces serialized json, which is valid utf8
+                let reply = String::from_utf8(reply).expect("reply is not valid utf8");
+                self.socket.send(Message::Text(reply)).map_err(into_io)?;
+            }
+            // items are pushed once the reply carrying the id of their subscription is sent
+            for subscription in subscriptions {
+                let notifier = self.notifier();
+                std::thread::spawn(move || {
+                    for notification in subscription {
+                        if notifier.notify(&notification).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    fn push_notifications(&mut self) -> io::Result<()> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(notification) => self
+                    .socket
+                    .send(Message::Text(notification))
+                    .map_err(into_io)?,
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Pushes notifications to the client of a [WsSession](struct.WsSession.html).
+#[derive(Clone)]
+pub struct Notifier {
+    sender: Sender<String>,
+}
+
+impl Notifier {
+    /// Queue a notification to be sent to the client. Fails once the session has ended.
+    pub fn notify(&self, notification: &BoundNotification) -> Result<(), SessionClosed> {
+        self.sender
+            .send(notification.as_request().to_string())
+            .map_err(|_| SessionClosed)
+    }
+}
+
+/// The session a [Notifier](struct.Notifier.html) belongs to has ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionClosed;
+
+impl fmt::Display for SessionClosed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "websocket session is closed")
+    }
+}
+
+impl std::error::Error for SessionClosed {}
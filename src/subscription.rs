@@ -0,0 +1,195 @@
+// Results pushed to the client as notifications after the call returns, see Subscription.
+
+use crate::BoundNotification;
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{self, Serialize, Serializer};
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Identifies a [Subscription](struct.Subscription.html). It is the result of the call that
+/// created the subscription, and is sent along with each of its items.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[serde(transparent)]
+pub struct SubscriptionId(pub u64);
+
+impl fmt::Display for SubscriptionId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A stream of items pushed to the client as notifications, for pub/sub apis. A method returning
+/// a Subscription answers the call with the id of the subscription, then each item is sent as a
+/// notification of the method given to [new](#method.new), with the named parameters
+/// `{"subscription": id, "result": item}`.
+///
+/// The items are pushed by transports that can send notifications at any time, like
+/// `server::ws`, which drains each subscription on a thread of its own until it ends or the
+/// session closes. Transports collect the subscriptions returned while handling a request with
+/// [collect_subscriptions](fn.collect_subscriptions.html). Anywhere else, the call is answered
+/// with an error of code [SERIALIZATION_ERROR](constant.SERIALIZATION_ERROR.html), and the items
+/// are dropped. So are the items of a subscription returned to a notification, which gets no id
+/// to refer to it by.
+///
+/// ```
+/// use easy_jsonrpc::Subscription;
+///
+/// #[easy_jsonrpc::rpc]
+/// pub trait Ticker {
+///     fn subscribe(&self, count: u64) -> Subscription<u64>;
+/// }
+///
+/// struct TickerImpl;
+///
+/// impl Ticker for TickerImpl {
+///     fn subscribe(&self, count: u64) -> Subscription<u64> {
+///         Subscription::new("tick", 0..count)
+///     }
+/// }
+/// ```
+///
+/// Items are serialized as they are pushed, the subscription ends at the first item that fails
+/// to serialize. Client helpers deserialize the result into a subscription that holds only the
+/// id, the items arrive as notifications.
+pub struct Subscription<T> {
+    id: SubscriptionId,
+    method: &'static str,
+    items: Cell<Option<Box<dyn Iterator<Item = T> + Send>>>,
+}
+
+impl<T> Subscription<T> {
+    /// Push each of items as a notification of method, under a new id.
+    pub fn new<I>(method: &'static str, items: I) -> Subscription<T>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: Send + 'static,
+    {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Subscription {
+            id: SubscriptionId(NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+            method,
+            items: Cell::new(Some(Box::new(items.into_iter()))),
+        }
+    }
+
+    /// The id the call is answered with.
+    pub fn id(&self) -> SubscriptionId {
+        self.id
+    }
+}
+
+impl<T: Serialize + 'static> Serialize for Subscription<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let items = match self.items.take() {
+            Some(items) => items,
+            None => return Err(ser::Error::custom("subscription was serialized already")),
+        };
+        let pending = PendingSubscription {
+            id: self.id,
+            method: self.method,
+            items: Box::new(items.map_while(|item| serde_json::to_value(item).ok())),
+        };
+        let collected = COLLECTED.with(|collected| match &mut *collected.borrow_mut() {
+            Some(subscriptions) => {
+                subscriptions.push(pending);
+                true
+            }
+            None => false,
+        });
+        if !collected {
+            return Err(ser::Error::custom(
+                "subscriptions are only served by transports pushing notifications",
+            ));
+        }
+        self.id.serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Subscription<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Subscription {
+            id: SubscriptionId::deserialize(deserializer)?,
+            method: "",
+            items: Cell::new(None),
+        })
+    }
+}
+
+impl<T> fmt::Debug for Subscription<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .field("method", &self.method)
+            .finish()
+    }
+}
+
+/// A [Subscription](struct.Subscription.html) returned by a method, iterating over the
+/// notifications carrying its items. See [collect_subscriptions](fn.collect_subscriptions.html).
+pub struct PendingSubscription {
+    id: SubscriptionId,
+    method: &'static str,
+    items: Box<dyn Iterator<Item = Value> + Send>,
+}
+
+impl PendingSubscription {
+    /// The id of the subscription.
+    pub fn id(&self) -> SubscriptionId {
+        self.id
+    }
+}
+
+impl Iterator for PendingSubscription {
+    type Item = BoundNotification;
+
+    fn next(&mut self) -> Option<BoundNotification> {
+        let item = self.items.next()?;
+        Some(BoundNotification::new_named(
+            self.method,
+            &["subscription", "result"],
+            vec![Value::from(self.id.0), item],
+        ))
+    }
+}
+
+impl fmt::Debug for PendingSubscription {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PendingSubscription")
+            .field("id", &self.id)
+            .field("method", &self.method)
+            .finish()
+    }
+}
+
+thread_local! {
+    // the subscriptions serialized on this thread within collect_subscriptions
+    static COLLECTED: RefCell<Option<Vec<PendingSubscription>>> = const { RefCell::new(None) };
+}
+
+/// Run handle, collecting the [subscriptions](struct.Subscription.html) returned by the methods
+/// it calls. For transports pushing the notifications of subscriptions, which should send the
+/// reply to the request first, so clients learn the ids before the items start arriving.
+///
+/// Only the subscriptions serialized on the calling thread are collected, handle must not hand
+/// calls over to other threads, as `Handler::handle_parsed_parallel` does.
+pub fn collect_subscriptions<R>(handle: impl FnOnce() -> R) -> (R, Vec<PendingSubscription>) {
+    // restores the collection of an enclosing call, even if handle panics
+    struct Restore(Option<Vec<PendingSubscription>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let enclosing = self.0.take();
+            COLLECTED.with(|collected| *collected.borrow_mut() = enclosing);
+        }
+    }
+
+    let restore = Restore(COLLECTED.with(|collected| collected.replace(Some(Vec::new()))));
+    let ret = handle();
+    let subscriptions = COLLECTED.with(|collected| collected.borrow_mut().take());
+    drop(restore);
+    (ret, subscriptions.unwrap_or_default())
+}
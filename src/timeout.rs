@@ -0,0 +1,187 @@
+// Bounds the time async handlers may take for each call. Requires the "async" feature.
+
+use crate::{AsyncHandler, Id, Params, Value, TIMEOUT_ERROR};
+use futures::future::{self, BoxFuture, Either};
+use jsonrpc_core::{Error, ErrorCode};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::OnceLock;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// Wraps an [AsyncHandler](trait.AsyncHandler.html), answering calls that take longer than a
+/// timeout with an error of code [TIMEOUT_ERROR](constant.TIMEOUT_ERROR.html). Every call is
+/// bounded, the calls of a batch and notifications included.
+///
+/// A call that times out is cancelled by dropping its future, so whatever the handler was doing
+/// stops at its next await point. Handlers must be cancel-safe for this to leave them in a
+/// consistent state. Blocking code that never awaits can't be interrupted, only its reply is
+/// replaced by the timeout error once it returns.
+///
+/// ```
+/// # #[easy_jsonrpc::rpc_async]
+/// # pub trait Adder: Sync {
+/// #     async fn wrapping_add(&self, a: isize, b: isize) -> isize;
+/// # }
+/// # struct AdderImpl;
+/// # #[easy_jsonrpc::async_trait]
+/// # impl Adder for AdderImpl {
+/// #     async fn wrapping_add(&self, a: isize, b: isize) -> isize { a.wrapping_add(b) }
+/// # }
+/// use easy_jsonrpc::TimeoutServer;
+/// use std::time::Duration;
+///
+/// let server = TimeoutServer::new(&AdderImpl as &dyn Adder, Duration::from_secs(5));
+/// ```
+pub struct TimeoutServer<S> {
+    inner: S,
+    timeout: Duration,
+}
+
+impl<S: AsyncHandler> TimeoutServer<S> {
+    /// Bound each call handled by inner to timeout.
+    pub fn new(inner: S, timeout: Duration) -> TimeoutServer<S> {
+        TimeoutServer { inner, timeout }
+    }
+
+    /// The wrapped handler.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: AsyncHandler> AsyncHandler for TimeoutServer<S> {
+    fn handle_async<'a>(
+        &'a self,
+        method: &'a str,
+        params: Params,
+    ) -> BoxFuture<'a, Result<Value, Error>> {
+        bounded(
+            self.timeout,
+            method,
+            self.inner.handle_async(method, params),
+        )
+    }
+
+    fn handle_async_with_id<'a>(
+        &'a self,
+        id: &'a Id,
+        method: &'a str,
+        params: Params,
+    ) -> BoxFuture<'a, Result<Value, Error>> {
+        bounded(
+            self.timeout,
+            method,
+            self.inner.handle_async_with_id(id, method, params),
+        )
+    }
+
+    fn methods_async(&self) -> Vec<&'static str> {
+        self.inner.methods_async()
+    }
+}
+
+// race call against the timeout, dropping call if the timeout wins
+fn bounded<'a>(
+    timeout: Duration,
+    method: &'a str,
+    call: BoxFuture<'a, Result<Value, Error>>,
+) -> BoxFuture<'a, Result<Value, Error>> {
+    let delay = Delay::new(timeout);
+    Box::pin(async move {
+        match future::select(call, delay).await {
+            Either::Left((ret, _)) => ret,
+            Either::Right(((), _)) => Err(Error {
+                code: ErrorCode::ServerError(TIMEOUT_ERROR),
+                message: "Timeout".into(),
+                data: Some(Value::String(format!(
+                    "method '{}' did not complete within {:?}",
+                    method, timeout
+                ))),
+            }),
+        }
+    })
+}
+
+// A future completing once its deadline has passed. Works with any executor, pending delays are
+// woken by a single timer thread shared by all of them.
+struct Delay {
+    deadline: Instant,
+    registered: Option<Waker>, // the waker last handed to the timer thread
+}
+
+impl Delay {
+    fn new(timeout: Duration) -> Delay {
+        Delay {
+            deadline: Instant::now() + timeout,
+            registered: None,
+        }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        let registered = match &self.registered {
+            Some(waker) => waker.will_wake(cx.waker()),
+            None => false,
+        };
+        if !registered {
+            wake_at(self.deadline, cx.waker().clone());
+            self.registered = Some(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+// Have the timer thread wake waker once deadline has passed, starting the thread if needed.
+fn wake_at(deadline: Instant, waker: Waker) {
+    static TIMER: OnceLock<Sender<(Instant, Waker)>> = OnceLock::new();
+    let timer = TIMER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<(Instant, Waker)>();
+        std::thread::spawn(move || {
+            // deadlines ordered earliest first, along with the key of their waker
+            let mut deadlines = BinaryHeap::new();
+            let mut wakers = HashMap::new();
+            let mut next_key = 0u64;
+            loop {
+                let received = match deadlines.peek() {
+                    Some(Reverse((deadline, _))) => {
+                        let timeout = Instant::saturating_duration_since(deadline, Instant::now());
+                        receiver.recv_timeout(timeout)
+                    }
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                match received {
+                    Ok((deadline, waker)) => {
+                        deadlines.push(Reverse((deadline, next_key)));
+                        wakers.insert(next_key, waker);
+                        next_key += 1;
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+                let now = Instant::now();
+                while let Some(Reverse((deadline, key))) = deadlines.peek().cloned() {
+                    if deadline > now {
+                        break;
+                    }
+                    deadlines.pop();
+                    if let Some(waker) = wakers.remove(&key) {
+                        waker.wake();
+                    }
+                }
+            }
+        });
+        sender
+    });
+    // the timer thread runs for as long as the process, so sending never fails
+    let _ = timer.send((deadline, waker));
+}